@@ -0,0 +1,93 @@
+use core::fmt::Debug;
+
+use crate::{KdValue, Scalar};
+
+/// A plain axis-aligned bounding box, usable both as a value and to describe
+/// query regions.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Aabb<P> {
+    pub min_x: P,
+    pub max_x: P,
+    pub min_y: P,
+    pub max_y: P,
+}
+
+impl<P: PartialOrd + Clone> Aabb<P> {
+    pub fn new(min_x: P, max_x: P, min_y: P, max_y: P) -> Self {
+        Self {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+
+    pub fn of<Value: KdValue<Position = P>>(value: &Value) -> Self {
+        Self::new(value.min_x(), value.max_x(), value.min_y(), value.max_y())
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    pub fn contains_point(&self, x: &P, y: &P) -> bool {
+        self.min_x <= *x && *x <= self.max_x && self.min_y <= *y && *y <= self.max_y
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        let min = |a: &P, b: &P| if b < a { b.clone() } else { a.clone() };
+        let max = |a: &P, b: &P| if b > a { b.clone() } else { a.clone() };
+        Self::new(
+            min(&self.min_x, &other.min_x),
+            max(&self.max_x, &other.max_x),
+            min(&self.min_y, &other.min_y),
+            max(&self.max_y, &other.max_y),
+        )
+    }
+}
+
+impl<P: Scalar> Aabb<P> {
+    /// Grows the box by `margin` on every side.
+    pub fn inflate(&self, margin: P) -> Self {
+        Self::new(
+            self.min_x - margin,
+            self.max_x + margin,
+            self.min_y - margin,
+            self.max_y + margin,
+        )
+    }
+
+    /// Whether the boxes overlap, or are at most `tolerance` apart along both axes.
+    pub fn overlaps_within(&self, other: &Self, tolerance: P) -> bool {
+        self.inflate(tolerance).overlaps(other)
+    }
+}
+
+impl<P: PartialOrd + Debug + Default + Clone> KdValue for Aabb<P> {
+    type Position = P;
+
+    fn min_x(&self) -> Self::Position {
+        self.min_x.clone()
+    }
+
+    fn min_y(&self) -> Self::Position {
+        self.min_y.clone()
+    }
+
+    fn max_x(&self) -> Self::Position {
+        self.max_x.clone()
+    }
+
+    fn max_y(&self) -> Self::Position {
+        self.max_y.clone()
+    }
+}
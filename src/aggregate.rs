@@ -0,0 +1,922 @@
+use alloc::{boxed::Box, collections::BinaryHeap, vec, vec::Vec};
+use core::{cmp::Ordering, fmt::Debug, mem};
+
+use crate::{cmp_position, scalar::floor, split_due, split_index, Aabb, KdValue, Scalar};
+
+/// A summary of values that can be merged, cached on every node of an
+/// `AggregateKdTree`. `combine` must be associative, with `empty` as identity.
+pub trait Monoid<Value> {
+    type Summary: Clone + Debug;
+
+    fn empty(&self) -> Self::Summary;
+
+    fn summarize(&self, value: &Value) -> Self::Summary;
+
+    fn combine(&self, a: &Self::Summary, b: &Self::Summary) -> Self::Summary;
+}
+
+/// Counts the values.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Count;
+
+impl<Value> Monoid<Value> for Count {
+    type Summary = usize;
+
+    fn empty(&self) -> usize {
+        0
+    }
+
+    fn summarize(&self, _: &Value) -> usize {
+        1
+    }
+
+    fn combine(&self, a: &usize, b: &usize) -> usize {
+        a + b
+    }
+}
+
+/// The highest priority given by the closure, `f64::NEG_INFINITY` when there are
+/// no values.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MaxPriority<F>(pub F);
+
+impl<Value, F: Fn(&Value) -> f64> Monoid<Value> for MaxPriority<F> {
+    type Summary = f64;
+
+    fn empty(&self) -> f64 {
+        f64::NEG_INFINITY
+    }
+
+    fn summarize(&self, value: &Value) -> f64 {
+        (self.0)(value)
+    }
+
+    fn combine(&self, a: &f64, b: &f64) -> f64 {
+        a.max(*b)
+    }
+}
+
+/// A tree caching the bounds of every subtree along with a user-defined summary
+/// of its values, so that the summary of the values overlapping a region is
+/// computed from whole subtrees inside of it, only descending at its boundary.
+///
+/// Summaries are kept up to date on insertion and removal.
+#[derive(Debug)]
+pub struct AggregateKdTree<Value: KdValue, M: Monoid<Value>, const ISLAND_SIZE: usize> {
+    monoid: M,
+    root: Subtree<Value, M::Summary>,
+}
+
+#[derive(Debug)]
+struct Subtree<Value: KdValue, S> {
+    //`None` when the subtree is empty
+    bounds: Option<Aabb<Value::Position>>,
+    len: usize,
+    summary: S,
+    node: Node<Value, S>,
+}
+
+#[derive(Debug)]
+enum Node<Value: KdValue, S> {
+    Leaf(Vec<Value>),
+    Split(Box<Split<Value, S>>),
+}
+
+#[derive(Debug)]
+struct Split<Value: KdValue, S> {
+    vertical: bool,
+    median: Value::Position,
+    left_max: Value::Position,
+    left: Subtree<Value, S>,
+    right: Subtree<Value, S>,
+}
+
+impl<Value: KdValue, M: Monoid<Value> + Default, const ISLAND_SIZE: usize> Default
+    for AggregateKdTree<Value, M, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<Value: KdValue, M: Monoid<Value>, const ISLAND_SIZE: usize>
+    AggregateKdTree<Value, M, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    pub fn new(monoid: M) -> Self {
+        let root = Subtree::leaf(Vec::new(), &monoid);
+        Self { monoid, root }
+    }
+
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.len == 0
+    }
+
+    /// The summary of every value of the tree.
+    pub fn summary(&self) -> &M::Summary {
+        &self.root.summary
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.root
+            .insert::<M, ISLAND_SIZE>(value, false, &self.monoid)
+    }
+
+    /// Removes one value equal to `value`, returning whether one was found.
+    pub fn remove_one(&mut self, value: &Value) -> bool {
+        self.root.remove_one(value, &self.monoid)
+    }
+
+    /// The summary of the values overlapping the rectangle.
+    pub fn aggregate_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> M::Summary {
+        let rect = Aabb::new(min_x, max_x, min_y, max_y);
+        self.root.aggregate(&rect, &self.monoid)
+    }
+
+    /// The number of values overlapping the rectangle, adding up the lengths of
+    /// the subtrees inside of it.
+    pub fn count_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> usize {
+        let rect = Aabb::new(min_x, max_x, min_y, max_y);
+        self.root.count(&rect)
+    }
+
+    /// The smallest rectangle enclosing the values overlapping the rectangle, or
+    /// `None` if there are none. Subtrees inside of it only contribute their bounds.
+    pub fn bounds_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Option<Aabb<Value::Position>> {
+        let rect = Aabb::new(min_x, max_x, min_y, max_y);
+        let mut bounds = None;
+        self.root.bounds_in(&rect, &mut bounds);
+        bounds
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> AggregateRectQuery<'_, Value, M::Summary> {
+        AggregateRectQuery {
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+            queue: vec![&self.root],
+            leaf: [].iter(),
+        }
+    }
+}
+
+impl<Value: KdValue, M: Monoid<Value>, const ISLAND_SIZE: usize>
+    AggregateKdTree<Value, M, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Counts the values overlapping the rectangle in `bins` equal slices of it
+    /// along x, by the x of their center. Centers outside of the rectangle count in
+    /// the closest slice.
+    ///
+    /// Subtrees inside the rectangle and spanning a single slice are counted
+    /// without descending into them.
+    pub fn histogram_x(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        bins: usize,
+    ) -> Vec<usize> {
+        self.histogram(Aabb::new(min_x, max_x, min_y, max_y), false, bins)
+    }
+
+    /// Like `histogram_x`, along y.
+    pub fn histogram_y(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        bins: usize,
+    ) -> Vec<usize> {
+        self.histogram(Aabb::new(min_x, max_x, min_y, max_y), true, bins)
+    }
+
+    fn histogram(&self, rect: Aabb<Value::Position>, vertical: bool, bins: usize) -> Vec<usize> {
+        let mut counts = vec![0; bins];
+        if bins > 0 {
+            let (low, high) = if vertical {
+                (rect.min_y.to_f64(), rect.max_y.to_f64())
+            } else {
+                (rect.min_x.to_f64(), rect.max_x.to_f64())
+            };
+            let bin = |center: f64| {
+                let bin = floor((center - low) / (high - low) * bins as f64);
+                //also catches the NaN of an empty range
+                if bin >= 0. {
+                    (bin as usize).min(bins - 1)
+                } else {
+                    0
+                }
+            };
+            self.root.histogram(&rect, vertical, &bin, &mut counts);
+        }
+        counts
+    }
+
+    /// The `q`-th quantile of the min x of the values overlapping the rectangle,
+    /// `q` going from 0 for the smallest to 1 for the largest, or `None` if there
+    /// are no such values.
+    ///
+    /// Bisects over the positions, counting the values below each candidate from
+    /// the lengths of the subtrees entirely below it.
+    pub fn quantile_x(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        q: f64,
+    ) -> Option<Value::Position> {
+        self.quantile(Aabb::new(min_x, max_x, min_y, max_y), false, q)
+    }
+
+    /// Like `quantile_x`, for the min y.
+    pub fn quantile_y(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        q: f64,
+    ) -> Option<Value::Position> {
+        self.quantile(Aabb::new(min_x, max_x, min_y, max_y), true, q)
+    }
+
+    fn quantile(
+        &self,
+        rect: Aabb<Value::Position>,
+        vertical: bool,
+        q: f64,
+    ) -> Option<Value::Position> {
+        let len = self.root.count(&rect);
+        let bounds = self.root.bounds.as_ref()?;
+        if len == 0 {
+            return None;
+        }
+        let rank = floor(q.clamp(0., 1.) * (len - 1) as f64) as usize;
+        //the smallest position with more than `rank` values at or below it
+        let (min, max) = bounds_along(bounds, vertical);
+        let (mut low, mut high) = (ordered(min.to_f64()), ordered(max.to_f64()));
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.root.count_at_most(&rect, vertical, unordered(middle)) > rank {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        Some(Value::Position::from_f64(unordered(low)))
+    }
+}
+
+impl<Value: KdValue, S> Subtree<Value, S>
+where
+    Value::Position: Scalar,
+{
+    fn histogram(
+        &self,
+        rect: &Aabb<Value::Position>,
+        vertical: bool,
+        bin: &impl Fn(f64) -> usize,
+        counts: &mut [usize],
+    ) {
+        let Some(bounds) = &self.bounds else {
+            return;
+        };
+        if !bounds.overlaps(rect) {
+            return;
+        }
+        let (min, max) = bounds_along(bounds, vertical);
+        let (first, last) = (bin(min.to_f64()), bin(max.to_f64()));
+        if first == last && contains(rect, bounds) {
+            counts[first] += self.len;
+            return;
+        }
+        match &self.node {
+            Node::Leaf(values) => {
+                for value in values {
+                    if Aabb::of(value).overlaps(rect) {
+                        let (min, max) = bounds_along(value, vertical);
+                        counts[bin((min.to_f64() + max.to_f64()) / 2.)] += 1;
+                    }
+                }
+            }
+            Node::Split(split) => {
+                split.left.histogram(rect, vertical, bin, counts);
+                split.right.histogram(rect, vertical, bin, counts);
+            }
+        }
+    }
+
+    //the number of values overlapping the rectangle with a min along the axis
+    //at most `at`
+    fn count_at_most(&self, rect: &Aabb<Value::Position>, vertical: bool, at: f64) -> usize {
+        let Some(bounds) = &self.bounds else {
+            return 0;
+        };
+        let (min, max) = bounds_along(bounds, vertical);
+        if !bounds.overlaps(rect) || min.to_f64() > at {
+            return 0;
+        }
+        if max.to_f64() <= at && contains(rect, bounds) {
+            return self.len;
+        }
+        match &self.node {
+            Node::Leaf(values) => values
+                .iter()
+                .filter(|value| {
+                    Aabb::of(*value).overlaps(rect)
+                        && bounds_along(*value, vertical).0.to_f64() <= at
+                })
+                .count(),
+            Node::Split(split) => {
+                let left = split.left.count_at_most(rect, vertical, at);
+                //the right values all have a min of at least the median
+                if split.vertical == vertical && at < split.median.to_f64() {
+                    left
+                } else {
+                    left + split.right.count_at_most(rect, vertical, at)
+                }
+            }
+        }
+    }
+}
+
+//maps floats to integers in the same order
+fn ordered(value: f64) -> u64 {
+    let bits = value.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    }
+}
+
+fn unordered(key: u64) -> f64 {
+    f64::from_bits(if key >> 63 == 1 {
+        key & !(1 << 63)
+    } else {
+        !key
+    })
+}
+
+impl<Value: KdValue, S: Clone> Subtree<Value, S>
+where
+    Value::Position: Clone,
+{
+    fn leaf<M: Monoid<Value, Summary = S>>(values: Vec<Value>, monoid: &M) -> Self {
+        let mut subtree = Self {
+            bounds: None,
+            len: 0,
+            summary: monoid.empty(),
+            node: Node::Leaf(values),
+        };
+        subtree.refresh(monoid);
+        subtree
+    }
+
+    //recomputes the bounds, length and summary from the values or children
+    fn refresh<M: Monoid<Value, Summary = S>>(&mut self, monoid: &M) {
+        let (bounds, summary) = match &self.node {
+            Node::Leaf(values) => {
+                values
+                    .iter()
+                    .fold((None, monoid.empty()), |(bounds, summary), value| {
+                        (
+                            union(bounds, Aabb::of(value)),
+                            monoid.combine(&summary, &monoid.summarize(value)),
+                        )
+                    })
+            }
+            Node::Split(split) => (
+                match (&split.left.bounds, &split.right.bounds) {
+                    (Some(left), Some(right)) => Some(left.union(right)),
+                    (left, right) => left.clone().or_else(|| right.clone()),
+                },
+                monoid.combine(&split.left.summary, &split.right.summary),
+            ),
+        };
+        self.len = match &self.node {
+            Node::Leaf(values) => values.len(),
+            Node::Split(split) => split.left.len + split.right.len,
+        };
+        self.bounds = bounds;
+        self.summary = summary;
+    }
+
+    fn insert<M: Monoid<Value, Summary = S>, const ISLAND_SIZE: usize>(
+        &mut self,
+        value: Value,
+        vertical: bool,
+        monoid: &M,
+    ) {
+        self.bounds = union(self.bounds.take(), Aabb::of(&value));
+        self.len += 1;
+        self.summary = monoid.combine(&self.summary, &monoid.summarize(&value));
+        match &mut self.node {
+            Node::Leaf(values) => {
+                values.push(value);
+                //leaves whose minimums are all equal can not be split, and are
+                //retried each time their length doubles
+                if split_due(values.len(), ISLAND_SIZE) {
+                    self.split(vertical, monoid);
+                }
+            }
+            Node::Split(split) => {
+                let (min, max) = bounds_along(&value, split.vertical);
+                if min < split.median {
+                    if max > split.left_max {
+                        split.left_max = max;
+                    }
+                    split
+                        .left
+                        .insert::<M, ISLAND_SIZE>(value, !vertical, monoid)
+                } else {
+                    split
+                        .right
+                        .insert::<M, ISLAND_SIZE>(value, !vertical, monoid)
+                }
+            }
+        }
+    }
+
+    fn split<M: Monoid<Value, Summary = S>>(&mut self, vertical: bool, monoid: &M) {
+        let Node::Leaf(values) = &mut self.node else {
+            return;
+        };
+        values.sort_unstable_by(|a, b| {
+            cmp_position(&bounds_along(a, vertical).0, &bounds_along(b, vertical).0)
+        });
+        let Some(middle) = split_index(values, |value| bounds_along(value, vertical).0) else {
+            return;
+        };
+        let median = bounds_along(&values[middle], vertical).0;
+        let right = values.split_off(middle);
+        let left = mem::take(values);
+        let left_max = left
+            .iter()
+            .map(|value| bounds_along(value, vertical).1)
+            .reduce(|a, b| if b > a { b } else { a })
+            .unwrap();
+        self.node = Node::Split(Box::new(Split {
+            vertical,
+            median,
+            left_max,
+            left: Subtree::leaf(left, monoid),
+            right: Subtree::leaf(right, monoid),
+        }));
+    }
+
+    fn remove_one<M: Monoid<Value, Summary = S>>(&mut self, value: &Value, monoid: &M) -> bool {
+        let removed = match &mut self.node {
+            Node::Leaf(values) => match values.iter().position(|other| other == value) {
+                Some(index) => {
+                    values.swap_remove(index);
+                    true
+                }
+                None => false,
+            },
+            Node::Split(split) => {
+                if bounds_along(value, split.vertical).0 < split.median {
+                    split.left.remove_one(value, monoid)
+                } else {
+                    split.right.remove_one(value, monoid)
+                }
+            }
+        };
+        if removed {
+            self.refresh(monoid);
+        }
+        removed
+    }
+
+    fn count(&self, rect: &Aabb<Value::Position>) -> usize {
+        match &self.bounds {
+            Some(bounds) if contains(rect, bounds) => self.len,
+            Some(bounds) if bounds.overlaps(rect) => match &self.node {
+                Node::Leaf(values) => values
+                    .iter()
+                    .filter(|value| Aabb::of(*value).overlaps(rect))
+                    .count(),
+                Node::Split(split) => split.left.count(rect) + split.right.count(rect),
+            },
+            _ => 0,
+        }
+    }
+
+    fn bounds_in(&self, rect: &Aabb<Value::Position>, found: &mut Option<Aabb<Value::Position>>) {
+        match &self.bounds {
+            Some(bounds) if contains(rect, bounds) => *found = union(found.take(), bounds.clone()),
+            Some(bounds) if bounds.overlaps(rect) => match &self.node {
+                Node::Leaf(values) => {
+                    for value in values {
+                        let bounds = Aabb::of(value);
+                        if bounds.overlaps(rect) {
+                            *found = union(found.take(), bounds);
+                        }
+                    }
+                }
+                Node::Split(split) => {
+                    split.left.bounds_in(rect, found);
+                    split.right.bounds_in(rect, found);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn aggregate<M: Monoid<Value, Summary = S>>(
+        &self,
+        rect: &Aabb<Value::Position>,
+        monoid: &M,
+    ) -> S {
+        let Some(bounds) = &self.bounds else {
+            return monoid.empty();
+        };
+        if !bounds.overlaps(rect) {
+            return monoid.empty();
+        }
+        if contains(rect, bounds) {
+            return self.summary.clone();
+        }
+        match &self.node {
+            Node::Leaf(values) => values
+                .iter()
+                .filter(|value| Aabb::of(*value).overlaps(rect))
+                .fold(monoid.empty(), |summary, value| {
+                    monoid.combine(&summary, &monoid.summarize(value))
+                }),
+            Node::Split(split) => monoid.combine(
+                &split.left.aggregate(rect, monoid),
+                &split.right.aggregate(rect, monoid),
+            ),
+        }
+    }
+}
+
+impl<Value: KdValue, F: Fn(&Value) -> f64, const ISLAND_SIZE: usize>
+    AggregateKdTree<Value, MaxPriority<F>, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// The values overlapping the rectangle along with their priority, from the
+    /// highest.
+    ///
+    /// Subtrees are opened by their cached max priority, so taking only the first
+    /// results skips the subtrees whose priorities are all lower.
+    pub fn query_rect_by_priority(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> PriorityRectQuery<'_, Value, F> {
+        let mut heap = BinaryHeap::new();
+        heap.push(Ranked {
+            priority: self.root.summary,
+            item: RankedItem::Tree(&self.root),
+        });
+        PriorityRectQuery {
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+            priority: &self.monoid.0,
+            heap,
+        }
+    }
+}
+
+fn bounds_along<Value: KdValue>(
+    value: &Value,
+    vertical: bool,
+) -> (Value::Position, Value::Position) {
+    if vertical {
+        (value.min_y(), value.max_y())
+    } else {
+        (value.min_x(), value.max_x())
+    }
+}
+
+fn union<P: PartialOrd + Clone>(bounds: Option<Aabb<P>>, other: Aabb<P>) -> Option<Aabb<P>> {
+    Some(match bounds {
+        Some(bounds) => bounds.union(&other),
+        None => other,
+    })
+}
+
+fn contains<P: PartialOrd>(outer: &Aabb<P>, inner: &Aabb<P>) -> bool {
+    outer.min_x <= inner.min_x
+        && inner.max_x <= outer.max_x
+        && outer.min_y <= inner.min_y
+        && inner.max_y <= outer.max_y
+}
+
+pub struct AggregateRectQuery<'a, Value: KdValue, S> {
+    rect: Aabb<Value::Position>,
+    queue: Vec<&'a Subtree<Value, S>>,
+    leaf: core::slice::Iter<'a, Value>,
+}
+
+impl<'a, Value: KdValue, S> Iterator for AggregateRectQuery<'a, Value, S>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        loop {
+            for value in self.leaf.by_ref() {
+                if Aabb::of(value).overlaps(&self.rect) {
+                    return Some(value);
+                }
+            }
+            let subtree = self.queue.pop()?;
+            if !subtree
+                .bounds
+                .as_ref()
+                .is_some_and(|bounds| bounds.overlaps(&self.rect))
+            {
+                continue;
+            }
+            match &subtree.node {
+                Node::Leaf(values) => self.leaf = values.iter(),
+                Node::Split(split) => {
+                    self.queue.push(&split.right);
+                    self.queue.push(&split.left);
+                }
+            }
+        }
+    }
+}
+
+pub struct PriorityRectQuery<'a, Value: KdValue, F> {
+    rect: Aabb<Value::Position>,
+    priority: &'a F,
+    heap: BinaryHeap<Ranked<'a, Value>>,
+}
+
+//a subtree ranked by its max priority, or a value by its own
+struct Ranked<'a, Value: KdValue> {
+    priority: f64,
+    item: RankedItem<'a, Value>,
+}
+
+enum RankedItem<'a, Value: KdValue> {
+    Tree(&'a Subtree<Value, f64>),
+    Value(&'a Value),
+}
+
+impl<Value: KdValue> PartialEq for Ranked<'_, Value> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Value: KdValue> Eq for Ranked<'_, Value> {}
+
+impl<Value: KdValue> PartialOrd for Ranked<'_, Value> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Value: KdValue> Ord for Ranked<'_, Value> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.total_cmp(&other.priority)
+    }
+}
+
+impl<'a, Value: KdValue, F: Fn(&Value) -> f64> Iterator for PriorityRectQuery<'a, Value, F>
+where
+    Value::Position: Clone,
+{
+    type Item = (f64, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ranked { priority, item } = self.heap.pop()?;
+            let subtree = match item {
+                RankedItem::Value(value) => return Some((priority, value)),
+                RankedItem::Tree(subtree) => subtree,
+            };
+            if !subtree
+                .bounds
+                .as_ref()
+                .is_some_and(|bounds| bounds.overlaps(&self.rect))
+            {
+                continue;
+            }
+            match &subtree.node {
+                Node::Leaf(values) => {
+                    for value in values {
+                        if Aabb::of(value).overlaps(&self.rect) {
+                            self.heap.push(Ranked {
+                                priority: (self.priority)(value),
+                                item: RankedItem::Value(value),
+                            });
+                        }
+                    }
+                }
+                Node::Split(split) => {
+                    for child in [&split.left, &split.right] {
+                        self.heap.push(Ranked {
+                            priority: child.summary,
+                            item: RankedItem::Tree(child),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AggregateKdTree, Count, MaxPriority, Monoid};
+    use crate::{
+        tests::{overlapping, query_corners, scattered, TestValue},
+        Aabb, KdValue,
+    };
+
+    //the largest width, standing in for a max priority
+    struct MaxWidth;
+
+    impl Monoid<TestValue> for MaxWidth {
+        type Summary = f32;
+
+        fn empty(&self) -> f32 {
+            0.
+        }
+
+        fn summarize(&self, value: &TestValue) -> f32 {
+            value.max_x() - value.min_x()
+        }
+
+        fn combine(&self, a: &f32, b: &f32) -> f32 {
+            a.max(*b)
+        }
+    }
+
+    #[test]
+    fn histograms() {
+        let mut tree = AggregateKdTree::<TestValue, Count, 4>::default();
+        for i in 0..200 {
+            let (x, y) = ((i % 20) as f32 * 5., (i / 20) as f32 * 10.);
+            tree.insert(TestValue::new(x, x + 1., y, y + 1.));
+        }
+        //centers at 0.5, 5.5, ..., 95.5, ten rows
+        assert_eq!(tree.histogram_x(0., 100., 0., 100., 4), vec![50; 4]);
+        assert_eq!(tree.histogram_y(0., 100., 0., 100., 2), vec![100; 2]);
+        //the column at x = 30 has its centers past the rectangle, in the last slice
+        assert_eq!(tree.histogram_x(10., 30., 0., 15., 2), vec![4, 6]);
+        assert!(tree.histogram_x(0., 100., 0., 100., 0).is_empty());
+    }
+
+    #[test]
+    fn quantiles() {
+        let mut tree = AggregateKdTree::<TestValue, Count, 4>::default();
+        //moved to x in -50..51 and squeezed to y in 0..49
+        let values: Vec<_> = scattered(300, |i| ((i % 7) as f32, 2.))
+            .into_iter()
+            .map(|v| {
+                let y = v.min_y * 0.5;
+                TestValue::new(v.min_x - 50., v.max_x - 50., y, y + 2.)
+            })
+            .collect();
+        for value in &values {
+            tree.insert(value.clone());
+        }
+        for i in 0..10 {
+            let (x, y) = ((i * 13 % 80) as f32 - 50., (i * 29 % 40) as f32);
+            let mut xs: Vec<_> = overlapping(&values, x, x + 30., y, y + 10.)
+                .into_iter()
+                .map(|v| v.min_x)
+                .collect();
+            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for q in [0., 0.25, 0.5, 0.9, 1.] {
+                let expected = xs[(q * (xs.len() - 1) as f64) as usize];
+                assert_eq!(tree.quantile_x(x, x + 30., y, y + 10., q), Some(expected));
+            }
+        }
+        assert_eq!(tree.quantile_y(-100., 100., -100., 100., 0.), Some(0.));
+        assert_eq!(tree.quantile_y(-100., 100., -100., 100., 1.), Some(48.));
+        assert_eq!(tree.quantile_x(500., 600., 0., 10., 0.5), None);
+    }
+
+    #[test]
+    fn aggregates() {
+        let mut counts = AggregateKdTree::<TestValue, Count, 4>::default();
+        let mut widths = AggregateKdTree::<TestValue, MaxWidth, 4>::new(MaxWidth);
+        let values = scattered(300, |i| ((i % 7) as f32, 2.));
+        for value in &values {
+            counts.insert(value.clone());
+            widths.insert(value.clone());
+        }
+        assert_eq!(*counts.summary(), 300);
+        for (x, y) in query_corners() {
+            let matching = overlapping(&values, x, x + 20., y, y + 15.);
+            assert_eq!(
+                counts.aggregate_in_rect(x, x + 20., y, y + 15.),
+                matching.len()
+            );
+            assert_eq!(
+                counts.query_rect(x, x + 20., y, y + 15.).count(),
+                matching.len()
+            );
+            assert_eq!(widths.count_in_rect(x, x + 20., y, y + 15.), matching.len());
+            let widest = matching
+                .iter()
+                .map(|v| v.max_x - v.min_x)
+                .fold(0., f32::max);
+            assert_eq!(widths.aggregate_in_rect(x, x + 20., y, y + 15.), widest);
+            let bounds = matching
+                .iter()
+                .map(|v| Aabb::of(*v))
+                .reduce(|a, b| a.union(&b));
+            assert_eq!(counts.bounds_in_rect(x, x + 20., y, y + 15.), bounds);
+        }
+        for value in &values[..100] {
+            assert!(counts.remove_one(value));
+        }
+        assert!(!counts.remove_one(&values[0]));
+        assert_eq!(*counts.summary(), 200);
+        assert_eq!(counts.len(), 200);
+        assert_eq!(counts.count_in_rect(-1., 200., -1., 200.), 200);
+        assert_eq!(counts.aggregate_in_rect(-1., 200., -1., 200.), 200);
+    }
+
+    #[test]
+    fn priority_order() {
+        let width = |value: &TestValue| (value.max_x - value.min_x) as f64;
+        let mut tree = AggregateKdTree::<_, _, 4>::new(MaxPriority(width));
+        let values = scattered(300, |i| ((i * 7 % 23) as f32, 2.));
+        for value in &values {
+            tree.insert(value.clone());
+        }
+        let mut expected: Vec<_> = overlapping(&values, 20., 60., 10., 50.)
+            .into_iter()
+            .map(width)
+            .collect();
+        expected.sort_by(|a, b| b.total_cmp(a));
+        let found: Vec<_> = tree
+            .query_rect_by_priority(20., 60., 10., 50.)
+            .map(|(priority, value)| {
+                assert_eq!(priority, width(value));
+                priority
+            })
+            .collect();
+        assert_eq!(found, expected);
+        let top: Vec<_> = tree
+            .query_rect_by_priority(20., 60., 10., 50.)
+            .take(3)
+            .map(|(priority, _)| priority)
+            .collect();
+        assert_eq!(top, expected[..3]);
+        assert_eq!(tree.query_rect_by_priority(500., 600., 0., 1.).count(), 0);
+    }
+
+    #[test]
+    fn equal_values() {
+        let mut tree = AggregateKdTree::<TestValue, Count, 8>::default();
+        for _ in 0..20000 {
+            tree.insert(TestValue::new(1., 2., 1., 2.));
+        }
+        tree.insert(TestValue::new(5., 6., 1., 2.));
+        assert_eq!(tree.count_in_rect(0., 3., 0., 3.), 20000);
+        assert_eq!(tree.query_rect(4., 7., 0., 3.).count(), 1);
+    }
+}
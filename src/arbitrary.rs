@@ -0,0 +1,100 @@
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+use crate::{Aabb, KdTree, KdValue, PayloadCell};
+
+//boxes are normalized, and rejected if their coordinates can not be ordered (NaN)
+impl<'a, P: Arbitrary<'a> + PartialOrd> Arbitrary<'a> for Aabb<P> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let (x0, x1, y0, y1) = <(P, P, P, P)>::arbitrary(u)?;
+        let (min_x, max_x) = ordered(x0, x1)?;
+        let (min_y, max_y) = ordered(y0, y1)?;
+        Ok(Aabb {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <(P, P, P, P)>::size_hint(depth)
+    }
+}
+
+fn ordered<P: PartialOrd>(a: P, b: P) -> Result<(P, P)> {
+    match a.partial_cmp(&b) {
+        Some(core::cmp::Ordering::Greater) => Ok((b, a)),
+        Some(_) => Ok((a, b)),
+        None => Err(Error::IncorrectFormat),
+    }
+}
+
+impl<'a, Bounds: Arbitrary<'a> + KdValue, T: Arbitrary<'a>> Arbitrary<'a>
+    for PayloadCell<Bounds, T>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(PayloadCell::new(u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <(Bounds, T)>::size_hint(depth)
+    }
+}
+
+//values are inserted one by one, so that the shape of the tree depends on their order
+impl<'a, Value: Arbitrary<'a> + KdValue, const ISLAND_SIZE: usize> Arbitrary<'a>
+    for KdTree<Value, ISLAND_SIZE>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut tree = Self::default();
+        for value in u.arbitrary_iter()? {
+            tree.insert(value?);
+        }
+        Ok(tree)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        let mut tree = Self::default();
+        for value in u.arbitrary_take_rest_iter()? {
+            tree.insert(value?);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::{Aabb, KdTree, PayloadCell};
+
+    #[test]
+    fn arbitrary_trees() {
+        //a simple xorshift, to get varied bytes without a dependency
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let bytes: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..10 {
+            let tree = KdTree::<PayloadCell<Aabb<i16>, u8>, 4>::arbitrary(&mut u).unwrap();
+            assert!(tree
+                .iter()
+                .all(|value| value.bounds().min_x <= value.bounds().max_x
+                    && value.bounds().min_y <= value.bounds().max_y));
+            let expected = tree
+                .iter()
+                .filter(|value| value.bounds().overlaps(&Aabb::new(-100, 100, -100, 100)))
+                .count();
+            assert_eq!(tree.query_rect(-100, 100, -100, 100).count(), expected);
+        }
+        let nan = f32::NAN.to_le_bytes();
+        let bytes: Vec<u8> = nan.iter().cycle().take(16).copied().collect();
+        assert!(Aabb::<f32>::arbitrary(&mut Unstructured::new(&bytes)).is_err());
+    }
+}
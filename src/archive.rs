@@ -0,0 +1,255 @@
+use alloc::{vec, vec::Vec};
+use rkyv::{Archive, Archived, Deserialize, Serialize};
+
+use crate::{
+    flat::{overlaps, union, EMPTY},
+    KdTree, KdValue, Scalar,
+};
+
+/// A flat copy of a tree that rkyv can archive, so that a prebuilt tree can be
+/// queried straight from the archived bytes, without deserializing it.
+///
+/// Archive it with `rkyv::to_bytes`, and query the `ArchivedFlatKdTree` returned
+/// by `rkyv::access` (or `rkyv::access_unchecked` for trusted assets) on the
+/// bytes, which can come from a memory-mapped asset pack.
+///
+/// Nodes are in pre-order, so the root is the first one, and keep the tight
+/// bounds of their subtree as `[min_x, max_x, min_y, max_y]`, converted to f64.
+#[derive(Debug, Clone, Archive, Serialize, Deserialize)]
+pub struct FlatKdTree<Value> {
+    nodes: Vec<FlatNode>,
+    //the bounds of the values, in the same order
+    bounds: Vec<[f64; 4]>,
+    values: Vec<Value>,
+}
+
+//the children of an inner node, or the range of values of a leaf
+#[derive(Debug, Clone, Copy, Archive, Serialize, Deserialize)]
+struct FlatNode {
+    bounds: [f64; 4],
+    leaf: bool,
+    first: u32,
+    second: u32,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    pub fn to_flat(&self) -> FlatKdTree<Value> {
+        let mut flat = FlatKdTree {
+            nodes: Vec::new(),
+            bounds: Vec::with_capacity(self.len()),
+            values: Vec::with_capacity(self.len()),
+        };
+        flat.push(self);
+        flat
+    }
+}
+
+impl<Value> FlatKdTree<Value> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    fn push<const ISLAND_SIZE: usize>(&mut self, tree: &KdTree<Value, ISLAND_SIZE>) -> u32
+    where
+        Value: KdValue,
+        Value::Position: Scalar,
+    {
+        let index = self.nodes.len();
+        self.nodes.push(FlatNode {
+            bounds: EMPTY,
+            leaf: false,
+            first: 0,
+            second: 0,
+        });
+        self.nodes[index] = match tree {
+            KdTree::Leaf(leaf, ..) => {
+                let first = self.values.len() as u32;
+                let mut bounds = EMPTY;
+                for value in leaf {
+                    let value_bounds = [
+                        value.min_x().to_f64(),
+                        value.max_x().to_f64(),
+                        value.min_y().to_f64(),
+                        value.max_y().to_f64(),
+                    ];
+                    bounds = union(bounds, value_bounds);
+                    self.bounds.push(value_bounds);
+                    self.values.push(value.clone());
+                }
+                FlatNode {
+                    bounds,
+                    leaf: true,
+                    first,
+                    second: leaf.len() as u32,
+                }
+            }
+            KdTree::Node(node) => {
+                let left = self.push(&node.left);
+                let right = self.push(&node.right);
+                FlatNode {
+                    bounds: union(
+                        self.nodes[left as usize].bounds,
+                        self.nodes[right as usize].bounds,
+                    ),
+                    leaf: false,
+                    first: left,
+                    second: right,
+                }
+            }
+        };
+        index as u32
+    }
+}
+
+impl<Value: Archive> ArchivedFlatKdTree<Value> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn query_point(&self, x: f64, y: f64) -> ArchivedRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: f64,
+        max_x: f64,
+        min_y: f64,
+        max_y: f64,
+    ) -> ArchivedRectQuery<'_, Value> {
+        ArchivedRectQuery {
+            tree: self,
+            query: [min_x, max_x, min_y, max_y],
+            queue: if self.nodes.is_empty() {
+                vec![]
+            } else {
+                vec![0]
+            },
+            leaf: 0..0,
+        }
+    }
+}
+
+/// The archived values overlapping a rectangle.
+pub struct ArchivedRectQuery<'a, Value: Archive> {
+    tree: &'a ArchivedFlatKdTree<Value>,
+    query: [f64; 4],
+    queue: Vec<usize>,
+    leaf: core::ops::Range<usize>,
+}
+
+impl<'a, Value: Archive> Iterator for ArchivedRectQuery<'a, Value> {
+    type Item = &'a Archived<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            //an archive can pass validation and still be inconsistent: indexes out of
+            //it end the leaf or skip the node, and children must come after their
+            //parent, as written in pre-order, so that there are no cycles
+            for index in &mut self.leaf {
+                let (Some(bounds), Some(value)) =
+                    (self.tree.bounds.get(index), self.tree.values.get(index))
+                else {
+                    self.leaf = 0..0;
+                    break;
+                };
+                if overlaps(native(bounds), self.query) {
+                    return Some(value);
+                }
+            }
+            let index = self.queue.pop()?;
+            let Some(node) = self.tree.nodes.get(index) else {
+                continue;
+            };
+            if !overlaps(native(&node.bounds), self.query) {
+                continue;
+            }
+            let (first, second) = (
+                node.first.to_native() as usize,
+                node.second.to_native() as usize,
+            );
+            if node.leaf {
+                self.leaf = first..first.saturating_add(second);
+            } else if first > index && second > index {
+                self.queue.push(second);
+                self.queue.push(first);
+            }
+        }
+    }
+}
+
+fn native(bounds: &[Archived<f64>; 4]) -> [f64; 4] {
+    [
+        bounds[0].to_native(),
+        bounds[1].to_native(),
+        bounds[2].to_native(),
+        bounds[3].to_native(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use rkyv::rancor::Error;
+
+    use super::{ArchivedFlatKdTree, FlatKdTree, FlatNode};
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn archived_queries() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..50 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(Aabb::new(x, x + 3., i as f32, i as f32 + 2.));
+        }
+        let bytes = rkyv::to_bytes::<Error>(&tree.to_flat()).unwrap();
+        let archived = rkyv::access::<ArchivedFlatKdTree<Aabb<f32>>, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 50);
+        assert_eq!(
+            archived.query_rect(10., 30., 5., 20.).count(),
+            tree.query_rect(10., 30., 5., 20.).count()
+        );
+        let hit = archived.query_point(38., 1.5).next().unwrap();
+        assert_eq!(hit.min_x.to_native(), 37.);
+    }
+
+    #[test]
+    fn inconsistent_archive() {
+        let everything = [
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+        ];
+        let node = |leaf, first, second| FlatNode {
+            bounds: everything,
+            leaf,
+            first,
+            second,
+        };
+        //a node pointing back to the root, one out of the archive, and a leaf
+        //running past the values
+        let flat = FlatKdTree {
+            nodes: vec![node(false, 1, 2), node(false, 0, 7), node(true, 0, 5)],
+            bounds: vec![everything; 2],
+            values: vec![Aabb::new(0f32, 1., 0., 1.); 2],
+        };
+        let bytes = rkyv::to_bytes::<Error>(&flat).unwrap();
+        let archived = rkyv::access::<ArchivedFlatKdTree<Aabb<f32>>, Error>(&bytes).unwrap();
+        assert_eq!(archived.query_point(0.5, 0.5).count(), 2);
+    }
+}
@@ -0,0 +1,88 @@
+use arrow_array::{Array, ArrowNativeTypeOp, ArrowPrimitiveType, PrimitiveArray};
+use arrow_schema::ArrowError;
+
+use crate::{Aabb, KdTree, PayloadCell};
+
+impl<P, Id, const ISLAND_SIZE: usize> KdTree<PayloadCell<Aabb<P>, Id>, ISLAND_SIZE>
+where
+    P: ArrowNativeTypeOp,
+    Id: ArrowNativeTypeOp,
+{
+    /// Bulk-builds a tree from Arrow bounds columns, with the matching `ids` as
+    /// payloads. The columns are read directly from their buffers, for example
+    /// the columns of a record batch read from Parquet.
+    ///
+    /// Returns an error if the columns have different lengths or contain nulls.
+    pub fn from_arrow<T, I>(
+        min_x: &PrimitiveArray<T>,
+        max_x: &PrimitiveArray<T>,
+        min_y: &PrimitiveArray<T>,
+        max_y: &PrimitiveArray<T>,
+        ids: &PrimitiveArray<I>,
+    ) -> Result<Self, ArrowError>
+    where
+        T: ArrowPrimitiveType<Native = P>,
+        I: ArrowPrimitiveType<Native = Id>,
+    {
+        let len = ids.len();
+        let columns: [&dyn Array; 5] = [min_x, max_x, min_y, max_y, ids];
+        if columns.iter().any(|column| column.len() != len) {
+            return Err(ArrowError::InvalidArgumentError(
+                "bounds and id columns have different lengths".to_string(),
+            ));
+        }
+        if columns.iter().any(|column| column.null_count() > 0) {
+            return Err(ArrowError::InvalidArgumentError(
+                "bounds and id columns cannot contain nulls".to_string(),
+            ));
+        }
+        let values = min_x
+            .values()
+            .iter()
+            .zip(max_x.values().iter())
+            .zip(min_y.values().iter().zip(max_y.values().iter()))
+            .zip(ids.values().iter())
+            .map(|(((&min_x, &max_x), (&min_y, &max_y)), id)| {
+                PayloadCell::new(Aabb::new(min_x, max_x, min_y, max_y), *id)
+            })
+            .collect();
+        Ok(Self::build(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Float32Array, UInt64Array};
+
+    use crate::{Aabb, KdTree, PayloadCell};
+
+    #[test]
+    fn arrow_ingestion() {
+        let min_x = Float32Array::from((0..100).map(|i| (i * 7 % 101) as f32).collect::<Vec<_>>());
+        let max_x = Float32Array::from(min_x.values().iter().map(|x| x + 1.).collect::<Vec<_>>());
+        let min_y = Float32Array::from((0..100).map(|i| i as f32).collect::<Vec<_>>());
+        let max_y = Float32Array::from((0..100).map(|i| i as f32 + 1.).collect::<Vec<_>>());
+        let ids = UInt64Array::from((1000..1100).collect::<Vec<u64>>());
+        let tree = KdTree::<PayloadCell<Aabb<f32>, u64>, 8>::from_arrow(
+            &min_x, &max_x, &min_y, &max_y, &ids,
+        )
+        .unwrap();
+        assert_eq!(tree.len(), 100);
+        let hits: Vec<_> = tree
+            .query_point(35.5, 5.5)
+            .map(|value| *value.payload())
+            .collect();
+        assert_eq!(hits, vec![1005]);
+
+        let short = UInt64Array::from(vec![1, 2]);
+        assert!(KdTree::<PayloadCell<Aabb<f32>, u64>, 8>::from_arrow(
+            &min_x, &max_x, &min_y, &max_y, &short
+        )
+        .is_err());
+        let nulls = Float32Array::from(vec![None; 100]);
+        assert!(KdTree::<PayloadCell<Aabb<f32>, u64>, 8>::from_arrow(
+            &nulls, &max_x, &min_y, &max_y, &ids
+        )
+        .is_err());
+    }
+}
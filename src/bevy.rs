@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    lifecycle::RemovedComponents,
+    query::{Changed, Or},
+    resource::Resource,
+    schedule::IntoScheduleConfigs,
+    system::{Query, Res, ResMut, SystemParam},
+};
+use bevy_transform::{components::GlobalTransform, TransformSystems};
+
+use crate::{Aabb, KdTree, KdValue};
+
+/// The local box of an entity, placed in the tree once moved and scaled by its
+/// `GlobalTransform` (rotations are ignored).
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SpatialBounds(pub Aabb<f32>);
+
+/// An entity and its box in world space, as stored in the tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityBounds {
+    pub entity: Entity,
+    pub bounds: Aabb<f32>,
+}
+
+impl Default for EntityBounds {
+    fn default() -> Self {
+        Self {
+            entity: Entity::PLACEHOLDER,
+            bounds: Aabb::default(),
+        }
+    }
+}
+
+impl KdValue for EntityBounds {
+    type Position = f32;
+
+    fn min_x(&self) -> f32 {
+        self.bounds.min_x
+    }
+
+    fn min_y(&self) -> f32 {
+        self.bounds.min_y
+    }
+
+    fn max_x(&self) -> f32 {
+        self.bounds.max_x
+    }
+
+    fn max_y(&self) -> f32 {
+        self.bounds.max_y
+    }
+}
+
+/// The tree of every entity with `SpatialBounds` and a `GlobalTransform`, kept up to
+/// date by `KdTreePlugin` after transforms are propagated.
+#[derive(Resource, Debug, Default)]
+pub struct SpatialTree {
+    tree: KdTree<EntityBounds, 16>,
+    entities: HashMap<Entity, Aabb<f32>>,
+}
+
+impl SpatialTree {
+    pub fn tree(&self) -> &KdTree<EntityBounds, 16> {
+        &self.tree
+    }
+
+    /// The world box of an entity, as of the last sync.
+    pub fn bounds(&self, entity: Entity) -> Option<&Aabb<f32>> {
+        self.entities.get(&entity)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: f32,
+        max_x: f32,
+        min_y: f32,
+        max_y: f32,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        self.tree
+            .query_rect(min_x, max_x, min_y, max_y)
+            .map(|value| value.entity)
+    }
+
+    pub fn query_point(&self, x: f32, y: f32) -> impl Iterator<Item = Entity> + '_ {
+        self.tree.query_point(x, y).map(|value| value.entity)
+    }
+
+    fn set(&mut self, entity: Entity, bounds: Aabb<f32>) {
+        if let Some(old) = self.entities.insert(entity, bounds) {
+            if old == bounds {
+                return;
+            }
+            self.tree.remove_one(EntityBounds {
+                entity,
+                bounds: old,
+            });
+        }
+        self.tree.insert(EntityBounds { entity, bounds });
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(bounds) = self.entities.remove(&entity) {
+            self.tree.remove_one(EntityBounds { entity, bounds });
+        }
+    }
+}
+
+/// Read access to the `SpatialTree` from systems.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w> {
+    tree: Res<'w, SpatialTree>,
+}
+
+impl<'w> SpatialQuery<'w> {
+    pub fn query_rect(
+        &self,
+        min_x: f32,
+        max_x: f32,
+        min_y: f32,
+        max_y: f32,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        self.tree.query_rect(min_x, max_x, min_y, max_y)
+    }
+
+    pub fn query_point(&self, x: f32, y: f32) -> impl Iterator<Item = Entity> + '_ {
+        self.tree.query_point(x, y)
+    }
+
+    pub fn bounds(&self, entity: Entity) -> Option<&Aabb<f32>> {
+        self.tree.bounds(entity)
+    }
+}
+
+/// Maintains the `SpatialTree` resource in `PostUpdate`.
+#[derive(Debug, Default)]
+pub struct KdTreePlugin;
+
+impl Plugin for KdTreePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialTree>().add_systems(
+            PostUpdate,
+            sync_spatial_tree.after(TransformSystems::Propagate),
+        );
+    }
+}
+
+type MovedEntities<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static SpatialBounds, &'static GlobalTransform),
+    Or<(Changed<SpatialBounds>, Changed<GlobalTransform>)>,
+>;
+
+fn sync_spatial_tree(
+    mut tree: ResMut<SpatialTree>,
+    moved: MovedEntities,
+    mut removed: RemovedComponents<SpatialBounds>,
+) {
+    for entity in removed.read() {
+        tree.remove(entity);
+    }
+    for (entity, bounds, transform) in &moved {
+        let (translation, scale) = (transform.translation(), transform.scale());
+        let (x0, x1) = (bounds.0.min_x * scale.x, bounds.0.max_x * scale.x);
+        let (y0, y1) = (bounds.0.min_y * scale.y, bounds.0.max_y * scale.y);
+        //negative scales flip the box
+        let world = Aabb::new(
+            x0.min(x1) + translation.x,
+            x0.max(x1) + translation.x,
+            y0.min(y1) + translation.y,
+            y0.max(y1) + translation.y,
+        );
+        tree.set(entity, world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KdTreePlugin, SpatialBounds, SpatialTree};
+    use crate::Aabb;
+    use bevy_app::App;
+    use bevy_transform::components::GlobalTransform;
+
+    #[test]
+    fn bevy_plugin() {
+        let mut app = App::new();
+        app.add_plugins(KdTreePlugin);
+        let bounds = SpatialBounds(Aabb::new(-1., 1., -1., 1.));
+        let player = app
+            .world_mut()
+            .spawn((bounds, GlobalTransform::from_xyz(10., 0., 0.)))
+            .id();
+        let wall = app
+            .world_mut()
+            .spawn((bounds, GlobalTransform::from_xyz(0., 0., 0.)))
+            .id();
+        app.update();
+        let tree = app.world().resource::<SpatialTree>();
+        assert_eq!(tree.query_point(10.5, 0.).collect::<Vec<_>>(), vec![player]);
+        app.world_mut()
+            .entity_mut(player)
+            .insert(GlobalTransform::from_xyz(0.5, 0., 0.));
+        app.world_mut().entity_mut(wall).remove::<SpatialBounds>();
+        app.update();
+        let tree = app.world().resource::<SpatialTree>();
+        assert_eq!(tree.query_point(0., 0.).collect::<Vec<_>>(), vec![player]);
+        assert_eq!(tree.query_point(10.5, 0.).count(), 0);
+        assert_eq!(tree.tree().len(), 1);
+    }
+}
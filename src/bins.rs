@@ -0,0 +1,76 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{scalar::floor, KdTree, KdValue, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Splits the rectangle in a grid of `cols` by `rows` equal cells, and puts the
+    /// values overlapping it in the buckets of every cell they overlap, in a single
+    /// query.
+    ///
+    /// Buckets are row-major: the cell at `(col, row)` is at `row * cols + col`, row
+    /// 0 being at `min_y`.
+    pub fn bin_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        cols: usize,
+        rows: usize,
+    ) -> Vec<Vec<&Value>> {
+        let mut buckets = vec![Vec::new(); cols * rows];
+        if buckets.is_empty() {
+            return buckets;
+        }
+        let cell = |position: f64, low: f64, high: f64, cells: usize| {
+            let cell = floor((position - low) / (high - low) * cells as f64);
+            //also catches the NaN of an empty range
+            if cell >= 0. {
+                (cell as usize).min(cells - 1)
+            } else {
+                0
+            }
+        };
+        let (low_x, high_x) = (min_x.to_f64(), max_x.to_f64());
+        let (low_y, high_y) = (min_y.to_f64(), max_y.to_f64());
+        for value in self.query_rect(min_x, max_x, min_y, max_y) {
+            let first_col = cell(value.min_x().to_f64(), low_x, high_x, cols);
+            let last_col = cell(value.max_x().to_f64(), low_x, high_x, cols);
+            let first_row = cell(value.min_y().to_f64(), low_y, high_y, rows);
+            let last_row = cell(value.max_y().to_f64(), low_y, high_y, rows);
+            for row in first_row..=last_row {
+                for col in first_col..=last_col {
+                    buckets[row * cols + col].push(value);
+                }
+            }
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn bin_in_rect() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        tree.insert(TestValue::new(1., 2., 1., 2.));
+        //spans the two bottom cells
+        tree.insert(TestValue::new(4., 6., 0., 1.));
+        tree.insert(TestValue::new(7., 8., 7., 8.));
+        //sticks out of the rectangle
+        tree.insert(TestValue::new(-5., 1., 9., 15.));
+        tree.insert(TestValue::new(20., 21., 20., 21.));
+        let buckets = tree.bin_in_rect(0., 10., 0., 10., 2, 2);
+        let sizes: Vec<_> = buckets.iter().map(|bucket| bucket.len()).collect();
+        assert_eq!(sizes, vec![2, 1, 1, 1]);
+        assert!(buckets[0].contains(&&TestValue::new(4., 6., 0., 1.)));
+        assert!(buckets[1].contains(&&TestValue::new(4., 6., 0., 1.)));
+        assert!(buckets[2].contains(&&TestValue::new(-5., 1., 9., 15.)));
+        assert!(tree.bin_in_rect(0., 10., 0., 10., 0, 3).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::vec::Vec;
+use core::slice;
+
+use crate::{KdValue, SpatialIndex2D};
+
+/// A plain list of values queried by linear scan, with the same query semantics
+/// as `KdTree`, to use as ground truth when testing `KdValue` impls or code
+/// built on top of the tree.
+#[derive(Debug, Clone)]
+pub struct BruteForceIndex<Value: KdValue> {
+    values: Vec<Value>,
+}
+
+impl<Value: KdValue> Default for BruteForceIndex<Value> {
+    fn default() -> Self {
+        Self { values: Vec::new() }
+    }
+}
+
+impl<Value: KdValue> From<Vec<Value>> for BruteForceIndex<Value> {
+    fn from(values: Vec<Value>) -> Self {
+        Self { values }
+    }
+}
+
+impl<Value: KdValue> BruteForceIndex<Value> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.values.push(value)
+    }
+
+    /// Removes one value equal to `value`, returning whether one was found.
+    pub fn remove_one(&mut self, value: &Value) -> bool {
+        match self.values.iter().position(|other| other == value) {
+            Some(index) => {
+                self.values.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_all(&mut self, value: &Value) {
+        self.values.retain(|other| other != value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Value> {
+        self.values.iter()
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> BruteForceQuery<'_, Value> {
+        BruteForceQuery {
+            values: self.values.iter(),
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> BruteForceQuery<'_, Value>
+    where
+        Value::Position: Clone,
+    {
+        self.query_rect(x.clone(), x, y.clone(), y)
+    }
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    pub fn for_each_pair(&self, f: &mut dyn FnMut(&Value, &Value)) {
+        for (i, a) in self.values.iter().enumerate() {
+            for b in &self.values[i + 1..] {
+                if a.min_x() <= b.max_x()
+                    && b.min_x() <= a.max_x()
+                    && a.min_y() <= b.max_y()
+                    && b.min_y() <= a.max_y()
+                {
+                    f(a, b);
+                }
+            }
+        }
+    }
+}
+
+pub struct BruteForceQuery<'a, Value: KdValue> {
+    values: slice::Iter<'a, Value>,
+    min_x: Value::Position,
+    max_x: Value::Position,
+    min_y: Value::Position,
+    max_y: Value::Position,
+}
+
+impl<'a, Value: KdValue> Iterator for BruteForceQuery<'a, Value> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        let (min_x, max_x, min_y, max_y) = (&self.min_x, &self.max_x, &self.min_y, &self.max_y);
+        //not being apart, like `KdTree::query_rect`, so that values with NaN
+        //bounds match the same way
+        self.values.find(|value| {
+            !(value.min_x() > *max_x
+                || *min_x > value.max_x()
+                || value.min_y() > *max_y
+                || *min_y > value.max_y())
+        })
+    }
+}
+
+impl<Value: KdValue> SpatialIndex2D for BruteForceIndex<Value>
+where
+    Value::Position: Clone,
+{
+    type Value = Value;
+    type Query<'a>
+        = BruteForceQuery<'a, Value>
+    where
+        Self: 'a;
+
+    fn insert(&mut self, value: Value) {
+        BruteForceIndex::insert(self, value)
+    }
+
+    fn remove_one(&mut self, value: &Value) -> bool {
+        BruteForceIndex::remove_one(self, value)
+    }
+
+    fn len(&self) -> usize {
+        BruteForceIndex::len(self)
+    }
+
+    fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Self::Query<'_> {
+        BruteForceIndex::query_rect(self, min_x, max_x, min_y, max_y)
+    }
+
+    fn query_point(&self, x: Value::Position, y: Value::Position) -> Self::Query<'_> {
+        BruteForceIndex::query_point(self, x, y)
+    }
+
+    fn for_each_pair(&self, f: &mut dyn FnMut(&Value, &Value)) {
+        BruteForceIndex::for_each_pair(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BruteForceIndex;
+    use crate::{
+        tests::{assert_same, query_corners, scattered, TestValue},
+        KdTree,
+    };
+
+    #[test]
+    fn matches_kdtree() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let mut reference = BruteForceIndex::new();
+        let mut values = scattered(200, |i| ((i % 5) as f32, 3.));
+        //values with a NaN bound
+        values.extend((0..10).map(|i| {
+            let x = (i * 9) as f32;
+            TestValue::new(x, x + 2., x, f32::NAN)
+        }));
+        for value in values {
+            tree.insert(value.clone());
+            reference.insert(value);
+        }
+        for (x, y) in query_corners() {
+            let expected: Vec<_> = reference.query_rect(x, x + 10., y, y + 10.).collect();
+            let found: Vec<_> = tree.query_rect(x, x + 10., y, y + 10.).collect();
+            assert_same(&found, &expected);
+            assert_eq!(
+                tree.query_point(x, y).count(),
+                reference.query_point(x, y).count()
+            );
+        }
+    }
+}
@@ -0,0 +1,714 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{cmp::Ordering, iter::FromIterator, task::Poll};
+
+use crate::{cmp_position, raises_max, split_index, KdNode, KdTree, KdValue};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Builds a balanced tree from all the values at once.
+    ///
+    /// The tree only depends on the set of values, not on their order, except for
+    /// the order of values sharing the same bounds.
+    pub fn build(values: Vec<Value>) -> Self {
+        Self::build_internal(values, false)
+    }
+
+    /// Rebuilds the tree from scratch, balancing it again.
+    pub fn rebuild(&mut self) {
+        let values = core::mem::take(self).into_values();
+        *self = Self::build(values);
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        let mut values = Vec::with_capacity(self.len());
+        self.into_values_internal(&mut values);
+        values
+    }
+
+    fn into_values_internal(self, values: &mut Vec<Value>) {
+        match self {
+            KdTree::Leaf(mut leaf, ..) => values.append(&mut leaf),
+            KdTree::Node(node) => {
+                let node = *node;
+                node.left.into_values_internal(values);
+                node.right.into_values_internal(values);
+            }
+        }
+    }
+
+    pub(crate) fn build_internal(values: Vec<Value>, vertical: bool) -> Self {
+        match Self::partition(values, vertical) {
+            Err(leaf) => KdTree::leaf(leaf),
+            Ok(split) => KdTree::Node(Box::new(KdNode {
+                vertical: split.vertical,
+                median: split.median,
+                left_max: split.left_max,
+                left: Self::build_internal(split.left, !split.vertical),
+                right: Self::build_internal(split.right, !split.vertical),
+            })),
+        }
+    }
+
+    //splits the values along the axis, or the other one if they all start at the
+    //same position along it, or gives them back as a leaf if they fit in one or
+    //can't be split
+    fn partition(mut values: Vec<Value>, vertical: bool) -> Result<Split<Value>, Vec<Value>> {
+        if values.len() >= ISLAND_SIZE {
+            for vertical in [vertical, !vertical] {
+                if let Some(split) = Self::partition_along(&mut values, vertical) {
+                    return Ok(split);
+                }
+            }
+        }
+        values.sort_by(|a, b| cmp_bounds(a, b, vertical));
+        let mut leaf = Vec::with_capacity(ISLAND_SIZE.max(values.len()));
+        leaf.append(&mut values);
+        Err(leaf)
+    }
+
+    fn partition_along(values: &mut Vec<Value>, vertical: bool) -> Option<Split<Value>> {
+        let key = |value: &Value| {
+            if vertical {
+                value.min_y()
+            } else {
+                value.min_x()
+            }
+        };
+        //ties are broken by the other bounds, and the sort is stable, so that the
+        //same values give the same tree whatever their order
+        values.sort_by(|a, b| cmp_bounds(a, b, vertical));
+        //values equal to the median go right, so that removals find them again, and
+        //infinite positions can't become the median unless the values are all there
+        let split = split_index(values, key)?;
+        let median = key(&values[split]);
+        let right = values.split_off(split);
+        let left = core::mem::take(values);
+        let max = |value: &Value| {
+            if vertical {
+                value.max_y()
+            } else {
+                value.max_x()
+            }
+        };
+        let left_max = left.iter().skip(1).fold(max(&left[0]), |prev, value| {
+            let v_max = max(value);
+            if raises_max(&prev, &v_max) {
+                v_max
+            } else {
+                prev
+            }
+        });
+        Some(Split {
+            vertical,
+            median,
+            left_max,
+            left,
+            right,
+        })
+    }
+}
+
+//orders values along the axis, then by their other bounds
+fn cmp_bounds<Value: KdValue>(a: &Value, b: &Value, vertical: bool) -> Ordering {
+    let first = |value: &Value| {
+        if vertical {
+            value.min_y()
+        } else {
+            value.min_x()
+        }
+    };
+    let second = |value: &Value| {
+        if vertical {
+            value.min_x()
+        } else {
+            value.min_y()
+        }
+    };
+    cmp_position(&first(a), &first(b))
+        .then_with(|| cmp_position(&second(a), &second(b)))
+        .then_with(|| cmp_position(&a.max_x(), &b.max_x()))
+        .then_with(|| cmp_position(&a.max_y(), &b.max_y()))
+}
+
+struct Split<Value: KdValue> {
+    vertical: bool,
+    median: Value::Position,
+    left_max: Value::Position,
+    left: Vec<Value>,
+    right: Vec<Value>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> FromIterator<Value> for KdTree<Value, ISLAND_SIZE> {
+    fn from_iter<T: IntoIterator<Item = Value>>(iter: T) -> Self {
+        Self::build(iter.into_iter().collect())
+    }
+}
+
+/// Builds a tree from values arriving in chunks, without keeping more than one
+/// chunk in memory besides the tree itself.
+///
+/// Buffered values are inserted median first, so that input sorted along an axis
+/// (as is common in files) does not make the tree grow in only one direction.
+#[derive(Debug)]
+pub struct KdTreeBuilder<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    chunk: Vec<Value>,
+    chunk_size: usize,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTreeBuilder<Value, ISLAND_SIZE> {
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        Self {
+            tree: KdTree::default(),
+            chunk: Vec::with_capacity(chunk_size),
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.chunk.push(value);
+        if self.chunk.len() >= self.chunk_size {
+            self.flush();
+        }
+    }
+
+    pub fn extend_chunk<I: IntoIterator<Item = Value>>(&mut self, values: I) {
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    pub fn finish(mut self) -> KdTree<Value, ISLAND_SIZE> {
+        self.flush();
+        self.tree
+    }
+
+    fn flush(&mut self) {
+        if self.chunk.is_empty() {
+            return;
+        }
+        //the first chunk is bulk built, so that the top of the tree is balanced
+        if self.tree.is_empty() {
+            let chunk = core::mem::replace(&mut self.chunk, Vec::with_capacity(self.chunk_size));
+            self.tree = KdTree::build(chunk);
+            return;
+        }
+        self.chunk
+            .sort_unstable_by(|a, b| cmp_position(&a.min_x(), &b.min_x()));
+        let mut chunk: Vec<Option<Value>> = self.chunk.drain(..).map(Some).collect();
+        //breadth first over the ranges of the sorted chunk, inserting their middle
+        let mut ranges = alloc::collections::VecDeque::new();
+        ranges.push_back(0..chunk.len());
+        while let Some(range) = ranges.pop_front() {
+            if range.is_empty() {
+                continue;
+            }
+            let middle = range.start + range.len() / 2;
+            if let Some(value) = chunk[middle].take() {
+                self.tree.insert(value);
+            }
+            ranges.push_back(range.start..middle);
+            ranges.push_back(middle + 1..range.end);
+        }
+    }
+}
+
+/// Builds a balanced tree a bounded amount of work at a time, so that a game loop
+/// or an async task can spread a big build over many calls.
+#[derive(Debug)]
+pub struct IncrementalBuild<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: Option<KdTree<Value, ISLAND_SIZE>>,
+    //the nodes still to build, the last one being worked on
+    tasks: Vec<Task<Value>>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> IncrementalBuild<Value, ISLAND_SIZE> {
+    pub fn new(values: Vec<Value>) -> Self {
+        Self {
+            tree: Some(KdTree::default()),
+            tasks: vec![Task::new(
+                Vec::new(),
+                values.into_iter().map(Some).collect(),
+                false,
+            )],
+        }
+    }
+
+    /// Starts rebuilding an existing tree.
+    pub fn rebuild(tree: KdTree<Value, ISLAND_SIZE>) -> Self {
+        Self::new(tree.into_values())
+    }
+
+    /// Does about `budget` values worth of work, and returns the tree once it is
+    /// complete. The values of a big node are sorted and split over several steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning `Poll::Ready`.
+    pub fn poll_step(&mut self, budget: usize) -> Poll<KdTree<Value, ISLAND_SIZE>> {
+        let tree = self.tree.as_mut().expect("the build is already complete");
+        let mut done = 0;
+        while done < budget.max(1) {
+            let task = match self.tasks.last_mut() {
+                Some(task) => task,
+                None => break,
+            };
+            done += 1;
+            let built = match task.step(ISLAND_SIZE) {
+                Some(built) => built,
+                None => continue,
+            };
+            let path = self.tasks.pop().expect("the task was just stepped").path;
+            let slot = path.iter().fold(&mut *tree, |tree, right| match tree {
+                KdTree::Node(node) => {
+                    if *right {
+                        &mut node.right
+                    } else {
+                        &mut node.left
+                    }
+                }
+                KdTree::Leaf(..) => unreachable!("the path only goes through built nodes"),
+            });
+            match built {
+                Built::Leaf(leaf) => *slot = KdTree::leaf(leaf),
+                Built::Node {
+                    vertical,
+                    median,
+                    left_max,
+                    left,
+                    right,
+                } => {
+                    *slot = KdTree::Node(Box::new(KdNode {
+                        vertical,
+                        median,
+                        left_max,
+                        left: KdTree::default(),
+                        right: KdTree::default(),
+                    }));
+                    let mut right_path = path.clone();
+                    right_path.push(true);
+                    let mut left_path = path;
+                    left_path.push(false);
+                    self.tasks.push(Task::new(right_path, right, !vertical));
+                    self.tasks.push(Task::new(left_path, left, !vertical));
+                }
+            }
+        }
+        if self.tasks.is_empty() {
+            Poll::Ready(self.tree.take().expect("the build is already complete"))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+//a node of an incremental build, partitioned the same way as `KdTree::partition`
+//but one value at a time: its values are merge sorted through a list of indexes,
+//then the median is searched, then the values are moved to their side
+#[derive(Debug)]
+struct Task<Value: KdValue> {
+    //the path to the slot of the node (true going right)
+    path: Vec<bool>,
+    vertical: bool,
+    //the axis being sorted along, and whether the node is a leaf once it is
+    axis: bool,
+    leaf: bool,
+    values: Vec<Option<Value>>,
+    //the order of the previous merge pass (the order of `values` if empty), and
+    //the one being merged from it
+    order: Vec<usize>,
+    merged: Vec<usize>,
+    //the width of the sorted runs, the start of the two being merged and their
+    //next index
+    width: usize,
+    start: usize,
+    left: usize,
+    right: usize,
+    stage: Stage<Value>,
+}
+
+#[derive(Debug)]
+enum Stage<Value: KdValue> {
+    Sort,
+    //walking from the middle to the split index, as `split_index` does
+    Search {
+        index: usize,
+        backward: bool,
+    },
+    LeftMax {
+        split: usize,
+        index: usize,
+        max: Value::Position,
+    },
+    GatherLeaf(Vec<Value>),
+    GatherNode {
+        median: Value::Position,
+        left_max: Value::Position,
+        split: usize,
+        left: Vec<Option<Value>>,
+        right: Vec<Option<Value>>,
+    },
+}
+
+enum Built<Value: KdValue> {
+    Leaf(Vec<Value>),
+    Node {
+        vertical: bool,
+        median: Value::Position,
+        left_max: Value::Position,
+        left: Vec<Option<Value>>,
+        right: Vec<Option<Value>>,
+    },
+}
+
+impl<Value: KdValue> Task<Value> {
+    fn new(path: Vec<bool>, values: Vec<Option<Value>>, vertical: bool) -> Self {
+        let mut task = Self {
+            path,
+            vertical,
+            axis: vertical,
+            leaf: false,
+            values,
+            order: Vec::new(),
+            merged: Vec::new(),
+            width: 1,
+            start: 0,
+            left: 0,
+            right: 0,
+            stage: Stage::Sort,
+        };
+        task.sort_along(vertical);
+        task
+    }
+
+    //sorts again from the current order, so that ties keep it as with `sort_by`
+    fn sort_along(&mut self, axis: bool) {
+        self.axis = axis;
+        self.merged.clear();
+        self.width = 1;
+        self.start = 0;
+        self.left = 0;
+        self.right = self.values.len().min(1);
+        self.stage = Stage::Sort;
+    }
+
+    //the index of the value at this position of the order
+    fn at(&self, position: usize) -> usize {
+        if self.order.is_empty() {
+            position
+        } else {
+            self.order[position]
+        }
+    }
+
+    fn value(&self, position: usize) -> &Value {
+        self.values[self.at(position)]
+            .as_ref()
+            .expect("values are only moved once sorted")
+    }
+
+    fn key(&self, position: usize) -> Value::Position {
+        let value = self.value(position);
+        if self.axis {
+            value.min_y()
+        } else {
+            value.min_x()
+        }
+    }
+
+    fn take(&mut self, position: usize) -> Value {
+        let index = self.at(position);
+        self.values[index]
+            .take()
+            .expect("values are only moved once")
+    }
+
+    //does one value worth of work, and returns the node once it is built
+    fn step(&mut self, island_size: usize) -> Option<Built<Value>> {
+        let len = self.values.len();
+        let stage = core::mem::replace(&mut self.stage, Stage::Sort);
+        self.stage = match stage {
+            Stage::Sort if self.width >= len => {
+                if self.leaf || len < island_size {
+                    Stage::GatherLeaf(Vec::with_capacity(island_size.max(len)))
+                } else {
+                    Stage::Search {
+                        index: len / 2,
+                        backward: true,
+                    }
+                }
+            }
+            Stage::Sort => {
+                //merges the runs at `start`, the right one going first only when
+                //it is strictly smaller so that the sort is stable
+                let left_end = (self.start + self.width).min(len);
+                let right_end = (self.start + 2 * self.width).min(len);
+                let from_left = self.left < left_end
+                    && (self.right >= right_end
+                        || cmp_bounds(self.value(self.right), self.value(self.left), self.axis)
+                            != Ordering::Less);
+                if from_left {
+                    self.merged.push(self.at(self.left));
+                    self.left += 1;
+                } else {
+                    self.merged.push(self.at(self.right));
+                    self.right += 1;
+                }
+                if self.left == left_end && self.right == right_end {
+                    self.start = right_end;
+                    if self.start == len {
+                        core::mem::swap(&mut self.order, &mut self.merged);
+                        self.merged.clear();
+                        self.width *= 2;
+                        self.start = 0;
+                    }
+                    self.left = self.start;
+                    self.right = (self.start + self.width).min(len);
+                }
+                Stage::Sort
+            }
+            Stage::Search {
+                index,
+                backward: true,
+            } => {
+                if index > 0 && self.key(index - 1) >= self.key(index) {
+                    Stage::Search {
+                        index: index - 1,
+                        backward: true,
+                    }
+                } else if index > 0 {
+                    self.split_at(index)
+                } else {
+                    Stage::Search {
+                        index: len / 2,
+                        backward: false,
+                    }
+                }
+            }
+            Stage::Search {
+                index,
+                backward: false,
+            } => {
+                if index >= len {
+                    //the values can't be split along this axis
+                    if self.axis == self.vertical {
+                        self.sort_along(!self.vertical);
+                    } else {
+                        self.leaf = true;
+                        self.sort_along(self.vertical);
+                    }
+                    return None;
+                } else if self.key(index) > self.key(0) {
+                    self.split_at(index)
+                } else {
+                    Stage::Search {
+                        index: index + 1,
+                        backward: false,
+                    }
+                }
+            }
+            Stage::LeftMax { split, index, max } if index < split => {
+                let value = self.value(index);
+                let v_max = if self.axis {
+                    value.max_y()
+                } else {
+                    value.max_x()
+                };
+                Stage::LeftMax {
+                    split,
+                    index: index + 1,
+                    max: if raises_max(&max, &v_max) { v_max } else { max },
+                }
+            }
+            Stage::LeftMax { split, max, .. } => Stage::GatherNode {
+                median: self.key(split),
+                left_max: max,
+                split,
+                left: Vec::with_capacity(split),
+                right: Vec::with_capacity(len - split),
+            },
+            Stage::GatherLeaf(mut leaf) => {
+                if leaf.len() == len {
+                    return Some(Built::Leaf(leaf));
+                }
+                leaf.push(self.take(leaf.len()));
+                Stage::GatherLeaf(leaf)
+            }
+            Stage::GatherNode {
+                median,
+                left_max,
+                split,
+                mut left,
+                mut right,
+            } => {
+                let position = left.len() + right.len();
+                if position == len {
+                    return Some(Built::Node {
+                        vertical: self.axis,
+                        median,
+                        left_max,
+                        left,
+                        right,
+                    });
+                }
+                let value = Some(self.take(position));
+                if position < split {
+                    left.push(value);
+                } else {
+                    right.push(value);
+                }
+                Stage::GatherNode {
+                    median,
+                    left_max,
+                    split,
+                    left,
+                    right,
+                }
+            }
+        };
+        None
+    }
+
+    fn split_at(&self, split: usize) -> Stage<Value> {
+        let value = self.value(0);
+        Stage::LeftMax {
+            split,
+            index: 1,
+            max: if self.axis {
+                value.max_y()
+            } else {
+                value.max_x()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IncrementalBuild, KdTreeBuilder};
+    use crate::{tests::TestValue, KdTree, KdValue};
+    use core::{cell::Cell, task::Poll};
+
+    fn values() -> Vec<TestValue> {
+        (0..500)
+            .map(|i| {
+                let x = i as f32;
+                let y = (i * 37 % 101) as f32;
+                TestValue::new(x, x + 1.5, y, y + 1.5)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn build() {
+        let values = values();
+        let mut tree = KdTree::<TestValue, 8>::build(values.clone());
+        assert_eq!(tree.len(), 500);
+        let expected = values
+            .iter()
+            .filter(|v| v.min_x <= 120. && v.max_x >= 100. && v.min_y <= 60. && v.max_y >= 40.)
+            .count();
+        assert_eq!(tree.query_rect(100., 120., 40., 60.).count(), expected);
+        for value in &values {
+            assert!(tree.remove_one(value.clone()));
+        }
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn build_ignores_order() {
+        //many values sharing positions along both axes
+        let values: Vec<_> = (0..300)
+            .map(|i| {
+                let (x, y) = ((i % 7) as f32, (i % 11) as f32);
+                TestValue::new(x, x + (i % 3) as f32, y, y + (i % 5) as f32)
+            })
+            .collect();
+        let mut shuffled = values.clone();
+        shuffled.reverse();
+        shuffled.rotate_left(123);
+        let a = KdTree::<TestValue, 8>::build(values);
+        let b = KdTree::<TestValue, 8>::build(shuffled);
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+
+    #[test]
+    fn streaming_build() {
+        let values = values();
+        let mut builder = KdTreeBuilder::<TestValue, 8>::with_chunk_size(64);
+        for chunk in values.chunks(50) {
+            builder.extend_chunk(chunk.iter().cloned());
+        }
+        let tree = builder.finish();
+        assert_eq!(tree.len(), 500);
+        let expected = values
+            .iter()
+            .filter(|v| v.min_x <= 300.5 && v.max_x >= 300.5 && v.min_y <= 30. && v.max_y >= 30.)
+            .count();
+        assert_eq!(tree.query_point(300.5, 30.).count(), expected);
+    }
+
+    #[test]
+    fn incremental_build() {
+        let values = values();
+        let mut build = IncrementalBuild::<TestValue, 8>::new(values.clone());
+        let mut steps = 0;
+        let tree = loop {
+            steps += 1;
+            if let Poll::Ready(tree) = build.poll_step(100) {
+                break tree;
+            }
+        };
+        assert!(steps > 1);
+        assert_eq!(tree.len(), 500);
+        let balanced = KdTree::<TestValue, 8>::build(values);
+        assert_eq!(format!("{:?}", tree), format!("{:?}", balanced));
+    }
+
+    thread_local! {
+        static READS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct Counted(TestValue);
+    impl KdValue for Counted {
+        type Position = f32;
+        fn min_x(&self) -> f32 {
+            READS.with(|reads| reads.set(reads.get() + 1));
+            self.0.min_x
+        }
+        fn min_y(&self) -> f32 {
+            READS.with(|reads| reads.set(reads.get() + 1));
+            self.0.min_y
+        }
+        fn max_x(&self) -> f32 {
+            READS.with(|reads| reads.set(reads.get() + 1));
+            self.0.max_x
+        }
+        fn max_y(&self) -> f32 {
+            READS.with(|reads| reads.set(reads.get() + 1));
+            self.0.max_y
+        }
+    }
+
+    #[test]
+    fn incremental_budget() {
+        let values: Vec<_> = (0..10_000)
+            .map(|i| {
+                let (x, y) = ((i * 37 % 101) as f32, (i * 11 % 97) as f32);
+                Counted(TestValue::new(x, x + 1., y, y + 1.))
+            })
+            .collect();
+        let mut build = IncrementalBuild::<Counted, 8>::new(values.clone());
+        let tree = loop {
+            READS.with(|reads| reads.set(0));
+            let step = build.poll_step(16);
+            //a comparison reads at most the four bounds of both values
+            assert!(READS.with(Cell::get) <= 16 * 8);
+            if let Poll::Ready(tree) = step {
+                break tree;
+            }
+        };
+        let balanced = KdTree::<Counted, 8>::build(values);
+        assert_eq!(format!("{:?}", tree), format!("{:?}", balanced));
+    }
+}
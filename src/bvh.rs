@@ -0,0 +1,309 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Aabb, KdValue, Scalar};
+
+const BINS: usize = 16;
+
+/// A binary bounding volume hierarchy, built once with the surface area heuristic.
+///
+/// Every node stores the full box of its subtree, so queries prune better than on
+/// the k-d tree, at the cost of building the whole hierarchy up front. Values can
+/// still be moved in place with `values_mut` followed by `refit`.
+#[derive(Debug)]
+pub struct Bvh<Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    values: Vec<Value>,
+    //parents always come before their children
+    nodes: Vec<BvhNode<Value::Position>>,
+}
+
+#[derive(Debug)]
+struct BvhNode<P> {
+    bounds: Aabb<P>,
+    kind: BvhKind,
+}
+
+#[derive(Debug)]
+enum BvhKind {
+    Leaf { first: usize, count: usize },
+    Branch { left: usize, right: usize },
+}
+
+impl<Value: KdValue> Bvh<Value>
+where
+    Value::Position: Scalar,
+{
+    /// Builds the hierarchy, with at most `leaf_size` values per leaf.
+    pub fn build(mut values: Vec<Value>, leaf_size: usize) -> Self {
+        let mut bvh = Self {
+            values: Vec::new(),
+            nodes: Vec::new(),
+        };
+        if !values.is_empty() {
+            let len = values.len();
+            bvh.build_node(&mut values, 0, len, leaf_size.max(1));
+        }
+        bvh.values = values;
+        bvh
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The values, in the order of the leaves.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Gives mutable access to the values; `refit` must be called after moving any.
+    pub fn values_mut(&mut self) -> &mut [Value] {
+        &mut self.values
+    }
+
+    /// Recomputes the boxes of every node from the values, keeping the hierarchy.
+    pub fn refit(&mut self) {
+        for index in (0..self.nodes.len()).rev() {
+            let bounds = match self.nodes[index].kind {
+                BvhKind::Leaf { first, count } => bounds_of(&self.values[first..first + count]),
+                BvhKind::Branch { left, right } => {
+                    self.nodes[left].bounds.union(&self.nodes[right].bounds)
+                }
+            };
+            self.nodes[index].bounds = bounds;
+        }
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> BvhRectQuery<'_, Value> {
+        BvhRectQuery {
+            bvh: self,
+            stack: if self.nodes.is_empty() {
+                vec![]
+            } else {
+                vec![0]
+            },
+            values: [].iter(),
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+        }
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> BvhRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    fn build_node(
+        &mut self,
+        values: &mut [Value],
+        first: usize,
+        count: usize,
+        leaf_size: usize,
+    ) -> usize {
+        let slice = &mut values[first..first + count];
+        let bounds = bounds_of(slice);
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds,
+            kind: BvhKind::Leaf { first, count },
+        });
+        if count <= leaf_size {
+            return index;
+        }
+        let split = match best_split(slice) {
+            Some(split) => split,
+            None => return index,
+        };
+        //a leaf is cheaper than any split for small enough nodes
+        let leaf_cost = count as f64 * area(&bounds);
+        if count <= 4 * leaf_size && split.cost >= leaf_cost {
+            return index;
+        }
+        let center = |value: &Value| {
+            if split.vertical {
+                (value.min_y() + value.max_y()).to_f64()
+            } else {
+                (value.min_x() + value.max_x()).to_f64()
+            }
+        };
+        let mut left_count = 0;
+        for i in 0..slice.len() {
+            if center(&slice[i]) < split.position {
+                slice.swap(i, left_count);
+                left_count += 1;
+            }
+        }
+        if left_count == 0 || left_count == count {
+            left_count = count / 2;
+        }
+        let left = self.build_node(values, first, left_count, leaf_size);
+        let right = self.build_node(values, first + left_count, count - left_count, leaf_size);
+        self.nodes[index].kind = BvhKind::Branch { left, right };
+        index
+    }
+}
+
+fn bounds_of<Value: KdValue>(values: &[Value]) -> Aabb<Value::Position>
+where
+    Value::Position: Scalar,
+{
+    let first = Aabb::of(&values[0]);
+    values[1..]
+        .iter()
+        .fold(first, |bounds, value| bounds.union(&Aabb::of(value)))
+}
+
+fn area<P: Scalar>(bounds: &Aabb<P>) -> f64 {
+    //the perimeter is the 2D equivalent of the surface area
+    (bounds.max_x - bounds.min_x).to_f64() + (bounds.max_y - bounds.min_y).to_f64()
+}
+
+struct Split {
+    vertical: bool,
+    //in doubled coordinates, to compare with min + max
+    position: f64,
+    cost: f64,
+}
+
+//bins the centers of the values along both axes, and finds the split between bins
+//minimizing the surface area heuristic
+fn best_split<Value: KdValue>(values: &[Value]) -> Option<Split>
+where
+    Value::Position: Scalar,
+{
+    let mut best: Option<Split> = None;
+    for vertical in [false, true] {
+        let center = |value: &Value| {
+            if vertical {
+                (value.min_y() + value.max_y()).to_f64()
+            } else {
+                (value.min_x() + value.max_x()).to_f64()
+            }
+        };
+        let (low, high) = values
+            .iter()
+            .map(center)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), c| {
+                (low.min(c), high.max(c))
+            });
+        if high <= low || !(high - low).is_finite() {
+            continue;
+        }
+        let bin_of = |c: f64| (((c - low) / (high - low) * BINS as f64) as usize).min(BINS - 1);
+        let mut bins: [(usize, Option<Aabb<Value::Position>>); BINS] = [(0, None); BINS];
+        for value in values {
+            let bin = &mut bins[bin_of(center(value))];
+            bin.0 += 1;
+            let bounds = Aabb::of(value);
+            bin.1 = Some(bin.1.map_or(bounds, |b| b.union(&bounds)));
+        }
+        let sweep = |bins: &mut dyn Iterator<Item = &(usize, Option<Aabb<Value::Position>>)>| {
+            let mut count = 0;
+            let mut bounds: Option<Aabb<Value::Position>> = None;
+            bins.map(|(c, b)| {
+                count += c;
+                if let Some(b) = b {
+                    bounds = Some(bounds.map_or(*b, |bounds| bounds.union(b)));
+                }
+                count as f64 * bounds.as_ref().map_or(0., area)
+            })
+            .collect::<Vec<_>>()
+        };
+        let left = sweep(&mut bins.iter());
+        let mut right = sweep(&mut bins.iter().rev());
+        right.reverse();
+        for bin in 0..BINS - 1 {
+            let cost = left[bin] + right[bin + 1];
+            if best.as_ref().is_none_or(|best| cost < best.cost) {
+                best = Some(Split {
+                    vertical,
+                    position: low + (high - low) * (bin + 1) as f64 / BINS as f64,
+                    cost,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// The values of a `Bvh` overlapping a rectangle.
+#[derive(Debug)]
+pub struct BvhRectQuery<'a, Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    bvh: &'a Bvh<Value>,
+    stack: Vec<usize>,
+    values: core::slice::Iter<'a, Value>,
+    rect: Aabb<Value::Position>,
+}
+
+impl<'a, Value: KdValue> Iterator for BvhRectQuery<'a, Value>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for value in self.values.by_ref() {
+                if Aabb::of(value).overlaps(&self.rect) {
+                    return Some(value);
+                }
+            }
+            let node = &self.bvh.nodes[self.stack.pop()?];
+            if !node.bounds.overlaps(&self.rect) {
+                continue;
+            }
+            match node.kind {
+                BvhKind::Leaf { first, count } => {
+                    self.values = self.bvh.values[first..first + count].iter()
+                }
+                BvhKind::Branch { left, right } => self.stack.extend([right, left]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bvh;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn bvh() {
+        let values: Vec<_> = (0..300)
+            .map(|i| {
+                let (x, y) = ((i * 37 % 101) as f32, (i * 53 % 97) as f32);
+                let size = (i % 7) as f32;
+                TestValue::new(x, x + size, y, y + size)
+            })
+            .collect();
+        let mut bvh = Bvh::build(values.clone(), 4);
+        let tree = KdTree::<TestValue, 8>::build(values);
+        assert_eq!(bvh.len(), 300);
+        for (min_x, max_x, min_y, max_y) in [(10., 30., 40., 45.), (50., 50., 50., 50.)] {
+            assert_eq!(
+                bvh.query_rect(min_x, max_x, min_y, max_y).count(),
+                tree.query_rect(min_x, max_x, min_y, max_y).count()
+            );
+        }
+        for value in bvh.values_mut() {
+            value.min_x += 1000.;
+            value.max_x += 1000.;
+        }
+        bvh.refit();
+        assert_eq!(bvh.query_rect(0., 200., 0., 200.).count(), 0);
+        assert_eq!(bvh.query_rect(1000., 1200., 0., 200.).count(), 300);
+    }
+}
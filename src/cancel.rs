@@ -0,0 +1,49 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A flag checked by queries between nodes, to abort long traversals from
+/// another thread (or from the consumer of the query itself).
+#[derive(Debug, Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancelToken;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn cancel() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            tree.insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+        }
+        let token = CancelToken::new();
+        let mut query = tree.query_rect(0., 100., 0., 1.).with_cancel(&token);
+        assert!(query.next().is_some());
+        token.cancel();
+        //at most the rest of the current leaf is yielded
+        assert!(query.count() < 4);
+        assert_eq!(tree.query_point(50.5, 0.5).with_cancel(&token).count(), 0);
+        token.reset();
+        assert_eq!(tree.query_point(50.5, 0.5).with_cancel(&token).count(), 1);
+    }
+}
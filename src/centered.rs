@@ -0,0 +1,38 @@
+use crate::{KdTree, KdValue, RectQuery, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Like `query_rect`, for the rectangle centered on `(cx, cy)` and extending
+    /// by `hx` and `hy` on each side. Negative half extents match nothing.
+    pub fn query_rect_centered(
+        &self,
+        cx: Value::Position,
+        cy: Value::Position,
+        hx: Value::Position,
+        hy: Value::Position,
+    ) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(cx - hx, cx + hx, cy - hy, cy + hy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn query_rect_centered() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x + 1., x, x + 1.));
+        }
+        assert_eq!(
+            tree.query_rect_centered(50., 50., 5., 0.5).count(),
+            tree.query_rect(45., 55., 49.5, 50.5).count()
+        );
+        assert_eq!(tree.query_rect_centered(50.5, 50.5, 0., 0.).count(), 1);
+        assert_eq!(tree.query_rect_centered(50.5, 50.5, -1., 1.).count(), 0);
+    }
+}
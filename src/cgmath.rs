@@ -0,0 +1,99 @@
+use core::fmt::Debug;
+
+use cgmath::Point2;
+
+use crate::{Aabb, KdValue};
+
+/// A box of cgmath points, as cgmath has no box type of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CgmathBounds<S> {
+    pub min: Point2<S>,
+    pub max: Point2<S>,
+}
+
+impl<S> CgmathBounds<S> {
+    pub fn new(min: Point2<S>, max: Point2<S>) -> Self {
+        Self { min, max }
+    }
+}
+
+//cgmath points do not implement Default
+impl<S: Default> Default for CgmathBounds<S> {
+    fn default() -> Self {
+        Self::new(
+            Point2::new(S::default(), S::default()),
+            Point2::new(S::default(), S::default()),
+        )
+    }
+}
+
+impl<S: PartialOrd + Debug + Default + Clone> KdValue for CgmathBounds<S> {
+    type Position = S;
+
+    fn min_x(&self) -> S {
+        self.min.x.clone()
+    }
+
+    fn min_y(&self) -> S {
+        self.min.y.clone()
+    }
+
+    fn max_x(&self) -> S {
+        self.max.x.clone()
+    }
+
+    fn max_y(&self) -> S {
+        self.max.y.clone()
+    }
+}
+
+impl<S> From<CgmathBounds<S>> for Aabb<S> {
+    fn from(bounds: CgmathBounds<S>) -> Self {
+        Aabb {
+            min_x: bounds.min.x,
+            max_x: bounds.max.x,
+            min_y: bounds.min.y,
+            max_y: bounds.max.y,
+        }
+    }
+}
+
+impl<S> From<Aabb<S>> for CgmathBounds<S> {
+    fn from(bounds: Aabb<S>) -> Self {
+        Self::new(
+            Point2::new(bounds.min_x, bounds.min_y),
+            Point2::new(bounds.max_x, bounds.max_y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point2, Vector2};
+
+    use super::CgmathBounds;
+    use crate::{tests::interop_boxes, Aabb};
+
+    #[test]
+    fn cgmath_boxes() {
+        let hit = interop_boxes(
+            |x, y| {
+                let min = Point2::new(x as f32, y as f32);
+                CgmathBounds::new(min, min + Vector2::new(2., 2.))
+            },
+            |tree, x, y| {
+                tree.query_point_vec(Point2::new(x as f32, y as f32))
+                    .cloned()
+                    .collect()
+            },
+            |tree, [min_x, max_x, min_y, max_y]| {
+                let min = Point2::new(min_x as f32, min_y as f32);
+                tree.query_rect_vec(min, Point2::new(max_x as f32, max_y as f32))
+                    .count()
+            },
+        );
+        let bounds = Aabb::from(hit);
+        assert_eq!(bounds, Aabb::new(30., 32., 10., 12.));
+        assert_eq!(CgmathBounds::from(bounds), hit);
+    }
+}
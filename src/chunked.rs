@@ -0,0 +1,197 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{scalar::floor, Aabb, KdTree, KdValue, RectQuery, Scalar};
+
+/// A world split in square chunks of a fixed size, each with its own tree, which
+/// can be loaded and unloaded independently as the world is streamed.
+///
+/// Values belong to the chunk containing their minimum corner, and may extend over
+/// the neighbouring chunks: each chunk tracks the box covering its values, so that
+/// queries find values across chunk borders.
+#[derive(Debug)]
+pub struct ChunkedKdTree<Value: KdValue, const ISLAND_SIZE: usize>
+where
+    Value::Position: Scalar,
+{
+    chunk_size: Value::Position,
+    chunks: BTreeMap<(i64, i64), Chunk<Value, ISLAND_SIZE>>,
+}
+
+#[derive(Debug)]
+struct Chunk<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    //only grows, so it stays valid when values are removed
+    content: Option<Aabb<Value::Position>>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> ChunkedKdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    pub fn new(chunk_size: Value::Position) -> Self {
+        Self {
+            chunk_size,
+            chunks: BTreeMap::new(),
+        }
+    }
+
+    /// The chunk containing a position.
+    pub fn chunk_of(&self, x: Value::Position, y: Value::Position) -> (i64, i64) {
+        let cell =
+            |position: Value::Position| floor(position.to_f64() / self.chunk_size.to_f64()) as i64;
+        (cell(x), cell(y))
+    }
+
+    /// The bounds covered by a chunk.
+    pub fn chunk_bounds(&self, chunk: (i64, i64)) -> Aabb<Value::Position> {
+        let size = self.chunk_size.to_f64();
+        let (x, y) = (chunk.0 as f64 * size, chunk.1 as f64 * size);
+        Aabb::new(
+            Value::Position::from_f64(x),
+            Value::Position::from_f64(x + size),
+            Value::Position::from_f64(y),
+            Value::Position::from_f64(y + size),
+        )
+    }
+
+    pub fn is_loaded(&self, chunk: (i64, i64)) -> bool {
+        self.chunks.contains_key(&chunk)
+    }
+
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Loads the values of a chunk, replacing the chunk if it was already loaded.
+    /// The values are expected to start inside the chunk.
+    pub fn load(&mut self, chunk: (i64, i64), tree: KdTree<Value, ISLAND_SIZE>) {
+        let content = tree.iter().fold(None, |content: Option<Aabb<_>>, value| {
+            let bounds = Aabb::of(value);
+            Some(content.map_or(bounds, |content| content.union(&bounds)))
+        });
+        self.chunks.insert(chunk, Chunk { tree, content });
+    }
+
+    /// Unloads a chunk, giving its tree back so that it can be saved.
+    pub fn unload(&mut self, chunk: (i64, i64)) -> Option<KdTree<Value, ISLAND_SIZE>> {
+        self.chunks.remove(&chunk).map(|chunk| chunk.tree)
+    }
+
+    pub fn chunk(&self, chunk: (i64, i64)) -> Option<&KdTree<Value, ISLAND_SIZE>> {
+        self.chunks.get(&chunk).map(|chunk| &chunk.tree)
+    }
+
+    /// Inserts a value in its chunk, loading an empty chunk if needed.
+    pub fn insert(&mut self, value: Value) {
+        let chunk = self.chunk_of(value.min_x(), value.min_y());
+        let chunk = self.chunks.entry(chunk).or_insert_with(|| Chunk {
+            tree: KdTree::default(),
+            content: None,
+        });
+        let bounds = Aabb::of(&value);
+        chunk.content = Some(match &chunk.content {
+            Some(content) => content.union(&bounds),
+            None => bounds,
+        });
+        chunk.tree.insert(value);
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        let chunk = self.chunk_of(value.min_x(), value.min_y());
+        match self.chunks.get_mut(&chunk) {
+            Some(chunk) => chunk.tree.remove_one(value),
+            None => false,
+        }
+    }
+
+    /// The number of values in the loaded chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.tree.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.values().all(|chunk| chunk.tree.is_empty())
+    }
+
+    /// The values of the loaded chunks overlapping the rectangle.
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> ChunkedRectQuery<'_, Value, ISLAND_SIZE> {
+        let rect = Aabb::new(min_x, max_x, min_y, max_y);
+        ChunkedRectQuery {
+            trees: self
+                .chunks
+                .values()
+                .filter(|chunk| matches!(&chunk.content, Some(content) if content.overlaps(&rect)))
+                .map(|chunk| &chunk.tree)
+                .collect(),
+            query: None,
+            rect,
+        }
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> ChunkedRectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(x, x, y, y)
+    }
+}
+
+/// The values of a `ChunkedKdTree` overlapping a rectangle.
+pub struct ChunkedRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    //the trees left to query, last first
+    trees: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
+    query: Option<RectQuery<'a, Value, ISLAND_SIZE>>,
+    rect: Aabb<Value::Position>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for ChunkedRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.query.as_mut().and_then(Iterator::next) {
+                return Some(value);
+            }
+            let tree = self.trees.pop()?;
+            let rect = self.rect;
+            self.query = Some(tree.query_rect(rect.min_x, rect.max_x, rect.min_y, rect.max_y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkedKdTree;
+    use crate::tests::TestValue;
+
+    #[test]
+    fn chunks() {
+        let mut world = ChunkedKdTree::<TestValue, 4>::new(16.);
+        for i in 0..40 {
+            let x = i as f32 * 2. - 20.;
+            world.insert(TestValue::new(x, x + 3., 0., 3.));
+        }
+        assert_eq!(world.loaded_chunks().count(), 6);
+        //values starting in the chunk on the left overlap the border
+        assert_eq!(world.query_point(16.5, 1.).count(), 2);
+        assert_eq!(world.query_rect(-1., 1., 0., 0.).count(), 3);
+        let unloaded = world.unload((1, 0)).unwrap();
+        assert_eq!(unloaded.len(), 8);
+        assert_eq!(world.query_point(16.5, 1.).count(), 1);
+        world.load((1, 0), unloaded);
+        assert_eq!(world.query_point(16.5, 1.).count(), 2);
+        assert!(world.remove_one(TestValue::new(14., 17., 0., 3.)));
+        assert_eq!(world.query_point(16.5, 1.).count(), 1);
+    }
+}
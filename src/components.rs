@@ -0,0 +1,197 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    vec::Vec,
+};
+
+use crate::{KdTree, KdValue, RectQuery, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Groups the values transitively connected by overlaps, isolated values
+    /// making groups of their own.
+    ///
+    /// Groups are ordered by their first value, and values in tree order.
+    pub fn overlap_components(&self) -> Vec<Vec<&Value>> {
+        self.components_linked_by(|_, _| true)
+    }
+
+    //the groups of values transitively connected by the overlapping pairs kept by
+    //`linked`
+    fn components_linked_by(&self, linked: impl Fn(&Value, &Value) -> bool) -> Vec<Vec<&Value>> {
+        let values: Vec<&Value> = self.iter().collect();
+        let indices: BTreeMap<*const Value, usize> = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (*value as *const Value, index))
+            .collect();
+        let mut parents: Vec<usize> = (0..values.len()).collect();
+        self.for_each_pair(|a, b| {
+            if !linked(a, b) {
+                return;
+            }
+            let a = find(&mut parents, indices[&(a as *const Value)]);
+            let b = find(&mut parents, indices[&(b as *const Value)]);
+            //the smallest index stays the root, to keep groups in tree order
+            if a < b {
+                parents[b] = a;
+            } else {
+                parents[a] = b;
+            }
+        });
+        let mut groups: BTreeMap<usize, Vec<&Value>> = BTreeMap::new();
+        for (index, value) in values.into_iter().enumerate() {
+            let root = find(&mut parents, index);
+            groups.entry(root).or_default().push(value);
+        }
+        groups.into_values().collect()
+    }
+
+    /// The values reachable from `seed` through chains of overlapping values,
+    /// breadth first, starting with the values overlapping the seed (including
+    /// itself if it is in the tree).
+    ///
+    /// The tree is walked lazily, one query per value reached.
+    pub fn reachable_from(&self, seed: &Value) -> Reachable<'_, Value, ISLAND_SIZE> {
+        Reachable {
+            tree: self,
+            query: Some(self.query_rect(seed.min_x(), seed.max_x(), seed.min_y(), seed.max_y())),
+            queue: VecDeque::new(),
+            seen: BTreeSet::new(),
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Groups of at least two values transitively connected by overlaps of an area
+    /// of at least `min_overlap_area`, such as duplicated or conflicting geometry.
+    ///
+    /// Groups are ordered by their first value, and values in tree order.
+    pub fn find_overlapping_groups(&self, min_overlap_area: f64) -> Vec<Vec<&Value>> {
+        let mut groups = self.components_linked_by(|a, b| {
+            let width = a.max_x().to_f64().min(b.max_x().to_f64())
+                - a.min_x().to_f64().max(b.min_x().to_f64());
+            let height = a.max_y().to_f64().min(b.max_y().to_f64())
+                - a.min_y().to_f64().max(b.min_y().to_f64());
+            width * height >= min_overlap_area
+        });
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+}
+
+/// The values reachable from a seed through overlaps.
+pub struct Reachable<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: &'a KdTree<Value, ISLAND_SIZE>,
+    //the neighbours of the value being expanded
+    query: Option<RectQuery<'a, Value, ISLAND_SIZE>>,
+    //the values reached but not expanded yet
+    queue: VecDeque<&'a Value>,
+    seen: BTreeSet<*const Value>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for Reachable<'a, Value, ISLAND_SIZE> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(query) = &mut self.query {
+                for value in query.by_ref() {
+                    if self.seen.insert(value as *const Value) {
+                        self.queue.push_back(value);
+                        return Some(value);
+                    }
+                }
+            }
+            let value = self.queue.pop_front()?;
+            self.query = Some(self.tree.query_rect(
+                value.min_x(),
+                value.max_x(),
+                value.min_y(),
+                value.max_y(),
+            ));
+        }
+    }
+}
+
+//finds the root of a union-find set, halving the path on the way
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn overlap_components() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        //a chain of 5 boxes, a pair, and a lone box
+        for i in 0..5 {
+            let x = i as f32 * 2.;
+            tree.insert(Aabb::new(x, x + 2.5, 0., 1. + i as f32));
+        }
+        tree.insert(Aabb::new(50., 52., 50., 52.));
+        tree.insert(Aabb::new(51., 53., 51., 53.));
+        tree.insert(Aabb::new(-50., -49., 0., 1.));
+        let mut sizes: Vec<_> = tree
+            .overlap_components()
+            .iter()
+            .map(|group| group.len())
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 2, 5]);
+    }
+
+    #[test]
+    fn overlapping_groups() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        //a duplicate, a near duplicate, and a box only touching them
+        tree.insert(Aabb::new(0., 10., 0., 10.));
+        tree.insert(Aabb::new(0., 10., 0., 10.));
+        tree.insert(Aabb::new(1., 11., 0., 10.));
+        tree.insert(Aabb::new(10.5, 20., 0., 10.));
+        //a chain linked by large overlaps
+        tree.insert(Aabb::new(50., 60., 0., 10.));
+        tree.insert(Aabb::new(55., 65., 0., 10.));
+        tree.insert(Aabb::new(60., 70., 0., 10.));
+        tree.insert(Aabb::new(100., 101., 0., 1.));
+        let mut sizes: Vec<_> = tree
+            .find_overlapping_groups(40.)
+            .iter()
+            .map(|group| group.len())
+            .collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![3, 3]);
+        assert_eq!(tree.find_overlapping_groups(0.).len(), 2);
+        assert_eq!(tree.find_overlapping_groups(95.).len(), 1);
+        assert!(tree.find_overlapping_groups(200.).is_empty());
+    }
+
+    #[test]
+    fn reachable_from() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..10 {
+            let x = i as f32 * 2.;
+            tree.insert(Aabb::new(x, x + 2.5, 0., 1. + i as f32));
+        }
+        tree.insert(Aabb::new(50., 52., 50., 52.));
+        let seed = Aabb::new(14., 16.5, 0., 8.);
+        let reached: Vec<_> = tree.reachable_from(&seed).collect();
+        assert_eq!(reached.len(), 10);
+        assert!(reached.contains(&&seed));
+        assert_eq!(
+            tree.reachable_from(&Aabb::new(50., 51., 50., 51.)).count(),
+            1
+        );
+        assert_eq!(
+            tree.reachable_from(&Aabb::new(30., 31., 30., 31.)).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use crate::{KdValue, PersistentKdTree, PersistentRectQuery};
+
+/// A tree shared between many reading threads and one writer at a time.
+///
+/// Readers get a `ReadGuard` on the last published epoch, and query it without
+/// any lock. The writer works on its own copy-on-write version of the tree, which
+/// becomes the next epoch when its `WriteGuard` is dropped.
+///
+/// The current epoch is published through an atomic pointer, so `read` never
+/// blocks, even while a writer publishes its changes. The epochs replaced by
+/// writers are freed by the first writer to find no reader in the middle of
+/// `read`, so while reads never stop they are kept until a later write.
+#[derive(Debug)]
+pub struct ConcurrentKdTree<Value: KdValue, const ISLAND_SIZE: usize> {
+    //an `Arc` turned into a raw pointer, only swapped by the writer
+    published: AtomicPtr<Epoch<Value, ISLAND_SIZE>>,
+    //the readers between loading `published` and cloning the epoch it points to
+    readers: AtomicUsize,
+    writer: Mutex<Writer<Value, ISLAND_SIZE>>,
+    //the tree owns epochs, for it to be `Send` and `Sync` like them
+    epochs: PhantomData<Arc<Epoch<Value, ISLAND_SIZE>>>,
+}
+
+#[derive(Debug)]
+struct Writer<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: PersistentKdTree<Value, ISLAND_SIZE>,
+    //replaced epochs which readers may still be cloning
+    retired: Vec<Arc<Epoch<Value, ISLAND_SIZE>>>,
+}
+
+#[derive(Debug)]
+struct Epoch<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: PersistentKdTree<Value, ISLAND_SIZE>,
+    epoch: u64,
+}
+
+/// A consistent view of the tree as of one epoch.
+#[derive(Debug)]
+pub struct ReadGuard<Value: KdValue, const ISLAND_SIZE: usize> {
+    epoch: Arc<Epoch<Value, ISLAND_SIZE>>,
+}
+
+/// Exclusive write access to the tree, publishing the changes when dropped.
+pub struct WriteGuard<'a, Value: KdValue, const ISLAND_SIZE: usize>
+where
+    Value::Position: Clone,
+{
+    owner: &'a ConcurrentKdTree<Value, ISLAND_SIZE>,
+    writer: MutexGuard<'a, Writer<Value, ISLAND_SIZE>>,
+    modified: bool,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Default for ConcurrentKdTree<Value, ISLAND_SIZE> {
+    fn default() -> Self {
+        Self::new(PersistentKdTree::default())
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> ConcurrentKdTree<Value, ISLAND_SIZE> {
+    pub fn new(tree: PersistentKdTree<Value, ISLAND_SIZE>) -> Self {
+        let epoch = Arc::new(Epoch {
+            tree: tree.clone(),
+            epoch: 0,
+        });
+        Self {
+            published: AtomicPtr::new(Arc::into_raw(epoch) as *mut _),
+            readers: AtomicUsize::new(0),
+            writer: Mutex::new(Writer {
+                tree,
+                retired: Vec::new(),
+            }),
+            epochs: PhantomData,
+        }
+    }
+
+    pub fn read(&self) -> ReadGuard<Value, ISLAND_SIZE> {
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        let published = self.published.load(Ordering::SeqCst);
+        //the epoch is not freed while this thread is counted in `readers`: writers
+        //only free the epochs they replaced once they see no readers, after the
+        //swap, so after any reader which could have loaded them is done
+        let epoch = unsafe {
+            Arc::increment_strong_count(published);
+            Arc::from_raw(published)
+        };
+        self.readers.fetch_sub(1, Ordering::SeqCst);
+        ReadGuard { epoch }
+    }
+
+    /// Waits for the previous writer to be done, if any.
+    pub fn write(&self) -> WriteGuard<'_, Value, ISLAND_SIZE>
+    where
+        Value::Position: Clone,
+    {
+        WriteGuard {
+            owner: self,
+            writer: self.writer.lock().unwrap_or_else(|e| e.into_inner()),
+            modified: false,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.read().epoch()
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Drop for ConcurrentKdTree<Value, ISLAND_SIZE> {
+    fn drop(&mut self) {
+        //the published epoch holds a reference given up by `Arc::into_raw`
+        drop(unsafe { Arc::from_raw(*self.published.get_mut()) });
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for ReadGuard<Value, ISLAND_SIZE> {
+    fn clone(&self) -> Self {
+        Self {
+            epoch: Arc::clone(&self.epoch),
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> ReadGuard<Value, ISLAND_SIZE> {
+    pub fn epoch(&self) -> u64 {
+        self.epoch.epoch
+    }
+
+    pub fn tree(&self) -> &PersistentKdTree<Value, ISLAND_SIZE> {
+        &self.epoch.tree
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> ReadGuard<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.epoch.tree.query_point(x, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.epoch.tree.query_rect(min_x, max_x, min_y, max_y)
+    }
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> WriteGuard<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    pub fn insert(&mut self, value: Value) {
+        self.modified = true;
+        self.writer.tree.insert(value)
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        let removed = self.writer.tree.remove_one(value);
+        self.modified |= removed;
+        removed
+    }
+
+    pub fn tree(&self) -> &PersistentKdTree<Value, ISLAND_SIZE> {
+        &self.writer.tree
+    }
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Drop for WriteGuard<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn drop(&mut self) {
+        if !self.modified {
+            return;
+        }
+        let tree = self.writer.tree.clone();
+        //only the writer swaps the published epoch, so it is still alive here
+        let epoch = unsafe { &*self.owner.published.load(Ordering::SeqCst) }.epoch + 1;
+        let next = Arc::into_raw(Arc::new(Epoch { tree, epoch })) as *mut _;
+        let previous = self.owner.published.swap(next, Ordering::SeqCst);
+        self.writer.retired.push(unsafe { Arc::from_raw(previous) });
+        if self.owner.readers.load(Ordering::SeqCst) == 0 {
+            //the last references to some nodes may be freed with them
+            self.writer.retired.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConcurrentKdTree;
+    use crate::tests::TestValue;
+
+    #[test]
+    fn concurrent_reads() {
+        let tree = ConcurrentKdTree::<TestValue, 4>::default();
+        {
+            let mut writer = tree.write();
+            for i in 0..30 {
+                writer.insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+            }
+        }
+        let before = tree.read();
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    let guard = tree.read();
+                    scope.spawn(move || guard.query_rect(0., 100., 0., 1.).count())
+                })
+                .collect();
+            let mut writer = tree.write();
+            writer.insert(TestValue::new(50., 51., 0., 1.));
+            drop(writer);
+            for reader in readers {
+                assert_eq!(reader.join().unwrap(), 30);
+            }
+        });
+        assert_eq!(before.epoch(), 1);
+        assert_eq!(tree.epoch(), 2);
+        assert_eq!(tree.read().query_rect(0., 100., 0., 1.).count(), 31);
+    }
+
+    #[test]
+    fn reads_during_writes() {
+        let tree = ConcurrentKdTree::<TestValue, 4>::default();
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut last = 0;
+                        while last < 200 {
+                            let guard = tree.read();
+                            //each epoch has one more value than the previous one
+                            let count = guard.query_rect(0., 1000., 0., 1.).count();
+                            assert_eq!(count as u64, guard.epoch());
+                            assert!(guard.epoch() >= last);
+                            last = guard.epoch();
+                        }
+                    })
+                })
+                .collect();
+            for i in 0..200 {
+                tree.write()
+                    .insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+            }
+            for reader in readers {
+                reader.join().unwrap();
+            }
+        });
+        assert_eq!(tree.epoch(), 200);
+        //without readers, the replaced epochs are freed by the next write
+        let mut writer = tree.write();
+        writer.insert(TestValue::new(0., 1., 0., 1.));
+        drop(writer);
+        assert!(tree.write().writer.retired.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{cmp_position, KdTree, KdValue, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// The area of the union of the values, clipped to the rectangle. Overlapping
+    /// values are only counted once.
+    ///
+    /// Sweeps a line along x over the matches, keeping the covered length along y
+    /// in a segment tree, in `O(k log k)` for `k` matches.
+    pub fn covered_area_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> f64 {
+        let clip = [
+            min_x.to_f64(),
+            max_x.to_f64(),
+            min_y.to_f64(),
+            max_y.to_f64(),
+        ];
+        let mut ys = Vec::new();
+        //(x, y range, +1 when entering a box or -1 when leaving it)
+        let mut events = Vec::new();
+        for value in self.query_rect(min_x, max_x, min_y, max_y) {
+            let x0 = value.min_x().to_f64().max(clip[0]);
+            let x1 = value.max_x().to_f64().min(clip[1]);
+            let y0 = value.min_y().to_f64().max(clip[2]);
+            let y1 = value.max_y().to_f64().min(clip[3]);
+            if x0 < x1 && y0 < y1 {
+                ys.push(y0);
+                ys.push(y1);
+                events.push((x0, y0, y1, 1));
+                events.push((x1, y0, y1, -1));
+            }
+        }
+        if events.is_empty() {
+            return 0.;
+        }
+        ys.sort_unstable_by(cmp_position);
+        ys.dedup();
+        events.sort_unstable_by(|a, b| cmp_position(&a.0, &b.0));
+        let mut cover = CoverTree::new(ys);
+        let mut area = 0.;
+        let mut previous_x = events[0].0;
+        for (x, y0, y1, delta) in events {
+            area += cover.covered() * (x - previous_x);
+            cover.add(y0, y1, delta);
+            previous_x = x;
+        }
+        area
+    }
+}
+
+//a segment tree over the intervals between consecutive ys, keeping how many
+//boxes cover each node entirely and the covered length below it
+struct CoverTree {
+    ys: Vec<f64>,
+    count: Vec<i32>,
+    covered: Vec<f64>,
+}
+
+impl CoverTree {
+    fn new(ys: Vec<f64>) -> Self {
+        let size = 4 * ys.len();
+        Self {
+            ys,
+            count: vec![0; size],
+            covered: vec![0.; size],
+        }
+    }
+
+    fn covered(&self) -> f64 {
+        self.covered[1]
+    }
+
+    fn add(&mut self, y0: f64, y1: f64, delta: i32) {
+        let start = self.ys.partition_point(|y| *y < y0);
+        let end = self.ys.partition_point(|y| *y < y1);
+        self.update(1, 0, self.ys.len() - 1, start, end, delta);
+    }
+
+    //node covers the intervals [low, high), and the update [start, end)
+    fn update(
+        &mut self,
+        node: usize,
+        low: usize,
+        high: usize,
+        start: usize,
+        end: usize,
+        delta: i32,
+    ) {
+        if end <= low || high <= start {
+            return;
+        }
+        if start <= low && high <= end {
+            self.count[node] += delta;
+        } else {
+            let middle = (low + high) / 2;
+            self.update(2 * node, low, middle, start, end, delta);
+            self.update(2 * node + 1, middle, high, start, end, delta);
+        }
+        self.covered[node] = if self.count[node] > 0 {
+            self.ys[high] - self.ys[low]
+        } else if high - low == 1 {
+            0.
+        } else {
+            self.covered[2 * node] + self.covered[2 * node + 1]
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn covered_area() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        tree.insert(Aabb::new(0., 4., 0., 4.));
+        //overlaps the first one on a 2x2 square
+        tree.insert(Aabb::new(2., 6., 2., 6.));
+        //inside the first one
+        tree.insert(Aabb::new(1., 2., 1., 2.));
+        tree.insert(Aabb::new(10., 11., 10., 12.));
+        tree.insert(Aabb::new(20., 20., 0., 5.));
+        assert_eq!(tree.covered_area_in_rect(-10., 30., -10., 30.), 30.);
+        assert_eq!(tree.covered_area_in_rect(3., 10.5, 0., 10.5), 14.25);
+        assert_eq!(tree.covered_area_in_rect(7., 9., 7., 9.), 0.);
+
+        //many overlapping boxes against a fine grid estimate
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..40 {
+            let (x, y) = ((i * 7 % 23) as f32, (i * 5 % 17) as f32);
+            tree.insert(Aabb::new(x, x + 3., y, y + 2.));
+        }
+        let mut cells = 0;
+        for cx in 0..40 {
+            for cy in 0..40 {
+                let (x, y) = (cx as f32 * 0.5 + 0.25, cy as f32 * 0.5 + 0.25);
+                if tree.query_point(x, y).next().is_some() {
+                    cells += 1;
+                }
+            }
+        }
+        assert_eq!(
+            tree.covered_area_in_rect(0., 20., 0., 20.),
+            cells as f64 * 0.25
+        );
+    }
+}
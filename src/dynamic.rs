@@ -0,0 +1,473 @@
+use alloc::vec::Vec;
+
+use crate::{Aabb, KdValue, Scalar};
+
+/// Identifies a value stored in a `DynamicAabbTree`. It stays valid until the value
+/// is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProxyId(pub(crate) usize);
+
+/// A bounding volume hierarchy for scenes where everything moves all the time.
+///
+/// Each value is a leaf proxy with a box inflated by a margin. Leaves are inserted
+/// next to the sibling minimizing the perimeter of the hierarchy, and the tree is
+/// kept balanced with rotations on the way back up, so that it does not need to be
+/// rebuilt as values move around.
+#[derive(Debug)]
+pub struct DynamicAabbTree<Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    nodes: Vec<DynamicNode<Value>>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    margin: Value::Position,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct DynamicNode<Value: KdValue> {
+    bounds: Aabb<Value::Position>,
+    parent: Option<usize>,
+    height: usize,
+    kind: NodeKind<Value>,
+}
+
+#[derive(Debug)]
+enum NodeKind<Value> {
+    Leaf(Value),
+    Branch([usize; 2]),
+    Free,
+}
+
+impl<Value: KdValue> Default for DynamicAabbTree<Value>
+where
+    Value::Position: Scalar,
+{
+    fn default() -> Self {
+        Self::new(Value::Position::default())
+    }
+}
+
+impl<Value: KdValue> DynamicAabbTree<Value>
+where
+    Value::Position: Scalar,
+{
+    /// Creates a tree inflating leaves by `margin` on every side.
+    pub fn new(margin: Value::Position) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+            margin,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The height of the hierarchy, 0 for a single leaf.
+    pub fn height(&self) -> usize {
+        self.root.map_or(0, |root| self.nodes[root].height)
+    }
+
+    pub fn get(&self, id: ProxyId) -> Option<&Value> {
+        match self.nodes.get(id.0).map(|node| &node.kind) {
+            Some(NodeKind::Leaf(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The inflated box of a leaf.
+    pub fn fat_bounds(&self, id: ProxyId) -> Option<&Aabb<Value::Position>> {
+        self.get(id).map(|_| &self.nodes[id.0].bounds)
+    }
+
+    pub fn insert(&mut self, value: Value) -> ProxyId {
+        let bounds = self.fatten(&value);
+        let leaf = self.allocate(DynamicNode {
+            bounds,
+            parent: None,
+            height: 0,
+            kind: NodeKind::Leaf(value),
+        });
+        self.insert_leaf(leaf);
+        self.len += 1;
+        ProxyId(leaf)
+    }
+
+    pub fn remove(&mut self, id: ProxyId) -> Option<Value> {
+        self.get(id)?;
+        self.remove_leaf(id.0);
+        self.len -= 1;
+        self.free.push(id.0);
+        match core::mem::replace(&mut self.nodes[id.0].kind, NodeKind::Free) {
+            NodeKind::Leaf(value) => Some(value),
+            _ => unreachable!("checked above"),
+        }
+    }
+
+    /// Replaces a value, re-inserting its leaf only if it escaped its fat box.
+    /// Returns whether the hierarchy changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was removed.
+    pub fn update(&mut self, id: ProxyId, value: Value) -> bool {
+        let escaped = !self.fits(id, &value);
+        if escaped {
+            self.remove_leaf(id.0);
+            self.nodes[id.0].bounds = self.fatten(&value);
+            self.insert_leaf(id.0);
+        }
+        self.nodes[id.0].kind = NodeKind::Leaf(value);
+        escaped
+    }
+
+    /// Replaces a value, growing its leaf and the boxes of its ancestors in place
+    /// if it escaped its fat box, instead of re-inserting it. This is cheaper than
+    /// `update`, at the cost of a looser hierarchy over time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was removed.
+    pub fn refit(&mut self, id: ProxyId, value: Value) -> bool {
+        let escaped = !self.fits(id, &value);
+        if escaped {
+            let bounds = self.fatten(&value);
+            self.nodes[id.0].bounds = self.nodes[id.0].bounds.union(&bounds);
+            let mut index = self.nodes[id.0].parent;
+            while let Some(i) = index {
+                let [left, right] = self.children(i);
+                let bounds = self.nodes[left].bounds.union(&self.nodes[right].bounds);
+                //ancestors above an unchanged box already contain everything
+                if bounds == self.nodes[i].bounds {
+                    break;
+                }
+                self.nodes[i].bounds = bounds;
+                index = self.nodes[i].parent;
+            }
+        }
+        self.nodes[id.0].kind = NodeKind::Leaf(value);
+        escaped
+    }
+
+    /// Finds the proxy of a value, only visiting the branches containing it.
+    pub fn find(&self, value: &Value) -> Option<ProxyId> {
+        let bounds = Aabb::of(value);
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds.overlaps(&bounds) {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf(leaf) if leaf == value => return Some(ProxyId(index)),
+                NodeKind::Branch(children) => stack.extend_from_slice(children),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&Value, &Value)) {
+        for node in &self.nodes {
+            if let NodeKind::Leaf(value) = &node.kind {
+                let query =
+                    self.query_rect(value.min_x(), value.max_x(), value.min_y(), value.max_y());
+                for other in query {
+                    //each pair is found from both sides, only keep one of them
+                    if (value as *const Value) < (other as *const Value) {
+                        f(value, other)
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> DynamicRectQuery<'_, Value> {
+        DynamicRectQuery {
+            tree: self,
+            stack: self.root.into_iter().collect(),
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+        }
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> DynamicRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    fn fits(&self, id: ProxyId, value: &Value) -> bool {
+        let fat = self.fat_bounds(id).expect("the proxy was removed");
+        fat.min_x <= value.min_x()
+            && value.max_x() <= fat.max_x
+            && fat.min_y <= value.min_y()
+            && value.max_y() <= fat.max_y
+    }
+
+    fn fatten(&self, value: &Value) -> Aabb<Value::Position> {
+        Aabb::new(
+            value.min_x() - self.margin,
+            value.max_x() + self.margin,
+            value.min_y() - self.margin,
+            value.max_y() + self.margin,
+        )
+    }
+
+    fn allocate(&mut self, node: DynamicNode<Value>) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn children(&self, index: usize) -> [usize; 2] {
+        match self.nodes[index].kind {
+            NodeKind::Branch(children) => children,
+            _ => unreachable!("only branches have children"),
+        }
+    }
+
+    fn replace_child(&mut self, parent: Option<usize>, old: usize, new: usize) {
+        match parent {
+            Some(parent) => {
+                if let NodeKind::Branch(children) = &mut self.nodes[parent].kind {
+                    let slot = if children[0] == old { 0 } else { 1 };
+                    children[slot] = new;
+                }
+            }
+            None => self.root = Some(new),
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let root = match self.root {
+            Some(root) => root,
+            None => {
+                self.nodes[leaf].parent = None;
+                self.root = Some(leaf);
+                return;
+            }
+        };
+        //walks down towards the sibling which grows the perimeters the least
+        let bounds = self.nodes[leaf].bounds;
+        let mut index = root;
+        while let NodeKind::Branch(children) = self.nodes[index].kind {
+            let area = perimeter(&self.nodes[index].bounds);
+            let combined = perimeter(&self.nodes[index].bounds.union(&bounds));
+            let cost = 2. * combined;
+            let inheritance = 2. * (combined - area);
+            let child_cost = |child: usize| {
+                let node = &self.nodes[child];
+                let grown = perimeter(&node.bounds.union(&bounds));
+                match node.kind {
+                    NodeKind::Leaf(_) => grown + inheritance,
+                    _ => grown - perimeter(&node.bounds) + inheritance,
+                }
+            };
+            let (left, right) = (child_cost(children[0]), child_cost(children[1]));
+            if cost < left && cost < right {
+                break;
+            }
+            index = if left < right {
+                children[0]
+            } else {
+                children[1]
+            };
+        }
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let parent = self.allocate(DynamicNode {
+            bounds: self.nodes[sibling].bounds.union(&bounds),
+            parent: old_parent,
+            height: self.nodes[sibling].height + 1,
+            kind: NodeKind::Branch([sibling, leaf]),
+        });
+        self.replace_child(old_parent, sibling, parent);
+        self.nodes[sibling].parent = Some(parent);
+        self.nodes[leaf].parent = Some(parent);
+        self.fix_upwards(Some(parent));
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        let parent = match self.nodes[leaf].parent {
+            Some(parent) => parent,
+            None => {
+                self.root = None;
+                return;
+            }
+        };
+        let grand_parent = self.nodes[parent].parent;
+        let [left, right] = self.children(parent);
+        let sibling = if left == leaf { right } else { left };
+        self.replace_child(grand_parent, parent, sibling);
+        self.nodes[sibling].parent = grand_parent;
+        self.nodes[parent].kind = NodeKind::Free;
+        self.free.push(parent);
+        self.nodes[leaf].parent = None;
+        self.fix_upwards(grand_parent);
+    }
+
+    //rebalances and refits every branch from `index` to the root
+    fn fix_upwards(&mut self, mut index: Option<usize>) {
+        while let Some(i) = index {
+            let i = self.balance(i);
+            let [left, right] = self.children(i);
+            self.nodes[i].height = 1 + self.nodes[left].height.max(self.nodes[right].height);
+            self.nodes[i].bounds = self.nodes[left].bounds.union(&self.nodes[right].bounds);
+            index = self.nodes[i].parent;
+        }
+    }
+
+    //rotates the taller child up if the branch is unbalanced, returning the branch
+    //now in its place
+    fn balance(&mut self, a: usize) -> usize {
+        if self.nodes[a].height < 2 {
+            return a;
+        }
+        let [b, c] = self.children(a);
+        let (b_height, c_height) = (self.nodes[b].height, self.nodes[c].height);
+        if c_height > b_height + 1 {
+            self.rotate(a, c, b)
+        } else if b_height > c_height + 1 {
+            self.rotate(a, b, c)
+        } else {
+            a
+        }
+    }
+
+    fn rotate(&mut self, a: usize, up: usize, other: usize) -> usize {
+        let [f, g] = self.children(up);
+        let parent = self.nodes[a].parent;
+        self.nodes[up].parent = parent;
+        self.nodes[a].parent = Some(up);
+        self.replace_child(parent, a, up);
+        //the taller grandchild stays under `up`, the other one goes to `a`
+        let (kept, moved) = if self.nodes[f].height > self.nodes[g].height {
+            (f, g)
+        } else {
+            (g, f)
+        };
+        self.nodes[moved].parent = Some(a);
+        self.nodes[a].kind = NodeKind::Branch([other, moved]);
+        self.nodes[a].bounds = self.nodes[other].bounds.union(&self.nodes[moved].bounds);
+        self.nodes[a].height = 1 + self.nodes[other].height.max(self.nodes[moved].height);
+        self.nodes[up].kind = NodeKind::Branch([a, kept]);
+        self.nodes[up].bounds = self.nodes[a].bounds.union(&self.nodes[kept].bounds);
+        self.nodes[up].height = 1 + self.nodes[a].height.max(self.nodes[kept].height);
+        up
+    }
+}
+
+fn perimeter<P: Scalar>(bounds: &Aabb<P>) -> f64 {
+    2. * ((bounds.max_x - bounds.min_x).to_f64() + (bounds.max_y - bounds.min_y).to_f64())
+}
+
+/// The values of a `DynamicAabbTree` overlapping a rectangle.
+#[derive(Debug)]
+pub struct DynamicRectQuery<'a, Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    tree: &'a DynamicAabbTree<Value>,
+    stack: Vec<usize>,
+    rect: Aabb<Value::Position>,
+}
+
+impl<'a, Value: KdValue> Iterator for DynamicRectQuery<'a, Value>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(index) = self.stack.pop() {
+            let node = &self.tree.nodes[index];
+            if !node.bounds.overlaps(&self.rect) {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf(value) => {
+                    //the fat box may overlap when the value itself does not
+                    if Aabb::of(value).overlaps(&self.rect) {
+                        return Some(value);
+                    }
+                }
+                NodeKind::Branch(children) => self.stack.extend_from_slice(children),
+                NodeKind::Free => unreachable!("free nodes are not linked"),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynamicAabbTree;
+    use crate::{tests::TestValue, Aabb};
+
+    #[test]
+    fn dynamic_tree() {
+        let mut tree = DynamicAabbTree::<TestValue>::new(0.5);
+        let mut values: Vec<_> = (0..200)
+            .map(|i| {
+                let (x, y) = ((i % 20) as f32 * 3., (i / 20) as f32 * 3.);
+                TestValue::new(x, x + 1., y, y + 1.)
+            })
+            .collect();
+        let ids: Vec<_> = values.iter().map(|v| tree.insert(v.clone())).collect();
+        for step in 0..10 {
+            for (i, (id, value)) in ids.iter().zip(&mut values).enumerate() {
+                let dx = if (i + step) % 3 == 0 { 0.4 } else { -0.2 };
+                *value =
+                    TestValue::new(value.min_x + dx, value.max_x + dx, value.min_y, value.max_y);
+                if i % 2 == 0 {
+                    tree.update(*id, value.clone());
+                } else {
+                    tree.refit(*id, value.clone());
+                }
+            }
+        }
+        assert!(tree.height() <= 16);
+        let rect = Aabb::new(10., 25., 5., 12.);
+        let expected = values
+            .iter()
+            .filter(|v| Aabb::of(*v).overlaps(&rect))
+            .count();
+        assert_eq!(tree.query_rect(10., 25., 5., 12.).count(), expected);
+        for (id, value) in ids.iter().zip(&values).step_by(2) {
+            assert_eq!(tree.remove(*id).as_ref(), Some(value));
+        }
+        assert_eq!(tree.len(), 100);
+        let expected = values
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|v| Aabb::of(*v).overlaps(&rect))
+            .count();
+        assert_eq!(tree.query_rect(10., 25., 5., 12.).count(), expected);
+    }
+}
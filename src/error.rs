@@ -0,0 +1,173 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{KdTree, KdValue, RectQuery};
+
+/// An error of the `try_` methods of `KdTree`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdTreeError {
+    /// A min bound is above the max one, or a bound is NaN.
+    InvalidBounds,
+}
+
+impl fmt::Display for KdTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdTreeError::InvalidBounds => write!(f, "min bound above max bound, or NaN bound"),
+        }
+    }
+}
+
+impl core::error::Error for KdTreeError {}
+
+fn check_value<Value: KdValue>(value: &Value) -> Result<(), KdTreeError> {
+    check_bounds(
+        &value.min_x(),
+        &value.max_x(),
+        &value.min_y(),
+        &value.max_y(),
+    )
+}
+
+fn check_bounds<P: PartialOrd>(
+    min_x: &P,
+    max_x: &P,
+    min_y: &P,
+    max_y: &P,
+) -> Result<(), KdTreeError> {
+    //also fails on NaN
+    if min_x <= max_x && min_y <= max_y {
+        Ok(())
+    } else {
+        Err(KdTreeError::InvalidBounds)
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Like `insert`, failing on values which would corrupt the tree.
+    pub fn try_insert(&mut self, value: Value) -> Result<(), KdTreeError> {
+        check_value(&value)?;
+        self.insert(value);
+        Ok(())
+    }
+
+    /// Like `remove_one`, failing on values which could not have been inserted.
+    pub fn try_remove(&mut self, value: Value) -> Result<bool, KdTreeError> {
+        check_value(&value)?;
+        Ok(self.remove_one(value))
+    }
+
+    /// Like `query_rect`, failing on an invalid rectangle instead of matching
+    /// nothing.
+    pub fn try_query(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Result<RectQuery<'_, Value, ISLAND_SIZE>, KdTreeError> {
+        check_bounds(&min_x, &max_x, &min_y, &max_y)?;
+        Ok(self.query_rect(min_x, max_x, min_y, max_y))
+    }
+
+    /// The values with NaN or inverted bounds, which queries may miss.
+    pub fn invalid_values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().filter(|value| check_value(*value).is_err())
+    }
+
+    /// Passes each value with NaN or inverted bounds to `repair`, inserting back
+    /// the valid replacements it returns and removing the others, which are
+    /// returned.
+    ///
+    /// The tree is rebuilt when there were any, as they may have skewed its splits.
+    pub fn sanitize(&mut self, mut repair: impl FnMut(&Value) -> Option<Value>) -> Vec<Value> {
+        if self.invalid_values().next().is_none() {
+            return Vec::new();
+        }
+        let mut values = core::mem::take(self).into_values();
+        let mut removed = Vec::new();
+        let mut index = 0;
+        while index < values.len() {
+            if check_value(&values[index]).is_ok() {
+                index += 1;
+                continue;
+            }
+            match repair(&values[index]).filter(|fixed| check_value(fixed).is_ok()) {
+                Some(fixed) => {
+                    values[index] = fixed;
+                    index += 1;
+                }
+                None => removed.push(values.swap_remove(index)),
+            }
+        }
+        *self = Self::build(values);
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KdTreeError;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn try_methods() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..20 {
+            let x = i as f32;
+            assert_eq!(
+                tree.try_insert(TestValue::new(x, x + 1., x, x + 1.)),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            tree.try_insert(TestValue::new(2., 1., 0., 1.)),
+            Err(KdTreeError::InvalidBounds)
+        );
+        assert_eq!(
+            tree.try_insert(TestValue::new(0., 1., f32::NAN, 1.)),
+            Err(KdTreeError::InvalidBounds)
+        );
+        assert_eq!(tree.len(), 20);
+        assert_eq!(tree.try_remove(TestValue::new(3., 4., 3., 4.)), Ok(true));
+        assert_eq!(tree.try_remove(TestValue::new(3., 4., 3., 4.)), Ok(false));
+        assert_eq!(
+            tree.try_query(5., 7., 0., 20.).map(|query| query.count()),
+            Ok(4)
+        );
+        assert!(tree.try_query(7., 5., 0., 20.).is_err());
+
+        //islands of a single value are split like the others
+        let mut tree = KdTree::<TestValue, 1>::default();
+        for i in 0..20 {
+            let x = (i * 7 % 20) as f32;
+            assert_eq!(tree.try_insert(TestValue::new(x, x + 1., 0., 1.)), Ok(()));
+        }
+        assert_eq!(tree.query_rect(4.5, 6.5, 0., 1.).count(), 3);
+    }
+
+    #[test]
+    fn sanitize() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..50 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        assert!(tree.sanitize(|_| None).is_empty());
+        tree.insert(TestValue::new(5., 3., 0., 1.));
+        tree.insert(TestValue::new(f32::NAN, 1., 0., 1.));
+        tree.insert(TestValue::new(0., 1., 0., f32::NAN));
+        assert_eq!(tree.invalid_values().count(), 3);
+        //inverted boxes are swapped back, the NaN ones removed
+        let removed = tree.sanitize(|value| {
+            (value.min_x > value.max_x).then(|| TestValue::new(value.max_x, value.min_x, 0., 1.))
+        });
+        assert_eq!(removed.len(), 2);
+        assert_eq!(tree.len(), 51);
+        assert_eq!(tree.invalid_values().count(), 0);
+        assert!(tree
+            .query_point(4., 0.5)
+            .any(|value| value.min_x == 3. && value.max_x == 5.));
+        tree.validate();
+    }
+}
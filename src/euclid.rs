@@ -0,0 +1,88 @@
+use core::fmt::Debug;
+
+use euclid::{Box2D, Point2D};
+
+use crate::{Aabb, KdTree, KdValue, PointQuery, RectQuery};
+
+//the unit only tags the type, trees of boxes in different spaces can not be mixed
+impl<T: PartialOrd + Debug + Default + Clone, U> KdValue for Box2D<T, U> {
+    type Position = T;
+
+    fn min_x(&self) -> T {
+        self.min.x.clone()
+    }
+
+    fn min_y(&self) -> T {
+        self.min.y.clone()
+    }
+
+    fn max_x(&self) -> T {
+        self.max.x.clone()
+    }
+
+    fn max_y(&self) -> T {
+        self.max.y.clone()
+    }
+}
+
+impl<T, U> From<Box2D<T, U>> for Aabb<T> {
+    fn from(bounds: Box2D<T, U>) -> Self {
+        Aabb {
+            min_x: bounds.min.x,
+            max_x: bounds.max.x,
+            min_y: bounds.min.y,
+            max_y: bounds.max.y,
+        }
+    }
+}
+
+impl<T, U> From<Aabb<T>> for Box2D<T, U> {
+    fn from(bounds: Aabb<T>) -> Self {
+        Box2D::new(
+            Point2D::new(bounds.min_x, bounds.min_y),
+            Point2D::new(bounds.max_x, bounds.max_y),
+        )
+    }
+}
+
+impl<T: PartialOrd + Debug + Default + Clone, U, const ISLAND_SIZE: usize>
+    KdTree<Box2D<T, U>, ISLAND_SIZE>
+{
+    /// Like `query_point`, only accepting points in the space of the boxes.
+    pub fn query_point2d(&self, point: Point2D<T, U>) -> PointQuery<'_, Box2D<T, U>, ISLAND_SIZE> {
+        self.query_point(point.x, point.y)
+    }
+
+    /// Like `query_rect`, only accepting boxes in the space of the stored ones.
+    pub fn query_box2d(&self, bounds: Box2D<T, U>) -> RectQuery<'_, Box2D<T, U>, ISLAND_SIZE> {
+        self.query_rect(bounds.min.x, bounds.max.x, bounds.min.y, bounds.max.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::{Box2D, Point2D};
+
+    use crate::{tests::interop_boxes, Aabb};
+
+    struct WorldSpace;
+
+    fn point(x: f64, y: f64) -> Point2D<f32, WorldSpace> {
+        Point2D::new(x as f32, y as f32)
+    }
+
+    #[test]
+    fn euclid_boxes() {
+        let hit = interop_boxes(
+            |x, y| Box2D::new(point(x, y), point(x + 2., y + 2.)),
+            |tree, x, y| tree.query_point2d(point(x, y)).cloned().collect(),
+            |tree, [min_x, max_x, min_y, max_y]| {
+                tree.query_box2d(Box2D::new(point(min_x, min_y), point(max_x, max_y)))
+                    .count()
+            },
+        );
+        let bounds = Aabb::from(hit);
+        assert_eq!(bounds, Aabb::new(30., 32., 10., 12.));
+        assert_eq!(Box2D::<f32, WorldSpace>::from(bounds), hit);
+    }
+}
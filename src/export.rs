@@ -0,0 +1,81 @@
+use alloc::vec::Vec;
+
+use crate::{KdTree, KdValue};
+
+/// The bounds of every value of a tree, one contiguous column per coordinate.
+///
+/// Row `i` of every column describes `values[i]`.
+#[derive(Debug, Clone)]
+pub struct ColumnarBounds<'a, Value: KdValue> {
+    pub min_x: Vec<Value::Position>,
+    pub max_x: Vec<Value::Position>,
+    pub min_y: Vec<Value::Position>,
+    pub max_y: Vec<Value::Position>,
+    pub values: Vec<&'a Value>,
+}
+
+impl<'a, Value: KdValue> ColumnarBounds<'a, Value> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            min_x: Vec::with_capacity(capacity),
+            max_x: Vec::with_capacity(capacity),
+            min_y: Vec::with_capacity(capacity),
+            max_y: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    pub fn export_soa(&self) -> ColumnarBounds<'_, Value> {
+        let mut columns = ColumnarBounds::with_capacity(self.len());
+        self.export_soa_into(&mut columns);
+        columns
+    }
+
+    fn export_soa_into<'a>(&'a self, columns: &mut ColumnarBounds<'a, Value>) {
+        match self {
+            KdTree::Leaf(leaf, ..) => {
+                columns.min_x.extend(leaf.iter().map(KdValue::min_x));
+                columns.max_x.extend(leaf.iter().map(KdValue::max_x));
+                columns.min_y.extend(leaf.iter().map(KdValue::min_y));
+                columns.max_y.extend(leaf.iter().map(KdValue::max_y));
+                columns.values.extend(leaf.iter());
+            }
+            KdTree::Node(node) => {
+                node.left.export_soa_into(columns);
+                node.right.export_soa_into(columns);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn export_soa() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..50 {
+            let x = (i * 7 % 23) as f32;
+            tree.insert(TestValue::new(x, x + 1., -x, 2. * x));
+        }
+        let columns = tree.export_soa();
+        assert_eq!(columns.len(), 50);
+        for (i, value) in columns.values.iter().enumerate() {
+            assert_eq!(columns.min_x[i], value.min_x);
+            assert_eq!(columns.max_x[i], value.max_x);
+            assert_eq!(columns.min_y[i], value.min_y);
+            assert_eq!(columns.max_y[i], value.max_y);
+        }
+    }
+}
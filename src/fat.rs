@@ -0,0 +1,112 @@
+use crate::{Aabb, KdTree, KdValue, Scalar};
+
+/// A value stored with an inflated box, so that small moves do not require
+/// touching the tree.
+///
+/// The value is usually a handle to an object whose true bounds live elsewhere:
+/// as long as `needs_update` is false for the new true bounds, the object can move
+/// without being re-inserted, and queries on the tree still find it (along with a
+/// few false positives, to be filtered against the true bounds).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Fat<Value, P> {
+    pub value: Value,
+    pub(crate) bounds: Aabb<P>,
+}
+
+impl<Value, P: Scalar> Fat<Value, P> {
+    /// Inflates `bounds` by `margin` on every side.
+    pub fn new(value: Value, bounds: &Aabb<P>, margin: P) -> Self {
+        Self {
+            value,
+            bounds: Aabb::new(
+                bounds.min_x - margin,
+                bounds.max_x + margin,
+                bounds.min_y - margin,
+                bounds.max_y + margin,
+            ),
+        }
+    }
+
+    pub fn fat_bounds(&self) -> &Aabb<P> {
+        &self.bounds
+    }
+
+    /// Whether the true bounds escaped the fat box, requiring a re-insertion.
+    pub fn needs_update(&self, true_bounds: &Aabb<P>) -> bool {
+        true_bounds.min_x < self.bounds.min_x
+            || true_bounds.max_x > self.bounds.max_x
+            || true_bounds.min_y < self.bounds.min_y
+            || true_bounds.max_y > self.bounds.max_y
+    }
+}
+
+impl<Value: Default + Clone + core::fmt::Debug + PartialEq, P: Scalar> KdValue for Fat<Value, P> {
+    type Position = P;
+
+    fn min_x(&self) -> Self::Position {
+        self.bounds.min_x
+    }
+
+    fn min_y(&self) -> Self::Position {
+        self.bounds.min_y
+    }
+
+    fn max_x(&self) -> Self::Position {
+        self.bounds.max_x
+    }
+
+    fn max_y(&self) -> Self::Position {
+        self.bounds.max_y
+    }
+}
+
+impl<
+        Value: Default + Clone + core::fmt::Debug + PartialEq,
+        P: Scalar,
+        const ISLAND_SIZE: usize,
+    > KdTree<Fat<Value, P>, ISLAND_SIZE>
+{
+    /// Re-inserts `fat` with a new fat box if `true_bounds` escaped it, and returns
+    /// the new entry, or `None` if the tree was left untouched.
+    pub fn update_fat(
+        &mut self,
+        fat: &Fat<Value, P>,
+        true_bounds: &Aabb<P>,
+        margin: P,
+    ) -> Option<Fat<Value, P>> {
+        if !fat.needs_update(true_bounds) {
+            return None;
+        }
+        self.remove_one(fat.clone());
+        let moved = Fat::new(fat.value.clone(), true_bounds, margin);
+        self.insert(moved.clone());
+        Some(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fat;
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn fat_margins() {
+        let mut tree = KdTree::<Fat<u32, f32>, 4>::default();
+        let mut bounds = Aabb::new(0., 1., 0., 1.);
+        let mut fat = Fat::new(7, &bounds, 0.5);
+        tree.insert(fat.clone());
+        let mut reinsertions = 0;
+        for _ in 0..20 {
+            bounds.min_x += 0.1;
+            bounds.max_x += 0.1;
+            if let Some(moved) = tree.update_fat(&fat, &bounds, 0.5) {
+                fat = moved;
+                reinsertions += 1;
+            }
+            assert!(!fat.needs_update(&bounds));
+            assert_eq!(tree.query_point(bounds.min_x, 0.5).count(), 1);
+        }
+        assert_eq!(reinsertions, 3);
+        assert_eq!(tree.len(), 1);
+    }
+}
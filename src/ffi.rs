@@ -0,0 +1,282 @@
+//! A C ABI over a tree of `f32` boxes tagged with a `u64` id.
+//!
+//! Trees are opaque handles created with `kdtc_tree_new` and released with
+//! `kdtc_tree_free`. Query results are delivered either to a callback, or by
+//! filling a caller-provided buffer.
+
+use alloc::boxed::Box;
+use core::{ffi::c_void, slice};
+
+use crate::{KdTree, KdValue};
+
+const FFI_ISLAND_SIZE: usize = 16;
+
+/// A box and the id the caller associates with it.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct KdtcBox {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    pub id: u64,
+}
+
+impl KdValue for KdtcBox {
+    type Position = f32;
+
+    fn min_x(&self) -> f32 {
+        self.min_x
+    }
+
+    fn min_y(&self) -> f32 {
+        self.min_y
+    }
+
+    fn max_x(&self) -> f32 {
+        self.max_x
+    }
+
+    fn max_y(&self) -> f32 {
+        self.max_y
+    }
+}
+
+/// The opaque handle given to C.
+#[derive(Debug, Default)]
+pub struct KdtcTree(KdTree<KdtcBox, FFI_ISLAND_SIZE>);
+
+/// Called for each hit of a query with the user data; returning `false` stops
+/// the query. A null callback is rejected with `KDTC_NULL_CALLBACK`.
+pub type KdtcCallback =
+    Option<extern "C" fn(value: *const KdtcBox, user_data: *mut c_void) -> bool>;
+
+/// Returned instead of a number of calls by the queries given a null callback.
+pub const KDTC_NULL_CALLBACK: usize = usize::MAX;
+
+/// Creates an empty tree, to release with `kdtc_tree_free`.
+#[no_mangle]
+pub extern "C" fn kdtc_tree_new() -> *mut KdtcTree {
+    Box::into_raw(Box::default())
+}
+
+/// Releases a tree. Null is ignored.
+///
+/// # Safety
+/// `tree` must come from `kdtc_tree_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_free(tree: *mut KdtcTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// # Safety
+/// `tree` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_len(tree: *const KdtcTree) -> usize {
+    (*tree).0.len()
+}
+
+/// # Safety
+/// `tree` must be a live handle, not used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_insert(tree: *mut KdtcTree, value: KdtcBox) {
+    (*tree).0.insert(value)
+}
+
+/// Removes one value equal to `value`, id included. Returns whether one was found.
+///
+/// # Safety
+/// `tree` must be a live handle, not used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_remove(tree: *mut KdtcTree, value: KdtcBox) -> bool {
+    (*tree).0.remove_one(value)
+}
+
+/// Calls `callback` for each value overlapping the rectangle, and returns the
+/// number of calls, or `KDTC_NULL_CALLBACK` if `callback` is null.
+///
+/// # Safety
+/// `tree` must be a live handle, not modified during the query.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_query_rect(
+    tree: *const KdtcTree,
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+    callback: KdtcCallback,
+    user_data: *mut c_void,
+) -> usize {
+    deliver(
+        (*tree).0.query_rect(min_x, max_x, min_y, max_y),
+        callback,
+        user_data,
+    )
+}
+
+/// Calls `callback` for each value containing the point, and returns the number
+/// of calls, or `KDTC_NULL_CALLBACK` if `callback` is null.
+///
+/// # Safety
+/// `tree` must be a live handle, not modified during the query.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_query_point(
+    tree: *const KdtcTree,
+    x: f32,
+    y: f32,
+    callback: KdtcCallback,
+    user_data: *mut c_void,
+) -> usize {
+    deliver((*tree).0.query_point(x, y), callback, user_data)
+}
+
+/// Copies the values overlapping the rectangle to `out`, up to `capacity` of
+/// them, and returns the total number of hits. A result larger than `capacity`
+/// means the buffer was too small.
+///
+/// # Safety
+/// `tree` must be a live handle, and `out` valid for `capacity` writes (it can
+/// be null if `capacity` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_query_rect_into(
+    tree: *const KdtcTree,
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+    out: *mut KdtcBox,
+    capacity: usize,
+) -> usize {
+    fill(
+        (*tree).0.query_rect(min_x, max_x, min_y, max_y),
+        out,
+        capacity,
+    )
+}
+
+/// Like `kdtc_tree_query_rect_into`, for the values containing the point.
+///
+/// # Safety
+/// Same as `kdtc_tree_query_rect_into`.
+#[no_mangle]
+pub unsafe extern "C" fn kdtc_tree_query_point_into(
+    tree: *const KdtcTree,
+    x: f32,
+    y: f32,
+    out: *mut KdtcBox,
+    capacity: usize,
+) -> usize {
+    fill((*tree).0.query_point(x, y), out, capacity)
+}
+
+fn deliver<'a>(
+    hits: impl Iterator<Item = &'a KdtcBox>,
+    callback: KdtcCallback,
+    user_data: *mut c_void,
+) -> usize {
+    let callback = match callback {
+        Some(callback) => callback,
+        None => return KDTC_NULL_CALLBACK,
+    };
+    let mut count = 0;
+    for hit in hits {
+        count += 1;
+        if !callback(hit, user_data) {
+            break;
+        }
+    }
+    count
+}
+
+unsafe fn fill<'a>(
+    hits: impl Iterator<Item = &'a KdtcBox>,
+    out: *mut KdtcBox,
+    capacity: usize,
+) -> usize {
+    let out: &mut [KdtcBox] = if capacity == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(out, capacity)
+    };
+    let mut count = 0;
+    for hit in hits {
+        if let Some(slot) = out.get_mut(count) {
+            *slot = *hit;
+        }
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{ffi::c_void, ptr};
+
+    use super::*;
+
+    extern "C" fn collect(value: *const KdtcBox, user_data: *mut c_void) -> bool {
+        let ids = unsafe { &mut *(user_data as *mut Vec<u64>) };
+        ids.push(unsafe { (*value).id });
+        ids.len() < 2
+    }
+
+    #[test]
+    fn c_abi() {
+        unsafe {
+            let tree = kdtc_tree_new();
+            for i in 0..40u64 {
+                let x = (i * 13 % 41) as f32;
+                kdtc_tree_insert(
+                    tree,
+                    KdtcBox {
+                        min_x: x,
+                        min_y: i as f32,
+                        max_x: x + 2.,
+                        max_y: i as f32 + 2.,
+                        id: i,
+                    },
+                );
+            }
+            assert_eq!(kdtc_tree_len(tree), 40);
+            let mut ids = Vec::<u64>::new();
+            let calls = kdtc_tree_query_rect(
+                tree,
+                0.,
+                0.,
+                41.,
+                10.,
+                Some(collect),
+                &mut ids as *mut Vec<u64> as *mut c_void,
+            );
+            assert_eq!((calls, ids.len()), (2, 2));
+            assert_eq!(
+                kdtc_tree_query_point(tree, 14., 1.5, None, ptr::null_mut()),
+                KDTC_NULL_CALLBACK
+            );
+
+            let total = kdtc_tree_query_rect_into(tree, 0., 0., 41., 10., ptr::null_mut(), 0);
+            let mut out = vec![KdtcBox::default(); total];
+            assert_eq!(
+                kdtc_tree_query_rect_into(tree, 0., 0., 41., 10., out.as_mut_ptr(), total),
+                total
+            );
+            assert!(out.iter().all(|value| value.min_y <= 10.));
+
+            let mut hit = [KdtcBox::default()];
+            assert_eq!(
+                kdtc_tree_query_point_into(tree, 14., 1.5, hit.as_mut_ptr(), 1),
+                1
+            );
+            assert_eq!(hit[0].id, 1);
+            assert!(kdtc_tree_remove(tree, hit[0]));
+            assert!(!kdtc_tree_remove(tree, hit[0]));
+            assert_eq!(
+                kdtc_tree_query_point_into(tree, 14., 1.5, ptr::null_mut(), 0),
+                0
+            );
+            kdtc_tree_free(tree);
+        }
+    }
+}
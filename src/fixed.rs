@@ -0,0 +1,536 @@
+use core::{array, cmp::Ordering};
+
+use crate::{cmp_position, split_due, KdValue};
+
+//marks the end of a list, or the lack of a parent
+const NONE: u32 = u32::MAX;
+
+/// A tree holding at most `CAP` values, whose storage is entirely made of inline
+/// arrays: it never allocates, and inserting into a full tree fails.
+///
+/// Leaves are linked lists through the value slots, so they never overflow. The
+/// node array also has `CAP` slots: once it is exhausted leaves stop splitting,
+/// which only makes queries slower. A leaf which can't be split along either axis
+/// is tried again each time its length doubles.
+#[derive(Debug)]
+pub struct StaticKdTree<Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> {
+    values: [Value; CAP],
+    //the next value of the same leaf, or of the free list
+    next: [u32; CAP],
+    free: u32,
+    len: usize,
+    nodes: [StaticNode<Value::Position>; CAP],
+    node_count: usize,
+}
+
+#[derive(Debug)]
+struct StaticNode<P> {
+    parent: u32,
+    vertical: bool,
+    kind: NodeKind<P>,
+}
+
+#[derive(Debug)]
+enum NodeKind<P> {
+    Leaf { first: u32, len: usize },
+    //the right child is always stored right after the left one
+    Split { median: P, left_max: P, left: u32 },
+}
+
+impl<P> StaticNode<P> {
+    fn leaf(parent: u32, vertical: bool) -> Self {
+        Self {
+            parent,
+            vertical,
+            kind: NodeKind::Leaf {
+                first: NONE,
+                len: 0,
+            },
+        }
+    }
+}
+
+impl<Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> Default
+    for StaticKdTree<Value, CAP, ISLAND_SIZE>
+{
+    fn default() -> Self {
+        const { assert!(CAP > 0 && CAP < NONE as usize, "CAP must be in 1..u32::MAX") };
+        Self {
+            values: array::from_fn(|_| Value::default()),
+            next: array::from_fn(|i| if i + 1 < CAP { i as u32 + 1 } else { NONE }),
+            free: 0,
+            len: 0,
+            nodes: array::from_fn(|_| StaticNode::leaf(NONE, false)),
+            node_count: 1,
+        }
+    }
+}
+
+impl<Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize>
+    StaticKdTree<Value, CAP, ISLAND_SIZE>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        CAP
+    }
+
+    /// Inserts the value, or gives it back if the tree is full.
+    pub fn insert(&mut self, value: Value) -> Result<(), Value> {
+        if self.free == NONE {
+            return Err(value);
+        }
+        let slot = self.free;
+        self.free = self.next[slot as usize];
+        self.values[slot as usize] = value;
+        self.len += 1;
+        let value = &self.values[slot as usize];
+        let mut index = 0;
+        loop {
+            let node = &mut self.nodes[index];
+            match &mut node.kind {
+                NodeKind::Split {
+                    median,
+                    left_max,
+                    left,
+                } => {
+                    let (min, max) = bounds_along(value, node.vertical);
+                    if min < *median {
+                        if max > *left_max {
+                            *left_max = max;
+                        }
+                        index = *left as usize;
+                    } else {
+                        index = *left as usize + 1;
+                    }
+                }
+                NodeKind::Leaf { first, len } => {
+                    self.next[slot as usize] = *first;
+                    *first = slot;
+                    *len += 1;
+                    if split_due(*len, ISLAND_SIZE) {
+                        self.split(index);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Removes one value equal to `value`, returning whether one was found.
+    pub fn remove_one(&mut self, value: &Value) -> bool {
+        let mut index = 0;
+        let (first, len) = loop {
+            let node = &mut self.nodes[index];
+            match &mut node.kind {
+                NodeKind::Split { median, left, .. } => {
+                    let (min, _) = bounds_along(value, node.vertical);
+                    index = *left as usize + (min >= *median) as usize;
+                }
+                NodeKind::Leaf { first, len } => break (first, len),
+            }
+        };
+        let mut previous = NONE;
+        let mut current = *first;
+        while current != NONE {
+            let next = self.next[current as usize];
+            if self.values[current as usize] == *value {
+                if previous == NONE {
+                    *first = next;
+                } else {
+                    self.next[previous as usize] = next;
+                }
+                *len -= 1;
+                self.values[current as usize] = Value::default();
+                self.next[current as usize] = self.free;
+                self.free = current;
+                self.len -= 1;
+                return true;
+            }
+            previous = current;
+            current = next;
+        }
+        false
+    }
+
+    //splits a full leaf around the median of its minimums along its axis, or the
+    //other one if they are all equal, if there are free nodes
+    fn split(&mut self, index: usize) {
+        if self.node_count + 2 > CAP {
+            return;
+        }
+        let (mut first, len, vertical) = match self.nodes[index].kind {
+            NodeKind::Leaf { first, len } => (first, len, self.nodes[index].vertical),
+            NodeKind::Split { .. } => return,
+        };
+        for axis in [vertical, !vertical] {
+            first = self.sort(first, len, axis);
+            self.nodes[index].kind = NodeKind::Leaf { first, len };
+            let min = |slot: u32| bounds_along(&self.values[slot as usize], axis).0;
+            //values below the median go left, or if there are none, the ones equal
+            //to it
+            let mut middle = first;
+            for _ in 0..len / 2 {
+                middle = self.next[middle as usize];
+            }
+            let mut median = min(middle);
+            if min(first) >= median {
+                let mut current = middle;
+                while current != NONE && min(current) <= median {
+                    current = self.next[current as usize];
+                }
+                if current == NONE {
+                    continue;
+                }
+                median = min(current);
+            }
+            let (mut lists, mut lens) = ([NONE; 2], [0; 2]);
+            let mut left_max = None;
+            let mut current = first;
+            while current != NONE {
+                let next = self.next[current as usize];
+                let (min, max) = bounds_along(&self.values[current as usize], axis);
+                let side = if min < median { 0 } else { 1 };
+                if side == 0 && left_max.as_ref().is_none_or(|left_max| max > *left_max) {
+                    left_max = Some(max);
+                }
+                self.next[current as usize] = lists[side];
+                lists[side] = current;
+                lens[side] += 1;
+                current = next;
+            }
+            let Some(left_max) = left_max.filter(|_| lens[1] > 0) else {
+                //put the values back in a single list
+                first = lists[1];
+                if lists[0] != NONE {
+                    let mut last = lists[0];
+                    while self.next[last as usize] != NONE {
+                        last = self.next[last as usize];
+                    }
+                    self.next[last as usize] = lists[1];
+                    first = lists[0];
+                }
+                self.nodes[index].kind = NodeKind::Leaf { first, len };
+                continue;
+            };
+            let left = self.node_count as u32;
+            for child in 0..2 {
+                let mut node = StaticNode::leaf(index as u32, !axis);
+                node.kind = NodeKind::Leaf {
+                    first: lists[child],
+                    len: lens[child],
+                };
+                self.nodes[self.node_count + child] = node;
+            }
+            self.node_count += 2;
+            self.nodes[index].vertical = axis;
+            self.nodes[index].kind = NodeKind::Split {
+                median,
+                left_max,
+                left,
+            };
+            return;
+        }
+    }
+
+    //merge sorts the list of `len` values starting at `first` by their minimum
+    //along the axis, returning its new start
+    fn sort(&mut self, first: u32, len: usize, vertical: bool) -> u32 {
+        if len < 2 {
+            return first;
+        }
+        let mut last_left = first;
+        for _ in 1..len / 2 {
+            last_left = self.next[last_left as usize];
+        }
+        let right = self.next[last_left as usize];
+        self.next[last_left as usize] = NONE;
+        let mut a = self.sort(first, len / 2, vertical);
+        let mut b = self.sort(right, len - len / 2, vertical);
+        let min = |tree: &Self, slot: u32| bounds_along(&tree.values[slot as usize], vertical).0;
+        let (mut head, mut tail) = (NONE, NONE);
+        while a != NONE || b != NONE {
+            let take_a = b == NONE
+                || (a != NONE && cmp_position(&min(self, a), &min(self, b)) != Ordering::Greater);
+            let slot = if take_a { a } else { b };
+            if take_a {
+                a = self.next[a as usize];
+            } else {
+                b = self.next[b as usize];
+            }
+            if tail == NONE {
+                head = slot;
+            } else {
+                self.next[tail as usize] = slot;
+            }
+            tail = slot;
+        }
+        self.next[tail as usize] = NONE;
+        head
+    }
+
+    pub fn iter(&self) -> StaticIter<'_, Value, CAP, ISLAND_SIZE> {
+        StaticIter {
+            tree: self,
+            node: 0,
+            cursor: NONE,
+        }
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> StaticRectQuery<'_, Value, CAP, ISLAND_SIZE> {
+        StaticRectQuery {
+            tree: self,
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            node: NONE,
+            cursor: NONE,
+            started: false,
+        }
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> StaticRectQuery<'_, Value, CAP, ISLAND_SIZE>
+    where
+        Value::Position: Clone,
+    {
+        self.query_rect(x.clone(), x, y.clone(), y)
+    }
+}
+
+fn bounds_along<Value: KdValue>(
+    value: &Value,
+    vertical: bool,
+) -> (Value::Position, Value::Position) {
+    if vertical {
+        (value.min_y(), value.max_y())
+    } else {
+        (value.min_x(), value.max_x())
+    }
+}
+
+pub struct StaticIter<'a, Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> {
+    tree: &'a StaticKdTree<Value, CAP, ISLAND_SIZE>,
+    node: usize,
+    cursor: u32,
+}
+
+impl<'a, Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> Iterator
+    for StaticIter<'a, Value, CAP, ISLAND_SIZE>
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        while self.cursor == NONE {
+            if self.node >= self.tree.node_count {
+                return None;
+            }
+            if let NodeKind::Leaf { first, .. } = self.tree.nodes[self.node].kind {
+                self.cursor = first;
+            }
+            self.node += 1;
+        }
+        let value = &self.tree.values[self.cursor as usize];
+        self.cursor = self.tree.next[self.cursor as usize];
+        Some(value)
+    }
+}
+
+/// A query walking the tree through parent links, so it needs no stack.
+pub struct StaticRectQuery<'a, Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> {
+    tree: &'a StaticKdTree<Value, CAP, ISLAND_SIZE>,
+    min_x: Value::Position,
+    max_x: Value::Position,
+    min_y: Value::Position,
+    max_y: Value::Position,
+    //the last node entered
+    node: u32,
+    cursor: u32,
+    started: bool,
+}
+
+impl<Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize>
+    StaticRectQuery<'_, Value, CAP, ISLAND_SIZE>
+{
+    fn go_left(&self, node: &StaticNode<Value::Position>) -> bool {
+        let min = if node.vertical {
+            &self.min_y
+        } else {
+            &self.min_x
+        };
+        matches!(&node.kind, NodeKind::Split { left_max, .. } if min <= left_max)
+    }
+
+    fn go_right(&self, node: &StaticNode<Value::Position>) -> bool {
+        let max = if node.vertical {
+            &self.max_y
+        } else {
+            &self.max_x
+        };
+        matches!(&node.kind, NodeKind::Split { median, .. } if max >= median)
+    }
+
+    //moves to the next leaf to scan, returns false once the traversal is over
+    fn advance(&mut self) -> bool {
+        let nodes = &self.tree.nodes;
+        let mut entering = if self.started {
+            None
+        } else {
+            self.started = true;
+            Some(0)
+        };
+        loop {
+            match entering {
+                Some(index) => {
+                    self.node = index;
+                    let node = &nodes[index as usize];
+                    match node.kind {
+                        NodeKind::Leaf { first, .. } => {
+                            self.cursor = first;
+                            return true;
+                        }
+                        NodeKind::Split { left, .. } => {
+                            entering = if self.go_left(node) {
+                                Some(left)
+                            } else if self.go_right(node) {
+                                Some(left + 1)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                }
+                None => {
+                    let parent = nodes[self.node as usize].parent;
+                    if parent == NONE {
+                        return false;
+                    }
+                    let parent_node = &nodes[parent as usize];
+                    match parent_node.kind {
+                        NodeKind::Split { left, .. }
+                            if self.node == left && self.go_right(parent_node) =>
+                        {
+                            entering = Some(left + 1)
+                        }
+                        _ => self.node = parent,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, Value: KdValue, const CAP: usize, const ISLAND_SIZE: usize> Iterator
+    for StaticRectQuery<'a, Value, CAP, ISLAND_SIZE>
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        loop {
+            while self.cursor != NONE {
+                let value = &self.tree.values[self.cursor as usize];
+                self.cursor = self.tree.next[self.cursor as usize];
+                if value.min_x() <= self.max_x
+                    && self.min_x <= value.max_x()
+                    && value.min_y() <= self.max_y
+                    && self.min_y <= value.max_y()
+                {
+                    return Some(value);
+                }
+            }
+            if self.started && self.node == NONE {
+                return None;
+            }
+            if !self.advance() {
+                self.node = NONE;
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticKdTree;
+    use crate::{
+        tests::{assert_same, query_corners, scattered, TestValue},
+        KdTree,
+    };
+
+    #[test]
+    fn static_tree() {
+        let mut tree = StaticKdTree::<TestValue, 300, 4>::new();
+        let mut reference = KdTree::<TestValue, 4>::default();
+        for value in scattered(300, |_| (2., 3.)) {
+            tree.insert(value.clone()).unwrap();
+            reference.insert(value);
+        }
+        assert!(tree.insert(TestValue::default()).is_err());
+        assert_eq!(tree.iter().count(), 300);
+        for (x, y) in query_corners() {
+            let found: Vec<_> = tree.query_rect(x, x + 10., y, y + 10.).collect();
+            let expected: Vec<_> = reference.query_rect(x, x + 10., y, y + 10.).collect();
+            assert_same(&found, &expected);
+            assert_eq!(
+                tree.query_point(x, y).count(),
+                reference.query_point(x, y).count()
+            );
+        }
+        let removed = TestValue::new(37., 39., 11., 14.);
+        assert!(tree.remove_one(&removed));
+        assert!(!tree.remove_one(&removed));
+        assert_eq!(tree.len(), 299);
+        assert_eq!(tree.query_point(38., 12.).count(), 0);
+        tree.insert(removed).unwrap();
+
+        //with all nodes used, leaves keep growing instead of splitting
+        let mut tiny = StaticKdTree::<TestValue, 16, 2>::new();
+        for i in 0..16 {
+            tiny.insert(TestValue::new(i as f32, i as f32 + 0.5, 0., 1.))
+                .unwrap();
+        }
+        assert_eq!(tiny.query_rect(3.2, 6.2, 0., 1.).count(), 4);
+    }
+
+    #[test]
+    fn degenerate_axis() {
+        //a row of tiles which all start at the same y
+        let mut tree = StaticKdTree::<TestValue, 512, 8>::new();
+        for i in 0..500 {
+            let x = (i * 37 % 500) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.)).unwrap();
+        }
+        for x in [0., 17.5, 250.5, 499.5] {
+            assert_eq!(tree.query_point(x, 0.5).count(), 1);
+        }
+        assert_eq!(tree.query_rect(10., 20., 0., 1.).count(), 12);
+        //the leaves were split along x
+        assert!(tree.node_count > 64);
+        //values which can't be split along either axis
+        let mut equal = StaticKdTree::<TestValue, 64, 4>::new();
+        for _ in 0..64 {
+            equal.insert(TestValue::new(1., 2., 1., 2.)).unwrap();
+        }
+        assert_eq!(equal.query_point(1.5, 1.5).count(), 64);
+    }
+}
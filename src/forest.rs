@@ -0,0 +1,218 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::{KdTree, KdValue, RectQuery};
+
+/// A collision layer, mapped to one of the 64 bits of a `LayerMask`.
+pub trait Layer {
+    /// The bit of the layer, below 64.
+    fn bit(&self) -> u32;
+}
+
+impl Layer for u32 {
+    fn bit(&self) -> u32 {
+        *self
+    }
+}
+
+/// A set of layers.
+///
+/// The methods taking a layer panic if its bit is 64 or more, like `KdForest::insert`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerMask(pub u64);
+
+impl LayerMask {
+    pub const ALL: Self = Self(u64::MAX);
+    pub const NONE: Self = Self(0);
+
+    pub fn of<L: Layer>(layers: impl IntoIterator<Item = L>) -> Self {
+        layers
+            .into_iter()
+            .fold(Self::NONE, |mask, layer| mask.with(&layer))
+    }
+
+    pub fn with<L: Layer>(self, layer: &L) -> Self {
+        Self(self.0 | flag(layer))
+    }
+
+    pub fn without<L: Layer>(self, layer: &L) -> Self {
+        Self(self.0 & !flag(layer))
+    }
+
+    pub fn contains<L: Layer>(&self, layer: &L) -> bool {
+        self.0 & flag(layer) != 0
+    }
+}
+
+fn flag<L: Layer>(layer: &L) -> u64 {
+    1u64.checked_shl(layer.bit())
+        .expect("layer bits must be below 64")
+}
+
+/// One tree per collision layer, queried together through a `LayerMask`.
+#[derive(Debug)]
+pub struct KdForest<L, Value: KdValue, const ISLAND_SIZE: usize> {
+    trees: BTreeMap<u32, KdTree<Value, ISLAND_SIZE>>,
+    layer: PhantomData<L>,
+}
+
+impl<L, Value: KdValue, const ISLAND_SIZE: usize> Default for KdForest<L, Value, ISLAND_SIZE> {
+    fn default() -> Self {
+        Self {
+            trees: BTreeMap::new(),
+            layer: PhantomData,
+        }
+    }
+}
+
+impl<L: Layer, Value: KdValue, const ISLAND_SIZE: usize> KdForest<L, Value, ISLAND_SIZE> {
+    /// # Panics
+    ///
+    /// Panics if the bit of the layer is 64 or more.
+    pub fn insert(&mut self, layer: &L, value: Value) {
+        let bit = layer.bit();
+        assert!(bit < 64, "layer bits must be below 64");
+        self.trees.entry(bit).or_default().insert(value)
+    }
+
+    pub fn remove_one(&mut self, layer: &L, value: Value) -> bool {
+        match self.trees.get_mut(&layer.bit()) {
+            Some(tree) => tree.remove_one(value),
+            None => false,
+        }
+    }
+
+    pub fn layer(&self, layer: &L) -> Option<&KdTree<Value, ISLAND_SIZE>> {
+        self.trees.get(&layer.bit())
+    }
+
+    pub fn layer_mut(&mut self, layer: &L) -> Option<&mut KdTree<Value, ISLAND_SIZE>> {
+        self.trees.get_mut(&layer.bit())
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.values().map(KdTree::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.values().all(KdTree::is_empty)
+    }
+
+    /// The values of the layers in `mask` overlapping the rectangle, layer by layer.
+    pub fn query_rect(
+        &self,
+        mask: LayerMask,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> ForestRectQuery<'_, Value, ISLAND_SIZE>
+    where
+        Value::Position: Clone,
+    {
+        ForestRectQuery {
+            trees: self
+                .trees
+                .iter()
+                .filter(|(bit, _)| mask.contains(*bit))
+                .map(|(_, tree)| tree)
+                .collect(),
+            query: None,
+            rect: (min_x, max_x, min_y, max_y),
+        }
+    }
+
+    pub fn query_point(
+        &self,
+        mask: LayerMask,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> ForestRectQuery<'_, Value, ISLAND_SIZE>
+    where
+        Value::Position: Clone,
+    {
+        self.query_rect(mask, x.clone(), x, y.clone(), y)
+    }
+}
+
+/// The values of several layers of a `KdForest` overlapping a rectangle.
+pub struct ForestRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    //the trees left to query, last first
+    trees: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
+    query: Option<RectQuery<'a, Value, ISLAND_SIZE>>,
+    rect: (
+        Value::Position,
+        Value::Position,
+        Value::Position,
+        Value::Position,
+    ),
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for ForestRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.query.as_mut().and_then(Iterator::next) {
+                return Some(value);
+            }
+            let tree = self.trees.pop()?;
+            let (min_x, max_x, min_y, max_y) = self.rect.clone();
+            self.query = Some(tree.query_rect(min_x, max_x, min_y, max_y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KdForest, Layer, LayerMask};
+    use crate::tests::TestValue;
+
+    #[derive(Debug, Clone, Copy)]
+    enum Layers {
+        Walls,
+        Enemies,
+        Decals,
+    }
+
+    impl Layer for Layers {
+        fn bit(&self) -> u32 {
+            *self as u32
+        }
+    }
+
+    #[test]
+    fn layer_masks() {
+        let mut forest = KdForest::<Layers, TestValue, 4>::default();
+        for (i, layer) in [Layers::Walls, Layers::Enemies, Layers::Decals]
+            .iter()
+            .enumerate()
+        {
+            for j in 0..(i + 1) * 3 {
+                forest.insert(layer, TestValue::new(j as f32, j as f32 + 10., 0., 1.));
+            }
+        }
+        let solid = LayerMask::of([Layers::Walls, Layers::Enemies]);
+        assert_eq!(forest.query_point(solid, 9.5, 0.5).count(), 9);
+        assert_eq!(forest.query_point(LayerMask::ALL, 9.5, 0.5).count(), 18);
+        assert_eq!(
+            forest
+                .query_point(solid.without(&Layers::Walls), 9.5, 0.5)
+                .count(),
+            6
+        );
+        assert!(forest.remove_one(&Layers::Decals, TestValue::new(0., 10., 0., 1.)));
+        assert!(!forest.remove_one(&Layers::Walls, TestValue::new(5., 15., 0., 1.)));
+        assert_eq!(forest.len(), 17);
+    }
+
+    #[test]
+    #[should_panic(expected = "layer bits must be below 64")]
+    fn layer_bit_too_high() {
+        LayerMask::NONE.with(&64u32);
+    }
+}
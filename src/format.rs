@@ -0,0 +1,387 @@
+use std::{
+    cmp::Ordering,
+    convert::TryInto,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    bytes::{read_f64, read_u32, read_u64},
+    KdNode, KdTree, KdValue, Scalar,
+};
+
+const MAGIC: &[u8; 4] = b"KDTB";
+const HEADER_SIZE: usize = 24;
+const NODE_SIZE: usize = 24;
+const ENTRY_SIZE: usize = 48;
+const SPLIT_X: u32 = 0;
+const SPLIT_Y: u32 = 1;
+const LEAF: u32 = 2;
+//deeper files are rejected, dropping or walking such a tree would recurse too deep
+const MAX_DEPTH: usize = 1024;
+
+//the layout is specified in FORMAT.md, keep both in sync
+
+/// The version of the binary format, written in every file.
+///
+/// Readers accept the files of their major version, whatever their minor version:
+/// minor versions only append data after the known sections, which older readers
+/// skip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FormatVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl FormatVersion {
+    /// The version written by this crate.
+    pub const CURRENT: Self = Self { major: 1, minor: 0 };
+
+    /// Whether this crate can read files of this version.
+    pub fn is_supported(&self) -> bool {
+        self.major == Self::CURRENT.major
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Writes the tree, structure included, in the binary format described in
+    /// FORMAT.md, encoding every value with `encode`.
+    pub fn write_binary<W: Write>(
+        &self,
+        mut writer: W,
+        mut encode: impl FnMut(&Value, &mut Vec<u8>),
+    ) -> io::Result<()> {
+        let mut sections = Sections::default();
+        sections.push(self, &mut encode);
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&FormatVersion::CURRENT.major.to_le_bytes());
+        header.extend_from_slice(&FormatVersion::CURRENT.minor.to_le_bytes());
+        header.extend_from_slice(&((sections.nodes.len() / NODE_SIZE) as u32).to_le_bytes());
+        header.extend_from_slice(&((sections.entries.len() / ENTRY_SIZE) as u32).to_le_bytes());
+        header.extend_from_slice(&(sections.blob.len() as u64).to_le_bytes());
+        writer.write_all(&header)?;
+        writer.write_all(&sections.nodes)?;
+        writer.write_all(&sections.entries)?;
+        writer.write_all(&sections.blob)?;
+        writer.flush()
+    }
+
+    /// Reads a tree in the binary format, decoding every value with `decode`, and
+    /// returns it with the version of the file. The structure is kept as is.
+    pub fn read_binary<R: Read>(
+        mut reader: R,
+        mut decode: impl FnMut(&[u8]) -> io::Result<Value>,
+    ) -> io::Result<(Self, FormatVersion)> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(invalid("not a binary kd-tree"));
+        }
+        let version = FormatVersion {
+            major: u16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            minor: u16::from_le_bytes(bytes[6..8].try_into().unwrap()),
+        };
+        if !version.is_supported() {
+            return Err(invalid("unsupported binary kd-tree version"));
+        }
+        let nodes = read_u32(&bytes[8..]) as usize;
+        let entries = read_u32(&bytes[12..]) as usize;
+        let blob = read_u64(&bytes[16..]) as usize;
+        let size = HEADER_SIZE
+            .checked_add(nodes.saturating_mul(NODE_SIZE))
+            .and_then(|size| size.checked_add(entries.checked_mul(ENTRY_SIZE)?))
+            .and_then(|size| size.checked_add(blob));
+        //newer minor versions may append data, which is ignored
+        if size.is_none_or(|size| size > bytes.len()) || nodes == 0 {
+            return Err(invalid("truncated binary kd-tree"));
+        }
+        let entries_start = HEADER_SIZE + nodes * NODE_SIZE;
+        let mut reader = SectionReader {
+            nodes: &bytes[HEADER_SIZE..entries_start],
+            entries: &bytes[entries_start..entries_start + entries * ENTRY_SIZE],
+            blob: &bytes[entries_start + entries * ENTRY_SIZE..][..blob],
+            next_node: 0,
+            next_entry: 0,
+        };
+        let tree = reader.read(&mut decode)?;
+        if reader.next_node != nodes || reader.next_entry != entries {
+            return Err(invalid("corrupted binary kd-tree"));
+        }
+        Ok((tree, version))
+    }
+}
+
+#[derive(Default)]
+struct Sections {
+    nodes: Vec<u8>,
+    entries: Vec<u8>,
+    blob: Vec<u8>,
+}
+
+impl Sections {
+    //nodes are written in pre-order, the left subtree first
+    fn push<Value: KdValue, const ISLAND_SIZE: usize>(
+        &mut self,
+        tree: &KdTree<Value, ISLAND_SIZE>,
+        encode: &mut impl FnMut(&Value, &mut Vec<u8>),
+    ) where
+        Value::Position: Scalar,
+    {
+        match tree {
+            KdTree::Leaf(leaf, ..) => {
+                self.nodes.extend_from_slice(&LEAF.to_le_bytes());
+                self.nodes
+                    .extend_from_slice(&(leaf.len() as u32).to_le_bytes());
+                self.nodes.extend_from_slice(&[0; 16]);
+                for value in leaf {
+                    for bound in &[value.min_x(), value.max_x(), value.min_y(), value.max_y()] {
+                        self.entries
+                            .extend_from_slice(&bound.to_f64().to_le_bytes());
+                    }
+                    let offset = self.blob.len();
+                    encode(value, &mut self.blob);
+                    self.entries
+                        .extend_from_slice(&(offset as u64).to_le_bytes());
+                    self.entries
+                        .extend_from_slice(&((self.blob.len() - offset) as u64).to_le_bytes());
+                }
+            }
+            KdTree::Node(node) => {
+                let kind = if node.vertical { SPLIT_Y } else { SPLIT_X };
+                self.nodes.extend_from_slice(&kind.to_le_bytes());
+                self.nodes.extend_from_slice(&[0; 4]);
+                self.nodes
+                    .extend_from_slice(&node.median.to_f64().to_le_bytes());
+                self.nodes
+                    .extend_from_slice(&node.left_max.to_f64().to_le_bytes());
+                self.push(&node.left, encode);
+                self.push(&node.right, encode);
+            }
+        }
+    }
+}
+
+struct SectionReader<'a> {
+    nodes: &'a [u8],
+    entries: &'a [u8],
+    blob: &'a [u8],
+    next_node: usize,
+    next_entry: usize,
+}
+
+//a split whose subtrees are being read
+struct Split<Value: KdValue, const ISLAND_SIZE: usize> {
+    vertical: bool,
+    median: f64,
+    left_max: f64,
+    left: Option<(KdTree<Value, ISLAND_SIZE>, Extent)>,
+}
+
+//per axis, the smallest and the largest min, and the largest max of a subtree
+#[derive(Clone, Copy)]
+struct Extent {
+    min_low: [f64; 2],
+    min_high: [f64; 2],
+    max_high: [f64; 2],
+}
+
+impl Extent {
+    const EMPTY: Self = Self {
+        min_low: [f64::INFINITY; 2],
+        min_high: [f64::NEG_INFINITY; 2],
+        max_high: [f64::NEG_INFINITY; 2],
+    };
+
+    fn add(&mut self, min: [f64; 2], max: [f64; 2]) {
+        for axis in 0..2 {
+            self.min_low[axis] = self.min_low[axis].min(min[axis]);
+            self.min_high[axis] = self.min_high[axis].max(min[axis]);
+            self.max_high[axis] = self.max_high[axis].max(max[axis]);
+        }
+    }
+
+    fn union(mut self, other: Self) -> Self {
+        self.add(other.min_low, other.max_high);
+        self.add(other.min_high, other.max_high);
+        self
+    }
+}
+
+impl SectionReader<'_> {
+    //nodes are read in pre-order with an explicit stack of the open splits, so that
+    //a crafted file can't overflow the call stack
+    fn read<Value: KdValue, const ISLAND_SIZE: usize>(
+        &mut self,
+        decode: &mut impl FnMut(&[u8]) -> io::Result<Value>,
+    ) -> io::Result<KdTree<Value, ISLAND_SIZE>>
+    where
+        Value::Position: Scalar,
+    {
+        let mut splits: Vec<Split<Value, ISLAND_SIZE>> = Vec::new();
+        loop {
+            let record = self
+                .nodes
+                .get(self.next_node * NODE_SIZE..(self.next_node + 1) * NODE_SIZE)
+                .ok_or_else(|| invalid("corrupted binary kd-tree"))?;
+            self.next_node += 1;
+            let mut subtree = match read_u32(record) {
+                LEAF => self.read_leaf(read_u32(&record[4..]) as usize, decode)?,
+                kind @ (SPLIT_X | SPLIT_Y) => {
+                    if splits.len() == MAX_DEPTH {
+                        return Err(invalid("binary kd-tree too deep"));
+                    }
+                    splits.push(Split {
+                        vertical: kind == SPLIT_Y,
+                        median: read_f64(&record[8..]),
+                        left_max: read_f64(&record[16..]),
+                        left: None,
+                    });
+                    continue;
+                }
+                _ => return Err(invalid("corrupted binary kd-tree")),
+            };
+            //closes every split whose right subtree was just completed
+            loop {
+                let split = match splits.last_mut() {
+                    Some(split) => split,
+                    None => return Ok(subtree.0),
+                };
+                if split.left.is_none() {
+                    split.left = Some(subtree);
+                    break;
+                }
+                let split = splits.pop().unwrap();
+                let (left, left_extent) = split.left.unwrap();
+                let (right, right_extent) = subtree;
+                let axis = split.vertical as usize;
+                //the values must be where the split places them, as queries rely on it
+                if matches!(
+                    left_extent.min_high[axis].partial_cmp(&split.median),
+                    Some(Ordering::Greater | Ordering::Equal)
+                ) || left_extent.max_high[axis].partial_cmp(&split.left_max)
+                    == Some(Ordering::Greater)
+                    || right_extent.min_low[axis].partial_cmp(&split.median) == Some(Ordering::Less)
+                {
+                    return Err(invalid("corrupted binary kd-tree"));
+                }
+                subtree = (
+                    KdTree::Node(Box::new(KdNode {
+                        vertical: split.vertical,
+                        median: Value::Position::from_f64(split.median),
+                        left_max: Value::Position::from_f64(split.left_max),
+                        left,
+                        right,
+                    })),
+                    left_extent.union(right_extent),
+                );
+            }
+        }
+    }
+
+    fn read_leaf<Value: KdValue, const ISLAND_SIZE: usize>(
+        &mut self,
+        count: usize,
+        decode: &mut impl FnMut(&[u8]) -> io::Result<Value>,
+    ) -> io::Result<(KdTree<Value, ISLAND_SIZE>, Extent)>
+    where
+        Value::Position: Scalar,
+    {
+        //the count is not trusted for the allocation
+        let mut leaf =
+            Vec::with_capacity(count.min(self.entries.len() / ENTRY_SIZE).max(ISLAND_SIZE));
+        let mut extent = Extent::EMPTY;
+        for _ in 0..count {
+            let entry = self
+                .entries
+                .get(self.next_entry * ENTRY_SIZE..(self.next_entry + 1) * ENTRY_SIZE)
+                .ok_or_else(|| invalid("corrupted binary kd-tree"))?;
+            self.next_entry += 1;
+            let offset = read_u64(&entry[32..]) as usize;
+            let len = read_u64(&entry[40..]) as usize;
+            let bytes = offset
+                .checked_add(len)
+                .and_then(|end| self.blob.get(offset..end))
+                .ok_or_else(|| invalid("corrupted binary kd-tree"))?;
+            let value = decode(bytes)?;
+            extent.add(
+                [value.min_x().to_f64(), value.min_y().to_f64()],
+                [value.max_x().to_f64(), value.max_y().to_f64()],
+            );
+            leaf.push(value);
+        }
+        Ok((KdTree::leaf(leaf), extent))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::FormatVersion;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn binary_format() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i * 7 % 31) as f32;
+            tree.insert(TestValue::new(x, x + 1., i as f32, i as f32 + 0.5));
+        }
+        let mut bytes = Vec::new();
+        tree.write_binary(&mut bytes, |value, blob| {
+            for bound in &[value.min_x, value.max_x, value.min_y, value.max_y] {
+                blob.extend_from_slice(&bound.to_le_bytes());
+            }
+        })
+        .unwrap();
+        let decode = |bytes: &[u8]| {
+            let bound = |i: usize| f32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+            Ok(TestValue::new(bound(0), bound(1), bound(2), bound(3)))
+        };
+        let (loaded, version) = KdTree::<TestValue, 4>::read_binary(&bytes[..], decode).unwrap();
+        assert_eq!(version, FormatVersion::CURRENT);
+        assert_eq!(loaded.len(), 100);
+        assert!(loaded.iter().eq(tree.iter()));
+        //newer minor versions are read, newer major versions are not
+        bytes[6] = 3;
+        bytes.extend_from_slice(b"appended by a newer minor version");
+        assert!(KdTree::<TestValue, 4>::read_binary(&bytes[..], decode).is_ok());
+        bytes[4] = 2;
+        assert!(KdTree::<TestValue, 4>::read_binary(&bytes[..], decode).is_err());
+        //a median that doesn't separate the subtrees
+        bytes[4] = 1;
+        bytes[24 + 8..24 + 16].copy_from_slice(&(-1000f64).to_le_bytes());
+        assert!(KdTree::<TestValue, 4>::read_binary(&bytes[..], decode).is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        //a chain of splits, each with an empty left leaf
+        let depth = 100_000u32;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"KDTB");
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(2 * depth + 1).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        for _ in 0..depth {
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes.extend_from_slice(&[0; 20]);
+            bytes.extend_from_slice(&2u32.to_le_bytes());
+            bytes.extend_from_slice(&[0; 20]);
+        }
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 20]);
+        let decode = |_: &[u8]| Ok(TestValue::new(0., 0., 0., 0.));
+        let error = KdTree::<TestValue, 4>::read_binary(&bytes[..], decode).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
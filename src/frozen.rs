@@ -0,0 +1,203 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{cmp_position, Aabb, KdTree, KdValue};
+
+/// An immutable, perfectly balanced copy of a tree, for values that never change
+/// once loaded, such as static level geometry.
+///
+/// The nodes are a single array in post-order, holding the tight bounds of their
+/// subtree: a node at height `h` has its right child right before it and its left
+/// one `2^h` before it, so there are no child indexes. Leaves hold at most
+/// `2 * ISLAND_SIZE` values, and at least `ISLAND_SIZE` unless there are fewer
+/// values in total, all stored contiguously.
+#[derive(Debug, Clone)]
+pub struct FrozenKdTree<Value: KdValue, const ISLAND_SIZE: usize> {
+    nodes: Vec<Aabb<Value::Position>>,
+    //the height of the root, 0 when it is a leaf
+    height: u32,
+    values: Vec<Value>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// Converts the tree into a `FrozenKdTree`, splitting the values again at
+    /// their medians.
+    pub fn freeze(self) -> FrozenKdTree<Value, ISLAND_SIZE> {
+        FrozenKdTree::new(self.into_values())
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> FrozenKdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    pub fn new(mut values: Vec<Value>) -> Self {
+        let mut height = 0;
+        while ISLAND_SIZE.max(1) << (height + 1) <= values.len() {
+            height += 1;
+        }
+        let mut nodes = Vec::with_capacity((2 << height) - 1);
+        if !values.is_empty() {
+            Self::push(&mut nodes, &mut values, height, false);
+        }
+        Self {
+            nodes,
+            height,
+            values,
+        }
+    }
+
+    fn push(
+        nodes: &mut Vec<Aabb<Value::Position>>,
+        values: &mut [Value],
+        height: u32,
+        vertical: bool,
+    ) -> Aabb<Value::Position> {
+        let bounds = if height == 0 {
+            values
+                .iter()
+                .map(Aabb::of)
+                .reduce(|a, b| a.union(&b))
+                .expect("leaves are never empty")
+        } else {
+            let middle = values.len() / 2;
+            values.select_nth_unstable_by(middle, |a, b| {
+                if vertical {
+                    cmp_position(&a.min_y(), &b.min_y())
+                } else {
+                    cmp_position(&a.min_x(), &b.min_x())
+                }
+            });
+            let (left, right) = values.split_at_mut(middle);
+            let left = Self::push(nodes, left, height - 1, !vertical);
+            let right = Self::push(nodes, right, height - 1, !vertical);
+            left.union(&right)
+        };
+        nodes.push(bounds.clone());
+        bounds
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> FrozenRectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(x.clone(), x, y.clone(), y)
+    }
+
+    /// The values overlapping the rectangle. Subtrees inside of it are yielded
+    /// without testing their values.
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> FrozenRectQuery<'_, Value, ISLAND_SIZE> {
+        FrozenRectQuery {
+            tree: self,
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+            queue: match self.nodes.len() {
+                0 => vec![],
+                len => vec![(len - 1, self.height, 0, self.values.len())],
+            },
+            leaf: [].iter(),
+            check: true,
+        }
+    }
+}
+
+pub struct FrozenRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: &'a FrozenKdTree<Value, ISLAND_SIZE>,
+    rect: Aabb<Value::Position>,
+    //(node, height, first value, end of the values)
+    queue: Vec<(usize, u32, usize, usize)>,
+    leaf: core::slice::Iter<'a, Value>,
+    //false when the values being yielded are all inside the rectangle
+    check: bool,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for FrozenRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        loop {
+            for value in self.leaf.by_ref() {
+                if !self.check || Aabb::of(value).overlaps(&self.rect) {
+                    return Some(value);
+                }
+            }
+            let (node, height, start, end) = self.queue.pop()?;
+            let bounds = &self.tree.nodes[node];
+            if !bounds.overlaps(&self.rect) {
+                continue;
+            }
+            let inside = self.rect.min_x <= bounds.min_x
+                && bounds.max_x <= self.rect.max_x
+                && self.rect.min_y <= bounds.min_y
+                && bounds.max_y <= self.rect.max_y;
+            if inside || height == 0 {
+                self.leaf = self.tree.values[start..end].iter();
+                self.check = !inside;
+            } else {
+                let middle = start + (end - start) / 2;
+                self.queue.push((node - 1, height - 1, middle, end));
+                self.queue
+                    .push((node - (1 << height), height - 1, start, middle));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tests::{overlapping, query_corners, scattered, TestValue},
+        KdTree,
+    };
+
+    #[test]
+    fn frozen() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let values = scattered(500, |i| ((i % 5) as f32, (i % 3) as f32));
+        for value in &values {
+            tree.insert(value.clone());
+        }
+        let frozen = tree.freeze();
+        assert_eq!(frozen.len(), 500);
+        for (x, y) in query_corners() {
+            let expected = overlapping(&values, x, x + 25., y, y + 15.).len();
+            assert_eq!(frozen.query_rect(x, x + 25., y, y + 15.).count(), expected);
+        }
+        assert_eq!(frozen.query_rect(-10., 200., -10., 200.).count(), 500);
+        let expected = overlapping(&values, 42., 42., 17., 17.).len();
+        assert_eq!(frozen.query_point(42., 17.).count(), expected);
+
+        let empty = KdTree::<TestValue, 4>::default().freeze();
+        assert_eq!(empty.query_rect(0., 1., 0., 1.).count(), 0);
+        let small = KdTree::<TestValue, 4>::build(values[..3].to_vec()).freeze();
+        assert_eq!(small.query_rect(-10., 200., -10., 200.).count(), 3);
+    }
+}
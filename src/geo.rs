@@ -0,0 +1,204 @@
+use alloc::vec::Vec;
+use geo_types::{Coord, CoordNum, Geometry, GeometryCollection, LineString, Polygon, Rect};
+
+use crate::{Aabb, KdTree, KdValue};
+
+/// A `geo_types::Rect` usable as a value: `KdValue` requires `Default`, which
+/// `Rect` does not implement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoRect<T: CoordNum>(pub Rect<T>);
+
+impl<T: CoordNum> Default for GeoRect<T> {
+    fn default() -> Self {
+        Self(Rect::new(Coord::zero(), Coord::zero()))
+    }
+}
+
+impl<T: CoordNum + Default> KdValue for GeoRect<T> {
+    type Position = T;
+
+    fn min_x(&self) -> T {
+        self.0.min().x
+    }
+
+    fn min_y(&self) -> T {
+        self.0.min().y
+    }
+
+    fn max_x(&self) -> T {
+        self.0.max().x
+    }
+
+    fn max_y(&self) -> T {
+        self.0.max().y
+    }
+}
+
+impl<T: CoordNum> From<GeoRect<T>> for Aabb<T> {
+    fn from(GeoRect(rect): GeoRect<T>) -> Self {
+        Aabb::new(rect.min().x, rect.max().x, rect.min().y, rect.max().y)
+    }
+}
+
+impl<T: CoordNum> From<Aabb<T>> for GeoRect<T> {
+    fn from(bounds: Aabb<T>) -> Self {
+        Self(Rect::new(
+            Coord {
+                x: bounds.min_x,
+                y: bounds.min_y,
+            },
+            Coord {
+                x: bounds.max_x,
+                y: bounds.max_y,
+            },
+        ))
+    }
+}
+
+/// The bounding rect of a geometry, or `None` if it is empty.
+pub fn bounding_rect<T: CoordNum>(geometry: &Geometry<T>) -> Option<Rect<T>> {
+    let mut bounds: Option<Rect<T>> = None;
+    add_geometry(geometry, &mut |coord| {
+        let (min, max) = match bounds {
+            Some(rect) => (rect.min(), rect.max()),
+            None => (coord, coord),
+        };
+        let min_of = |a: T, b: T| if b < a { b } else { a };
+        let max_of = |a: T, b: T| if b > a { b } else { a };
+        bounds = Some(Rect::new(
+            Coord {
+                x: min_of(min.x, coord.x),
+                y: min_of(min.y, coord.y),
+            },
+            Coord {
+                x: max_of(max.x, coord.x),
+                y: max_of(max.y, coord.y),
+            },
+        ));
+    });
+    bounds
+}
+
+fn add_geometry<T: CoordNum>(geometry: &Geometry<T>, add: &mut impl FnMut(Coord<T>)) {
+    let add_line_string = |line: &LineString<T>, add: &mut dyn FnMut(Coord<T>)| {
+        line.coords().for_each(|coord| add(*coord))
+    };
+    let add_polygon = |polygon: &Polygon<T>, add: &mut dyn FnMut(Coord<T>)| {
+        //the interiors are inside the exterior
+        add_line_string(polygon.exterior(), add)
+    };
+    match geometry {
+        Geometry::Point(point) => add(point.0),
+        Geometry::Line(line) => {
+            add(line.start);
+            add(line.end);
+        }
+        Geometry::LineString(line) => add_line_string(line, add),
+        Geometry::Polygon(polygon) => add_polygon(polygon, add),
+        Geometry::MultiPoint(points) => points.iter().for_each(|point| add(point.0)),
+        Geometry::MultiLineString(lines) => {
+            lines.iter().for_each(|line| add_line_string(line, add))
+        }
+        Geometry::MultiPolygon(polygons) => polygons
+            .iter()
+            .for_each(|polygon| add_polygon(polygon, add)),
+        Geometry::GeometryCollection(GeometryCollection(geometries)) => geometries
+            .iter()
+            .for_each(|geometry| add_geometry(geometry, add)),
+        Geometry::Rect(rect) => {
+            add(rect.min());
+            add(rect.max());
+        }
+        Geometry::Triangle(triangle) => triangle.to_array().iter().for_each(|coord| add(*coord)),
+    }
+}
+
+/// A geometry indexed by its bounding rect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoValue<T: CoordNum> {
+    bounds: GeoRect<T>,
+    pub geometry: Geometry<T>,
+}
+
+impl<T: CoordNum> GeoValue<T> {
+    /// Returns `None` for empty geometries, which have no bounds.
+    pub fn new(geometry: impl Into<Geometry<T>>) -> Option<Self> {
+        let geometry = geometry.into();
+        let bounds = GeoRect(bounding_rect(&geometry)?);
+        Some(Self { bounds, geometry })
+    }
+
+    pub fn bounds(&self) -> &Rect<T> {
+        &self.bounds.0
+    }
+}
+
+impl<T: CoordNum> Default for GeoValue<T> {
+    fn default() -> Self {
+        Self {
+            bounds: GeoRect::default(),
+            geometry: Geometry::GeometryCollection(GeometryCollection(Vec::new())),
+        }
+    }
+}
+
+impl<T: CoordNum + Default> KdValue for GeoValue<T> {
+    type Position = T;
+
+    fn min_x(&self) -> T {
+        self.bounds.min_x()
+    }
+
+    fn min_y(&self) -> T {
+        self.bounds.min_y()
+    }
+
+    fn max_x(&self) -> T {
+        self.bounds.max_x()
+    }
+
+    fn max_y(&self) -> T {
+        self.bounds.max_y()
+    }
+}
+
+impl<T: CoordNum + Default, const ISLAND_SIZE: usize> KdTree<GeoValue<T>, ISLAND_SIZE> {
+    /// Bulk-builds a tree of geometries indexed by their bounding rects. Empty
+    /// geometries are skipped.
+    pub fn from_geometries<G: Into<Geometry<T>>>(geometries: impl IntoIterator<Item = G>) -> Self {
+        Self::build(geometries.into_iter().filter_map(GeoValue::new).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{coord, line_string, point, polygon, Geometry, GeometryCollection, Rect};
+
+    use super::{bounding_rect, GeoRect, GeoValue};
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn geo_geometries() {
+        let polygon = polygon![(x: 0., y: 0.), (x: 10., y: 2.), (x: 4., y: 8.)];
+        assert_eq!(
+            bounding_rect(&polygon.clone().into()),
+            Some(Rect::new(coord! { x: 0., y: 0. }, coord! { x: 10., y: 8. }))
+        );
+        let empty = Geometry::GeometryCollection(GeometryCollection::<f64>(Vec::new()));
+        assert_eq!(bounding_rect(&empty), None);
+        let tree = KdTree::<GeoValue<f64>, 4>::from_geometries(vec![
+            polygon.into(),
+            Geometry::from(point!(x: 20., y: 20.)),
+            line_string![(x: 15., y: 5.), (x: 25., y: -5.)].into(),
+            empty,
+        ]);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.query_point(5., 5.).count(), 1);
+        assert_eq!(tree.query_rect(9., 21., 0., 1.).count(), 2);
+
+        let mut rects = KdTree::<GeoRect<f32>, 4>::default();
+        rects.insert(GeoRect::from(Aabb::new(0., 1., 2., 3.)));
+        let hit = rects.query_point(0.5, 2.5).next().unwrap();
+        assert_eq!(Aabb::from(*hit), Aabb::new(0., 1., 2., 3.));
+    }
+}
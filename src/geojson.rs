@@ -0,0 +1,136 @@
+use serde_json::{json, Value as Json};
+
+use crate::{KdTree, KdValue, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Exports the stored boxes as a GeoJSON feature collection of polygons, to
+    /// inspect the index in GIS tools.
+    ///
+    /// Values have a `kind` property set to `"value"`, and their `Debug` output as
+    /// `value`. With `with_nodes`, the bounds of every subtree are exported as well,
+    /// with a `kind` of `"node"` or `"leaf"`, their `depth`, and the `axis` they
+    /// are split on for nodes.
+    pub fn to_geojson(&self, with_nodes: bool) -> Json {
+        let mut features = Vec::new();
+        push_features(self, with_nodes, 0, &mut features);
+        json!({
+            "type": "FeatureCollection",
+            "features": features,
+        })
+    }
+}
+
+//returns the bounds of the subtree, if it is not empty
+fn push_features<Value: KdValue, const ISLAND_SIZE: usize>(
+    tree: &KdTree<Value, ISLAND_SIZE>,
+    with_nodes: bool,
+    depth: usize,
+    features: &mut Vec<Json>,
+) -> Option<[f64; 4]>
+where
+    Value::Position: Scalar,
+{
+    let (bounds, properties) = match tree {
+        KdTree::Leaf(leaf, ..) => {
+            let mut bounds = None;
+            for value in leaf {
+                let value_bounds = [
+                    value.min_x().to_f64(),
+                    value.max_x().to_f64(),
+                    value.min_y().to_f64(),
+                    value.max_y().to_f64(),
+                ];
+                features.push(feature(
+                    value_bounds,
+                    json!({ "kind": "value", "value": format!("{:?}", value) }),
+                ));
+                bounds = Some(union(bounds, value_bounds));
+            }
+            (bounds, json!({ "kind": "leaf", "depth": depth }))
+        }
+        KdTree::Node(node) => {
+            let left = push_features(&node.left, with_nodes, depth + 1, features);
+            let right = push_features(&node.right, with_nodes, depth + 1, features);
+            let bounds = match (left, right) {
+                (Some(left), right) => Some(union(right, left)),
+                (None, right) => right,
+            };
+            let axis = if node.vertical { "y" } else { "x" };
+            (
+                bounds,
+                json!({ "kind": "node", "depth": depth, "axis": axis }),
+            )
+        }
+    };
+    if let (true, Some(bounds)) = (with_nodes, bounds) {
+        features.push(feature(bounds, properties));
+    }
+    bounds
+}
+
+fn feature([min_x, max_x, min_y, max_y]: [f64; 4], properties: Json) -> Json {
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [[
+                [min_x, min_y],
+                [max_x, min_y],
+                [max_x, max_y],
+                [min_x, max_y],
+                [min_x, min_y],
+            ]],
+        },
+        "properties": properties,
+    })
+}
+
+fn union(a: Option<[f64; 4]>, b: [f64; 4]) -> [f64; 4] {
+    match a {
+        Some(a) => [
+            a[0].min(b[0]),
+            a[1].max(b[1]),
+            a[2].min(b[2]),
+            a[3].max(b[3]),
+        ],
+        None => b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn geojson() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..20 {
+            let x = (i * 7 % 31) as f32;
+            tree.insert(Aabb::new(x, x + 1., i as f32, i as f32 + 0.5));
+        }
+        let geojson = tree.to_geojson(false);
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 20);
+        assert_eq!(
+            features[0]["geometry"]["coordinates"][0]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+        let with_nodes = tree.to_geojson(true);
+        let kinds: Vec<_> = with_nodes["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|feature| feature["properties"]["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds.iter().filter(|kind| **kind == "value").count(), 20);
+        assert!(kinds.contains(&"node") && kinds.contains(&"leaf"));
+        assert_eq!(kinds.last(), Some(&"node"));
+    }
+}
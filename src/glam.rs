@@ -0,0 +1,77 @@
+use glam::{DVec2, Vec2};
+
+use crate::{Aabb, KdValue};
+
+//boxes given as [min, max]
+macro_rules! impl_glam {
+    ($vec:ty, $scalar:ty) => {
+        impl KdValue for [$vec; 2] {
+            type Position = $scalar;
+
+            fn min_x(&self) -> $scalar {
+                self[0].x
+            }
+
+            fn min_y(&self) -> $scalar {
+                self[0].y
+            }
+
+            fn max_x(&self) -> $scalar {
+                self[1].x
+            }
+
+            fn max_y(&self) -> $scalar {
+                self[1].y
+            }
+        }
+
+        impl From<[$vec; 2]> for Aabb<$scalar> {
+            fn from([min, max]: [$vec; 2]) -> Self {
+                Aabb::new(min.x, max.x, min.y, max.y)
+            }
+        }
+
+        impl From<Aabb<$scalar>> for [$vec; 2] {
+            fn from(bounds: Aabb<$scalar>) -> Self {
+                [
+                    <$vec>::new(bounds.min_x, bounds.min_y),
+                    <$vec>::new(bounds.max_x, bounds.max_y),
+                ]
+            }
+        }
+    };
+}
+
+impl_glam!(Vec2, f32);
+impl_glam!(DVec2, f64);
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use crate::{tests::interop_boxes, Aabb};
+
+    #[test]
+    fn glam_boxes() {
+        let hit = interop_boxes(
+            |x, y| {
+                let min = Vec2::new(x as f32, y as f32);
+                [min, min + Vec2::splat(2.)]
+            },
+            |tree, x, y| {
+                tree.query_point_vec(Vec2::new(x as f32, y as f32))
+                    .cloned()
+                    .collect()
+            },
+            |tree, [min_x, max_x, min_y, max_y]| {
+                let min = Vec2::new(min_x as f32, min_y as f32);
+                tree.query_rect_vec(min, Vec2::new(max_x as f32, max_y as f32))
+                    .count()
+            },
+        );
+        assert_eq!(hit, [Vec2::new(30., 10.), Vec2::new(32., 12.)]);
+        let bounds = Aabb::from(hit);
+        assert_eq!(bounds, Aabb::new(30., 32., 10., 12.));
+        assert_eq!(<[Vec2; 2]>::from(bounds), hit);
+    }
+}
@@ -0,0 +1,269 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    scalar::{ceil, floor},
+    KdTree, KdValue, Scalar,
+};
+
+/// Set on a child link when it points into `GpuSnapshot::leaves` instead of `GpuSnapshot::nodes`.
+pub const GPU_LEAF_FLAG: u32 = 1 << 31;
+
+const QUANTIZATION_STEPS: f32 = u16::MAX as f32;
+
+/// An inner node of a `GpuSnapshot`.
+///
+/// Bounds are stored as `[min_x, min_y, max_x, max_y]`, quantized over the
+/// finite snapshot bounds and rounded outward, so they always contain the child.
+/// A min of 0 or a max of `u16::MAX` is unbounded, which covers infinite bounds.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GpuNode {
+    pub child_bounds: [[u16; 4]; 2],
+    pub children: [u32; 2],
+}
+
+/// A range of `GpuSnapshot::items`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GpuLeaf {
+    pub first: u32,
+    pub count: u32,
+}
+
+/// A flat copy of a tree, laid out to be uploaded in GPU buffers and traversed
+/// with an explicit stack.
+///
+/// `root` and `GpuNode::children` are indexes in `nodes`, or in `leaves` when
+/// `GPU_LEAF_FLAG` is set. `items` holds the exact bounds of the values as
+/// `[min_x, min_y, max_x, max_y]`, and `values` the matching values.
+#[derive(Debug, Clone)]
+pub struct GpuSnapshot<'a, Value> {
+    pub origin: [f32; 2],
+    pub extent: [f32; 2],
+    pub root: u32,
+    pub nodes: Vec<GpuNode>,
+    pub leaves: Vec<GpuLeaf>,
+    pub items: Vec<[f32; 4]>,
+    pub values: Vec<&'a Value>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    pub fn gpu_snapshot(&self) -> GpuSnapshot<'_, Value> {
+        let mut snapshot = GpuSnapshot {
+            origin: [0.; 2],
+            extent: [0.; 2],
+            root: 0,
+            nodes: Vec::new(),
+            leaves: Vec::new(),
+            items: Vec::with_capacity(self.len()),
+            values: Vec::with_capacity(self.len()),
+        };
+        for value in self.iter() {
+            snapshot.items.push(bounds_f32(value));
+            snapshot.values.push(value);
+        }
+        //infinite bounds are left out, they are mapped to the ends of the range
+        for axis in 0..2 {
+            let finite = snapshot
+                .items
+                .iter()
+                .flat_map(|item| [item[axis], item[axis + 2]])
+                .filter(|position| position.is_finite());
+            let range = finite.fold(None, |range: Option<(f32, f32)>, position| {
+                Some(match range {
+                    Some((min, max)) => (min.min(position), max.max(position)),
+                    None => (position, position),
+                })
+            });
+            if let Some((min, max)) = range {
+                snapshot.origin[axis] = min;
+                snapshot.extent[axis] = max - min;
+            }
+        }
+        //the values were collected in the same order as the leaves are visited
+        let mut next_item = 0;
+        snapshot.root = snapshot.push(self, &mut next_item).0;
+        snapshot
+    }
+}
+
+impl<'a, Value> GpuSnapshot<'a, Value> {
+    //returns the link to the subtree, and its exact bounds
+    fn push<V: KdValue, const ISLAND_SIZE: usize>(
+        &mut self,
+        tree: &KdTree<V, ISLAND_SIZE>,
+        next_item: &mut usize,
+    ) -> (u32, Option<[f32; 4]>)
+    where
+        V::Position: Scalar,
+    {
+        match tree {
+            KdTree::Leaf(leaf, ..) => {
+                let first = *next_item;
+                *next_item += leaf.len();
+                let bounds = self.items[first..*next_item].iter().copied().reduce(union);
+                self.leaves.push(GpuLeaf {
+                    first: first as u32,
+                    count: leaf.len() as u32,
+                });
+                ((self.leaves.len() - 1) as u32 | GPU_LEAF_FLAG, bounds)
+            }
+            KdTree::Node(node) => {
+                let index = self.nodes.len();
+                self.nodes.push(GpuNode::default());
+                let (left, left_bounds) = self.push(&node.left, next_item);
+                let (right, right_bounds) = self.push(&node.right, next_item);
+                self.nodes[index] = GpuNode {
+                    child_bounds: [self.quantize(left_bounds), self.quantize(right_bounds)],
+                    children: [left, right],
+                };
+                let bounds = match (left_bounds, right_bounds) {
+                    (Some(left), Some(right)) => Some(union(left, right)),
+                    (left, right) => left.or(right),
+                };
+                (index as u32, bounds)
+            }
+        }
+    }
+
+    //empty subtrees get an inverted box that overlaps nothing
+    fn quantize(&self, bounds: Option<[f32; 4]>) -> [u16; 4] {
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return [u16::MAX, u16::MAX, 0, 0],
+        };
+        let scale = |value: f32, axis: usize| {
+            if value.is_infinite() {
+                value
+            } else if self.extent[axis] > 0. {
+                (value - self.origin[axis]) / self.extent[axis] * QUANTIZATION_STEPS
+            } else {
+                0.
+            }
+        };
+        [
+            floor(scale(bounds[0], 0) as f64).max(0.) as u16,
+            floor(scale(bounds[1], 1) as f64).max(0.) as u16,
+            ceil(scale(bounds[2], 0) as f64).min(QUANTIZATION_STEPS as f64) as u16,
+            ceil(scale(bounds[3], 1) as f64).min(QUANTIZATION_STEPS as f64) as u16,
+        ]
+    }
+
+    /// Reference traversal, doing what a shader would do with the buffers.
+    pub fn query_rect(&self, min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Vec<&'a Value> {
+        let mut result = Vec::new();
+        let query = [min_x, min_y, max_x, max_y];
+        let mut stack = vec![self.root];
+        while let Some(link) = stack.pop() {
+            if link & GPU_LEAF_FLAG != 0 {
+                let leaf = self.leaves[(link & !GPU_LEAF_FLAG) as usize];
+                let range = leaf.first as usize..(leaf.first + leaf.count) as usize;
+                for (item, value) in self.items[range.clone()].iter().zip(&self.values[range]) {
+                    if overlaps(item, &query) {
+                        result.push(*value);
+                    }
+                }
+                continue;
+            }
+            let node = &self.nodes[link as usize];
+            for (bounds, child) in node.child_bounds.iter().zip(node.children.iter()) {
+                if overlaps(&self.dequantize(bounds), &query) {
+                    stack.push(*child);
+                }
+            }
+        }
+        result
+    }
+
+    //the ends of the range stand for the infinite bounds
+    fn dequantize(&self, bounds: &[u16; 4]) -> [f32; 4] {
+        let unscale = |value: u16, axis: usize| {
+            self.origin[axis] + value as f32 / QUANTIZATION_STEPS * self.extent[axis]
+        };
+        let min = |value: u16, axis: usize| match value {
+            0 => f32::NEG_INFINITY,
+            _ => unscale(value, axis),
+        };
+        let max = |value: u16, axis: usize| match value {
+            u16::MAX => f32::INFINITY,
+            _ => unscale(value, axis),
+        };
+        [
+            min(bounds[0], 0),
+            min(bounds[1], 1),
+            max(bounds[2], 0),
+            max(bounds[3], 1),
+        ]
+    }
+}
+
+fn bounds_f32<Value: KdValue>(value: &Value) -> [f32; 4]
+where
+    Value::Position: Scalar,
+{
+    [
+        value.min_x().to_f64() as f32,
+        value.min_y().to_f64() as f32,
+        value.max_x().to_f64() as f32,
+        value.max_y().to_f64() as f32,
+    ]
+}
+
+fn union(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [
+        a[0].min(b[0]),
+        a[1].min(b[1]),
+        a[2].max(b[2]),
+        a[3].max(b[3]),
+    ]
+}
+
+fn overlaps(a: &[f32; 4], b: &[f32; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn gpu_snapshot() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..200 {
+            let x = (i * 13 % 71) as f32 * 0.7;
+            let y = (i * 29 % 83) as f32 * 1.3;
+            tree.insert(TestValue::new(x, x + 2.5, y, y + 0.5));
+        }
+        let snapshot = tree.gpu_snapshot();
+        assert_eq!(snapshot.items.len(), 200);
+        let mut expected: Vec<_> = tree.query_rect(10., 20., 30., 60.).collect();
+        let mut found = snapshot.query_rect(10., 20., 30., 60.);
+        let address = |v: &&TestValue| *v as *const TestValue as usize;
+        expected.sort_by_key(address);
+        found.sort_by_key(address);
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn gpu_snapshot_infinite() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..50 {
+            let x = (i * 13 % 71) as f32;
+            tree.insert(TestValue::new(x, x + 2., 0., 1.));
+        }
+        tree.insert(TestValue::new(f32::NEG_INFINITY, f32::INFINITY, 0., 1.));
+        tree.insert(TestValue::new(100., f32::INFINITY, -5., -4.));
+        let snapshot = tree.gpu_snapshot();
+        assert_eq!(snapshot.origin, [0., -5.]);
+        for (min_x, max_x) in [(10., 20.), (-1000., -900.), (500., 600.)] {
+            assert_eq!(
+                snapshot.query_rect(min_x, max_x, -10., 10.).len(),
+                tree.query_rect(min_x, max_x, -10., 10.).count()
+            );
+        }
+        assert_eq!(snapshot.query_rect(500., 600., -10., 10.).len(), 2);
+    }
+}
@@ -0,0 +1,383 @@
+use alloc::{
+    collections::{btree_map, BTreeMap},
+    vec::Vec,
+};
+
+use crate::{scalar::floor, Aabb, KdValue, ProxyId, Scalar};
+
+/// A uniform grid of fixed-size cells, each listing the values overlapping it.
+///
+/// For many small values of similar sizes (bullets, tiles) this is faster than any
+/// tree. Values bigger than a cell are stored in every cell they cover, and are
+/// reported once per query by only keeping them in the cell containing the corner
+/// of their intersection with the query. Only occupied cells are stored.
+///
+/// Values covering more than `MAX_CELLS` cells, such as infinite ones, are kept
+/// in a separate list instead, which every query scans.
+#[derive(Debug)]
+pub struct UniformGrid<Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    cell_size: Value::Position,
+    values: Vec<Option<Value>>,
+    free: Vec<usize>,
+    //keyed by (row, column), so that rows are contiguous
+    cells: BTreeMap<(i64, i64), Vec<usize>>,
+    //the values covering too many cells to list them
+    overflow: Vec<usize>,
+}
+
+//the most cells a value is stored in, bigger ones go in the overflow list
+const MAX_CELLS: u128 = 256;
+
+impl<Value: KdValue> UniformGrid<Value>
+where
+    Value::Position: Scalar,
+{
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not finite and positive.
+    pub fn new(cell_size: Value::Position) -> Self {
+        let size = cell_size.to_f64();
+        assert!(
+            size.is_finite() && size > 0.,
+            "the cell size must be finite and positive"
+        );
+        Self {
+            cell_size,
+            values: Vec::new(),
+            free: Vec::new(),
+            cells: BTreeMap::new(),
+            overflow: Vec::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> Value::Position {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of occupied cells.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get(&self, id: ProxyId) -> Option<&Value> {
+        self.values.get(id.0).and_then(Option::as_ref)
+    }
+
+    pub fn insert(&mut self, value: Value) -> ProxyId {
+        let proxy = match self.free.pop() {
+            Some(proxy) => proxy,
+            None => {
+                self.values.push(None);
+                self.values.len() - 1
+            }
+        };
+        self.link(proxy, &value);
+        self.values[proxy] = Some(value);
+        ProxyId(proxy)
+    }
+
+    pub fn remove(&mut self, id: ProxyId) -> Option<Value> {
+        let value = self.values.get_mut(id.0)?.take()?;
+        self.unlink(id.0, &value);
+        self.free.push(id.0);
+        Some(value)
+    }
+
+    /// Replaces a value, moving it to the cells it now covers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was removed.
+    pub fn update(&mut self, id: ProxyId, value: Value) {
+        let old = self.values[id.0].take().expect("the proxy was removed");
+        if self.cell_range(&Aabb::of(&old)) != self.cell_range(&Aabb::of(&value)) {
+            self.unlink(id.0, &old);
+            self.link(id.0, &value);
+        }
+        self.values[id.0] = Some(value);
+    }
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&Value, &Value)) {
+        for (i, &a) in self.overflow.iter().enumerate() {
+            let a = self.value(a);
+            for &b in &self.overflow[i + 1..] {
+                let b = self.value(b);
+                if Aabb::of(a).overlaps(&Aabb::of(b)) {
+                    f(a, b)
+                }
+            }
+            for b in self.query_cells(Aabb::of(a)) {
+                f(a, b)
+            }
+        }
+        for (&(row, column), proxies) in &self.cells {
+            for (i, &a) in proxies.iter().enumerate() {
+                let a = self.value(a);
+                for &b in &proxies[i + 1..] {
+                    let b = self.value(b);
+                    if !Aabb::of(a).overlaps(&Aabb::of(b)) {
+                        continue;
+                    }
+                    let corner = (max(a.min_y(), b.min_y()), max(a.min_x(), b.min_x()));
+                    if (self.cell(corner.0), self.cell(corner.1)) == (row, column) {
+                        f(a, b)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the proxy of a value, looking in the cell of its minimum corner.
+    pub fn find(&self, value: &Value) -> Option<ProxyId> {
+        let proxies = if self.overflows(value) {
+            &self.overflow
+        } else {
+            let cell = (self.cell(value.min_y()), self.cell(value.min_x()));
+            self.cells.get(&cell)?
+        };
+        proxies
+            .iter()
+            .find(|&&proxy| self.value(proxy) == value)
+            .map(|&proxy| ProxyId(proxy))
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> GridRectQuery<'_, Value> {
+        let mut query = self.query_cells(Aabb::new(min_x, max_x, min_y, max_y));
+        query.overflow = self.overflow.iter();
+        query
+    }
+
+    //the query leaving out the overflow list
+    fn query_cells(&self, rect: Aabb<Value::Position>) -> GridRectQuery<'_, Value> {
+        let ((column_min, row_min), (column_max, row_max)) = self.cell_range(&rect);
+        let cells = if row_min <= row_max && column_min <= column_max {
+            self.cells
+                .range((row_min, column_min)..=(row_max, column_max))
+        } else {
+            btree_map::Range::default()
+        };
+        GridRectQuery {
+            grid: self,
+            rect,
+            columns: (column_min, column_max),
+            cells,
+            cell: (0, 0),
+            proxies: [].iter(),
+            overflow: [].iter(),
+        }
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> GridRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    fn value(&self, proxy: usize) -> &Value {
+        self.values[proxy].as_ref().expect("live proxy")
+    }
+
+    fn cell(&self, position: Value::Position) -> i64 {
+        //saturates on huge and infinite positions, NaN ends up in cell 0
+        floor(position.to_f64() / self.cell_size.to_f64()) as i64
+    }
+
+    fn cell_range(&self, bounds: &Aabb<Value::Position>) -> ((i64, i64), (i64, i64)) {
+        (
+            (self.cell(bounds.min_x), self.cell(bounds.min_y)),
+            (self.cell(bounds.max_x), self.cell(bounds.max_y)),
+        )
+    }
+
+    fn overflows(&self, value: &Value) -> bool {
+        let ((column_min, row_min), (column_max, row_max)) = self.cell_range(&Aabb::of(value));
+        let span = |min: i64, max: i64| (max as i128 - min as i128 + 1).max(0) as u128;
+        span(column_min, column_max).saturating_mul(span(row_min, row_max)) > MAX_CELLS
+    }
+
+    fn covered_cells(&self, value: &Value) -> impl Iterator<Item = (i64, i64)> {
+        let ((column_min, row_min), (column_max, row_max)) = self.cell_range(&Aabb::of(value));
+        (row_min..=row_max).flat_map(move |row| (column_min..=column_max).map(move |c| (row, c)))
+    }
+
+    fn link(&mut self, proxy: usize, value: &Value) {
+        if self.overflows(value) {
+            self.overflow.push(proxy);
+            return;
+        }
+        for cell in self.covered_cells(value).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(proxy);
+        }
+    }
+
+    fn unlink(&mut self, proxy: usize, value: &Value) {
+        if self.overflows(value) {
+            self.overflow.retain(|&p| p != proxy);
+            return;
+        }
+        for cell in self.covered_cells(value).collect::<Vec<_>>() {
+            if let btree_map::Entry::Occupied(mut entry) = self.cells.entry(cell) {
+                entry.get_mut().retain(|&p| p != proxy);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+}
+
+fn max<P: PartialOrd>(a: P, b: P) -> P {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+/// The values of a `UniformGrid` overlapping a rectangle.
+#[derive(Debug)]
+pub struct GridRectQuery<'a, Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    grid: &'a UniformGrid<Value>,
+    rect: Aabb<Value::Position>,
+    columns: (i64, i64),
+    cells: btree_map::Range<'a, (i64, i64), Vec<usize>>,
+    cell: (i64, i64),
+    proxies: core::slice::Iter<'a, usize>,
+    overflow: core::slice::Iter<'a, usize>,
+}
+
+impl<'a, Value: KdValue> Iterator for GridRectQuery<'a, Value>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for &proxy in self.overflow.by_ref() {
+            let value = self.grid.value(proxy);
+            if Aabb::of(value).overlaps(&self.rect) {
+                return Some(value);
+            }
+        }
+        loop {
+            for &proxy in self.proxies.by_ref() {
+                let value = self.grid.value(proxy);
+                if !Aabb::of(value).overlaps(&self.rect) {
+                    continue;
+                }
+                let row = self.grid.cell(max(value.min_y(), self.rect.min_y));
+                let column = self.grid.cell(max(value.min_x(), self.rect.min_x));
+                if (row, column) == self.cell {
+                    return Some(value);
+                }
+            }
+            //the range spans whole rows, skip the columns outside of the query
+            let columns = self.columns.0..=self.columns.1;
+            let (&cell, proxies) = self
+                .cells
+                .find(|((_, column), _)| columns.contains(column))?;
+            self.cell = cell;
+            self.proxies = proxies.iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UniformGrid;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn uniform_grid() {
+        let mut grid = UniformGrid::<TestValue>::new(4.);
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let values: Vec<_> = (0..80)
+            .map(|i| {
+                let (x, y) = ((i * 7 % 31) as f32 - 10., (i * 13 % 29) as f32 - 10.);
+                let size = (i % 4) as f32 * 3.;
+                TestValue::new(x, x + size, y, y + size)
+            })
+            .collect();
+        let ids: Vec<_> = values.iter().map(|v| grid.insert(v.clone())).collect();
+        for value in &values {
+            tree.insert(value.clone());
+        }
+        let moved = TestValue::new(30., 31., 30., 31.);
+        grid.update(ids[0], moved.clone());
+        tree.remove_one(values[0].clone());
+        tree.insert(moved);
+        for (min_x, max_x, min_y, max_y) in [
+            (-3., 9., -6., 2.),
+            (0., 0., 0., 0.),
+            (-100., 100., -100., 100.),
+        ] {
+            assert_eq!(
+                grid.query_rect(min_x, max_x, min_y, max_y).count(),
+                tree.query_rect(min_x, max_x, min_y, max_y).count()
+            );
+        }
+        let (mut grid_pairs, mut tree_pairs) = (0, 0);
+        grid.for_each_pair(|_, _| grid_pairs += 1);
+        tree.for_each_pair(|_, _| tree_pairs += 1);
+        assert_eq!(grid_pairs, tree_pairs);
+        for id in ids {
+            grid.remove(id);
+        }
+        assert_eq!(grid.cell_count(), 0);
+    }
+
+    #[test]
+    fn huge_values() {
+        let mut grid = UniformGrid::<TestValue>::new(1.);
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let values = [
+            TestValue::new(f32::NEG_INFINITY, f32::INFINITY, 0., 1.),
+            TestValue::new(-1e30, 1e30, -1e30, 1e30),
+            TestValue::new(0., 100., 0., 100.),
+            TestValue::new(2., 3., 2., 3.),
+            TestValue::new(50., 51., 0., 1.),
+        ];
+        let ids: Vec<_> = values.iter().map(|v| grid.insert(v.clone())).collect();
+        for value in &values {
+            tree.insert(value.clone());
+        }
+        for (min_x, max_x, min_y, max_y) in [(2.5, 2.5, 2.5, 2.5), (-1e6, -1e6, 0.5, 0.5)] {
+            assert_eq!(
+                grid.query_rect(min_x, max_x, min_y, max_y).count(),
+                tree.query_rect(min_x, max_x, min_y, max_y).count()
+            );
+        }
+        let (mut grid_pairs, mut tree_pairs) = (0, 0);
+        grid.for_each_pair(|_, _| grid_pairs += 1);
+        tree.for_each_pair(|_, _| tree_pairs += 1);
+        assert_eq!(grid_pairs, tree_pairs);
+        assert_eq!(grid.find(&values[1]), Some(ids[1]));
+        grid.remove(ids[0]);
+        assert_eq!(grid.query_point(-1e6, 0.5).count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite and positive")]
+    fn zero_cell_size() {
+        UniformGrid::<TestValue>::new(0.);
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::{cmp_position, KdTree, KdValue, Scalar};
+
+/// The points of the values a convex hull is built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HullPoints {
+    Centers,
+    Corners,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// The convex hull of the centers or corners of the values overlapping the
+    /// rectangle, counterclockwise from the lowest x, without collinear points.
+    ///
+    /// Points are streamed from the query, and only the ones outside of the hull
+    /// found so far are kept until it is rebuilt.
+    pub fn convex_hull_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        points: HullPoints,
+    ) -> Vec<(f64, f64)> {
+        let mut hull = Vec::new();
+        let mut pending = Vec::new();
+        for value in self.query_rect(min_x, max_x, min_y, max_y) {
+            let (x0, x1) = (value.min_x().to_f64(), value.max_x().to_f64());
+            let (y0, y1) = (value.min_y().to_f64(), value.max_y().to_f64());
+            let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+            let candidates = match points {
+                HullPoints::Centers => &[((x0 + x1) / 2., (y0 + y1) / 2.)][..],
+                HullPoints::Corners => &corners[..],
+            };
+            for &point in candidates {
+                if !inside(&hull, point) {
+                    pending.push(point);
+                }
+            }
+            if pending.len() > hull.len().max(64) {
+                pending.append(&mut hull);
+                hull = monotone_chain(pending);
+                pending = Vec::new();
+            }
+        }
+        pending.append(&mut hull);
+        monotone_chain(pending)
+    }
+}
+
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+//a hull of less than 3 points contains nothing
+fn inside(hull: &[(f64, f64)], point: (f64, f64)) -> bool {
+    hull.len() >= 3
+        && (0..hull.len()).all(|i| cross(hull[i], hull[(i + 1) % hull.len()], point) >= 0.)
+}
+
+fn monotone_chain(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_unstable_by(|a, b| match cmp_position(&a.0, &b.0) {
+        Ordering::Equal => cmp_position(&a.1, &b.1),
+        ordering => ordering,
+    });
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() + 1);
+    //the lower chain left to right, then the upper one right to left
+    for pass in 0..2 {
+        let start = hull.len();
+        for &point in points.iter() {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        //the last point starts the other chain
+        hull.pop();
+        if pass == 0 {
+            points.reverse();
+        }
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HullPoints;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn convex_hull() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        tree.insert(TestValue::new(0., 2., 0., 2.));
+        tree.insert(TestValue::new(10., 12., 0., 4.));
+        tree.insert(TestValue::new(4., 6., 1., 3.));
+        tree.insert(TestValue::new(5., 7., 8., 10.));
+        let hull = tree.convex_hull_in_rect(0., 20., 0., 20., HullPoints::Corners);
+        assert_eq!(
+            hull,
+            vec![
+                (0., 0.),
+                (12., 0.),
+                (12., 4.),
+                (7., 10.),
+                (5., 10.),
+                (0., 2.)
+            ]
+        );
+        let hull = tree.convex_hull_in_rect(0., 20., 0., 5., HullPoints::Centers);
+        assert_eq!(hull, vec![(1., 1.), (11., 2.), (5., 2.)]);
+        let hull = tree.convex_hull_in_rect(4., 7., 9., 20., HullPoints::Centers);
+        assert_eq!(hull, vec![(6., 9.)]);
+        assert!(tree
+            .convex_hull_in_rect(-5., -1., -5., -1., HullPoints::Corners)
+            .is_empty());
+
+        //enough values to rebuild the hull while streaming
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let mut corners = Vec::new();
+        for i in 0..500 {
+            let (x, y) = ((i * 37 % 101) as f32, (i * 11 % 97) as f32);
+            let (width, height) = ((i % 5) as f32, (i % 3) as f32);
+            tree.insert(TestValue::new(x, x + width, y, y + height));
+            if x <= 60. && x + width >= 20. && y <= 70. && y + height >= 30. {
+                for (x, y) in [
+                    (x, y),
+                    (x + width, y),
+                    (x + width, y + height),
+                    (x, y + height),
+                ] {
+                    corners.push((x as f64, y as f64));
+                }
+            }
+        }
+        assert_eq!(
+            tree.convex_hull_in_rect(20., 60., 30., 70., HullPoints::Corners),
+            super::monotone_chain(corners)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    DynamicAabbTree, DynamicRectQuery, GridRectQuery, KdTree, KdValue, LooseQuadtree,
+    LooseRectQuery, RectQuery, Scalar, SweepAndPrune, SweepRectQuery, UniformGrid,
+};
+
+/// The operations shared by every mutable spatial structure of the crate, so that
+/// applications can swap them behind a generic parameter and benchmark them
+/// against each other.
+///
+/// Backends handing out proxy ids implement `remove_one` by looking the value up,
+/// which can be slower than removing by id.
+pub trait SpatialIndex2D {
+    type Value: KdValue;
+    type Query<'a>: Iterator<Item = &'a Self::Value>
+    where
+        Self: 'a;
+
+    fn insert(&mut self, value: Self::Value);
+
+    /// Removes one value equal to `value`, returning whether one was found.
+    fn remove_one(&mut self, value: &Self::Value) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn query_rect(
+        &self,
+        min_x: <Self::Value as KdValue>::Position,
+        max_x: <Self::Value as KdValue>::Position,
+        min_y: <Self::Value as KdValue>::Position,
+        max_y: <Self::Value as KdValue>::Position,
+    ) -> Self::Query<'_>;
+
+    fn query_point(
+        &self,
+        x: <Self::Value as KdValue>::Position,
+        y: <Self::Value as KdValue>::Position,
+    ) -> Self::Query<'_>;
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    fn for_each_pair(&self, f: &mut dyn FnMut(&Self::Value, &Self::Value));
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> SpatialIndex2D for KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    type Value = Value;
+    type Query<'a>
+        = RectQuery<'a, Value, ISLAND_SIZE>
+    where
+        Self: 'a;
+
+    fn insert(&mut self, value: Value) {
+        KdTree::insert(self, value)
+    }
+
+    fn remove_one(&mut self, value: &Value) -> bool {
+        KdTree::remove_one(self, value.clone())
+    }
+
+    fn len(&self) -> usize {
+        KdTree::len(self)
+    }
+
+    fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Self::Query<'_> {
+        KdTree::query_rect(self, min_x, max_x, min_y, max_y)
+    }
+
+    fn query_point(&self, x: Value::Position, y: Value::Position) -> Self::Query<'_> {
+        KdTree::query_rect(self, x.clone(), x, y.clone(), y)
+    }
+
+    fn for_each_pair(&self, f: &mut dyn FnMut(&Value, &Value)) {
+        KdTree::for_each_pair(self, f)
+    }
+}
+
+//the backends identifying values with proxies only differ in their types
+macro_rules! impl_proxy_index {
+    ($index:ident, $query:ident) => {
+        impl<Value: KdValue> SpatialIndex2D for $index<Value>
+        where
+            Value::Position: Scalar,
+        {
+            type Value = Value;
+            type Query<'a>
+                = $query<'a, Value>
+            where
+                Self: 'a;
+
+            fn insert(&mut self, value: Value) {
+                $index::insert(self, value);
+            }
+
+            fn remove_one(&mut self, value: &Value) -> bool {
+                match self.find(value) {
+                    Some(id) => self.remove(id).is_some(),
+                    None => false,
+                }
+            }
+
+            fn len(&self) -> usize {
+                $index::len(self)
+            }
+
+            fn query_rect(
+                &self,
+                min_x: Value::Position,
+                max_x: Value::Position,
+                min_y: Value::Position,
+                max_y: Value::Position,
+            ) -> Self::Query<'_> {
+                $index::query_rect(self, min_x, max_x, min_y, max_y)
+            }
+
+            fn query_point(&self, x: Value::Position, y: Value::Position) -> Self::Query<'_> {
+                $index::query_point(self, x, y)
+            }
+
+            fn for_each_pair(&self, f: &mut dyn FnMut(&Value, &Value)) {
+                $index::for_each_pair(self, f)
+            }
+        }
+    };
+}
+
+impl_proxy_index!(DynamicAabbTree, DynamicRectQuery);
+impl_proxy_index!(SweepAndPrune, SweepRectQuery);
+impl_proxy_index!(UniformGrid, GridRectQuery);
+
+impl<Value: KdValue> SpatialIndex2D for LooseQuadtree<Value>
+where
+    Value::Position: Scalar,
+{
+    type Value = Value;
+    type Query<'a>
+        = LooseRectQuery<'a, Value>
+    where
+        Self: 'a;
+
+    fn insert(&mut self, value: Value) {
+        LooseQuadtree::insert(self, value)
+    }
+
+    fn remove_one(&mut self, value: &Value) -> bool {
+        LooseQuadtree::remove_one(self, value.clone())
+    }
+
+    fn len(&self) -> usize {
+        LooseQuadtree::len(self)
+    }
+
+    fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Self::Query<'_> {
+        LooseQuadtree::query_rect(self, min_x, max_x, min_y, max_y)
+    }
+
+    fn query_point(&self, x: Value::Position, y: Value::Position) -> Self::Query<'_> {
+        LooseQuadtree::query_point(self, x, y)
+    }
+
+    fn for_each_pair(&self, f: &mut dyn FnMut(&Value, &Value)) {
+        LooseQuadtree::for_each_pair(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpatialIndex2D;
+    use crate::{
+        tests::TestValue, Aabb, DynamicAabbTree, KdTree, LooseQuadtree, SweepAndPrune, UniformGrid,
+    };
+
+    fn exercise<Index: SpatialIndex2D<Value = TestValue>>(
+        mut index: Index,
+    ) -> (usize, usize, usize) {
+        for i in 0..50 {
+            let (x, y) = ((i * 7 % 19) as f32, (i * 5 % 13) as f32);
+            index.insert(TestValue::new(x, x + 2., y, y + 2.));
+        }
+        assert!(index.remove_one(&TestValue::new(7., 9., 5., 7.)));
+        let mut pairs = 0;
+        index.for_each_pair(&mut |_, _| pairs += 1);
+        (
+            index.len(),
+            index.query_rect(3., 8., 2., 6.).count(),
+            pairs + index.query_point(4., 4.).count(),
+        )
+    }
+
+    #[test]
+    fn backends_agree() {
+        let expected = exercise(KdTree::<TestValue, 4>::default());
+        assert_eq!(expected.0, 49);
+        assert_eq!(exercise(DynamicAabbTree::new(0.5)), expected);
+        assert_eq!(exercise(SweepAndPrune::default()), expected);
+        assert_eq!(exercise(UniformGrid::new(3.)), expected);
+        assert_eq!(
+            exercise(LooseQuadtree::new(Aabb::new(0., 32., 0., 32.), 5)),
+            expected
+        );
+        #[cfg(feature = "testing")]
+        assert_eq!(exercise(crate::BruteForceIndex::new()), expected);
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{KdTree, KdValue, ValueMut};
+
+/// A tree along with an ordered index from the id of each value to its slot in
+/// the tree: the path to its leaf and its index there.
+///
+/// The slots are updated when a leaf is split by an insertion and when a removal
+/// moves the last value of a leaf, so `get`, `get_mut` and `remove` cost a lookup
+/// in the index and following the path, without comparing positions nor
+/// scanning the leaf.
+///
+/// Ids are given by the `id` closure and are unique: inserting a value replaces
+/// the one with the same id. Lookups and removals by id don't depend on the
+/// current bounds of the values, nor on them being equal to a copy.
+#[derive(Debug)]
+pub struct IndexedKdTree<Id, Value: KdValue, F, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    slots: BTreeMap<Id, Slot>,
+    id: F,
+}
+
+#[derive(Debug)]
+struct Slot {
+    //`true` going right
+    path: Vec<bool>,
+    index: usize,
+}
+
+impl<Id: Ord, Value: KdValue, F: Fn(&Value) -> Id, const ISLAND_SIZE: usize>
+    IndexedKdTree<Id, Value, F, ISLAND_SIZE>
+{
+    pub fn new(id: F) -> Self {
+        Self {
+            tree: KdTree::default(),
+            slots: BTreeMap::new(),
+            id,
+        }
+    }
+
+    /// The tree, to query it.
+    pub fn tree(&self) -> &KdTree<Value, ISLAND_SIZE> {
+        &self.tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Inserts the value, returning the one it replaced if its id was used.
+    pub fn insert(&mut self, value: Value) -> Option<Value> {
+        let old = self.remove(&(self.id)(&value));
+        let (slots, key) = (&mut self.slots, &self.id);
+        self.tree.insert_placed(value, |value, path, index| {
+            let slot = Slot {
+                path: path.to_vec(),
+                index,
+            };
+            slots.insert(key(value), slot);
+        });
+        old
+    }
+
+    pub fn contains(&self, id: &Id) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    pub fn get(&self, id: &Id) -> Option<&Value> {
+        let slot = self.slots.get(id)?;
+        Some(&self.tree.leaf_at(&slot.path)[slot.index])
+    }
+
+    /// The value with this id, whose bounds must be left unchanged like for
+    /// `KdTree::query_rect_mut`.
+    pub fn get_mut(&mut self, id: &Id) -> Option<ValueMut<'_, Value>> {
+        let slot = self.slots.get(id)?;
+        let (leaf, ..) = self.tree.leaf_at_mut(&slot.path);
+        Some(ValueMut::new(&mut leaf[slot.index]))
+    }
+
+    pub fn remove(&mut self, id: &Id) -> Option<Value> {
+        let Slot { path, index } = self.slots.remove(id)?;
+        let (leaf, stored) = self.tree.leaf_at_mut(&path);
+        let value = leaf.swap_remove(index);
+        stored.swap_removed(index, leaf.len());
+        //the last value of the leaf took its place
+        if let Some(moved) = leaf.get(index) {
+            if let Some(slot) = self.slots.get_mut(&(self.id)(moved)) {
+                slot.index = index;
+            }
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexedKdTree;
+    use crate::{Aabb, PayloadCell};
+
+    #[test]
+    fn indexed_tree() {
+        let mut tree = IndexedKdTree::<_, _, _, 4>::new(|value: &PayloadCell<Aabb<f32>, u32>| {
+            *value.payload()
+        });
+        for i in 0..200 {
+            let x = (i * 37 % 101) as f32;
+            assert!(tree
+                .insert(PayloadCell::new(Aabb::new(x, x + 1., 0., 1.), i))
+                .is_none());
+        }
+        assert_eq!(tree.len(), 200);
+        assert!(tree.contains(&17));
+        assert_eq!(tree.get(&17).map(|value| value.bounds().min_x), Some(23.));
+        //replacing by id
+        let old = tree.insert(PayloadCell::new(Aabb::new(-5., -4., 0., 1.), 17));
+        assert_eq!(old.map(|value| value.bounds().min_x), Some(23.));
+        assert_eq!(tree.len(), 200);
+        assert_eq!(tree.tree().query_point(-4.5, 0.5).count(), 1);
+        assert_eq!(
+            tree.get_mut(&17).map(|value| value.bounds().min_x),
+            Some(-5.)
+        );
+        for i in 0..100 {
+            assert!(tree.remove(&i).is_some());
+        }
+        assert!(tree.remove(&17).is_none());
+        assert!(!tree.contains(&17));
+        assert_eq!(tree.len(), 100);
+        assert_eq!(tree.tree().len(), 100);
+        assert!(tree.get(&150).is_some());
+        //the slots follow the splits and the values moved by removals
+        for i in 100..200 {
+            assert_eq!(tree.get(&i).map(|value| *value.payload()), Some(i));
+        }
+    }
+
+    #[test]
+    fn nan_bounds() {
+        let mut tree = IndexedKdTree::<_, _, _, 4>::new(|value: &PayloadCell<Aabb<f32>, u32>| {
+            *value.payload()
+        });
+        tree.insert(PayloadCell::new(Aabb::new(0., 1., 0., f32::NAN), 3));
+        assert!(tree.get_mut(&3).is_some());
+        assert!(tree.remove(&3).is_some());
+    }
+}
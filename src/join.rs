@@ -0,0 +1,246 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{KdTree, KdValue};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// The values overlapping nothing in `other`.
+    ///
+    /// Both trees are walked together: each subtree of this tree only keeps the
+    /// subtrees of `other` which can reach it, and is yielded whole once none are
+    /// left.
+    pub fn not_overlapping<'a, Other, const OTHER_ISLAND_SIZE: usize>(
+        &'a self,
+        other: &'a KdTree<Other, OTHER_ISLAND_SIZE>,
+    ) -> JoinQuery<'a, Value, Other, ISLAND_SIZE, OTHER_ISLAND_SIZE>
+    where
+        Other: KdValue<Position = Value::Position>,
+    {
+        JoinQuery::new(self, other, false)
+    }
+
+    /// The values overlapping anything in `other`, each yielded once however many
+    /// values of `other` it overlaps.
+    ///
+    /// Walks both trees together like `not_overlapping`, dropping the subtrees of
+    /// this tree that no subtree of `other` can reach.
+    pub fn overlapping_any<'a, Other, const OTHER_ISLAND_SIZE: usize>(
+        &'a self,
+        other: &'a KdTree<Other, OTHER_ISLAND_SIZE>,
+    ) -> JoinQuery<'a, Value, Other, ISLAND_SIZE, OTHER_ISLAND_SIZE>
+    where
+        Other: KdValue<Position = Value::Position>,
+    {
+        JoinQuery::new(self, other, true)
+    }
+}
+
+//the range of the values of a subtree along x and y, unbounded when `None`
+#[derive(Clone)]
+struct Region<P> {
+    low: [Option<P>; 2],
+    high: [Option<P>; 2],
+}
+
+impl<P: PartialOrd + Clone> Region<P> {
+    fn left(&self, axis: usize, left_max: &P) -> Self {
+        let mut high = self.high.clone();
+        if high[axis].as_ref().is_none_or(|high| left_max < high) {
+            high[axis] = Some(left_max.clone());
+        }
+        Self {
+            low: self.low.clone(),
+            high,
+        }
+    }
+
+    fn right(&self, axis: usize, median: &P) -> Self {
+        let mut low = self.low.clone();
+        if low[axis].as_ref().is_none_or(|low| median > low) {
+            low[axis] = Some(median.clone());
+        }
+        Self {
+            low,
+            high: self.high.clone(),
+        }
+    }
+
+    //whether values of the two regions can overlap
+    fn overlaps(&self, other: &Self) -> bool {
+        let below = |low: &Option<P>, high: &Option<P>| match (low, high) {
+            (Some(low), Some(high)) => low <= high,
+            _ => true,
+        };
+        (0..2).all(|axis| {
+            below(&self.low[axis], &other.high[axis]) && below(&other.low[axis], &self.high[axis])
+        })
+    }
+}
+
+type Subtree<'a, Value, const ISLAND_SIZE: usize> = (
+    &'a KdTree<Value, ISLAND_SIZE>,
+    Region<<Value as KdValue>::Position>,
+);
+
+/// The values of a tree kept by whether they overlap anything in another tree.
+pub struct JoinQuery<'a, Value: KdValue, Other: KdValue, const A: usize, const B: usize> {
+    //subtrees left to visit, with their region and the subtrees of the other
+    //tree which can reach it
+    queue: Vec<(Subtree<'a, Value, A>, Vec<Subtree<'a, Other, B>>)>,
+    items_to_yield: Vec<&'a Value>,
+    overlapping: bool,
+}
+
+impl<
+        'a,
+        Value: KdValue,
+        Other: KdValue<Position = Value::Position>,
+        const A: usize,
+        const B: usize,
+    > JoinQuery<'a, Value, Other, A, B>
+where
+    Value::Position: Clone,
+{
+    fn new(tree: &'a KdTree<Value, A>, other: &'a KdTree<Other, B>, overlapping: bool) -> Self {
+        let region = || Region {
+            low: [None, None],
+            high: [None, None],
+        };
+        Self {
+            queue: vec![((tree, region()), vec![(other, region())])],
+            items_to_yield: Vec::new(),
+            overlapping,
+        }
+    }
+
+    fn visit(
+        &mut self,
+        tree: &'a KdTree<Value, A>,
+        region: Region<Value::Position>,
+        candidates: Vec<Subtree<'a, Other, B>>,
+    ) {
+        if candidates.is_empty() {
+            if !self.overlapping {
+                self.items_to_yield.extend(tree.iter());
+            }
+            return;
+        }
+        match tree {
+            KdTree::Leaf(values, ..) => {
+                for value in values {
+                    let overlaps = candidates.iter().any(|(candidate, _)| {
+                        candidate
+                            .query_rect(value.min_x(), value.max_x(), value.min_y(), value.max_y())
+                            .next()
+                            .is_some()
+                    });
+                    if overlaps == self.overlapping {
+                        self.items_to_yield.push(value);
+                    }
+                }
+            }
+            KdTree::Node(node) => {
+                //the candidates go one level down along with this tree
+                let mut children = Vec::new();
+                for (candidate, candidate_region) in candidates {
+                    match candidate {
+                        KdTree::Leaf(values, ..) if values.is_empty() => {}
+                        KdTree::Leaf(..) => children.push((candidate, candidate_region)),
+                        KdTree::Node(other) => {
+                            let axis = other.vertical as usize;
+                            children
+                                .push((&other.left, candidate_region.left(axis, &other.left_max)));
+                            children
+                                .push((&other.right, candidate_region.right(axis, &other.median)));
+                        }
+                    }
+                }
+                let axis = node.vertical as usize;
+                let left = region.left(axis, &node.left_max);
+                let right = region.right(axis, &node.median);
+                let reaching = |region: &Region<Value::Position>| -> Vec<Subtree<'a, Other, B>> {
+                    children
+                        .iter()
+                        .filter(|(_, child_region)| child_region.overlaps(region))
+                        .map(|(child, child_region)| (*child, child_region.clone()))
+                        .collect()
+                };
+                let (left_candidates, right_candidates) = (reaching(&left), reaching(&right));
+                self.queue.push(((&node.right, right), right_candidates));
+                self.queue.push(((&node.left, left), left_candidates));
+            }
+        }
+    }
+}
+
+impl<
+        'a,
+        Value: KdValue,
+        Other: KdValue<Position = Value::Position>,
+        const A: usize,
+        const B: usize,
+    > Iterator for JoinQuery<'a, Value, Other, A, B>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.items_to_yield.pop();
+            if item.is_some() {
+                return item;
+            }
+            let ((tree, region), candidates) = self.queue.pop()?;
+            self.visit(tree, region, candidates);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, Aabb, KdTree};
+
+    #[test]
+    fn not_overlapping() {
+        let mut parcels = KdTree::<TestValue, 4>::default();
+        let mut buildings = KdTree::<Aabb<f32>, 8>::default();
+        let mut all_buildings = Vec::new();
+        for i in 0..400 {
+            let (x, y) = ((i % 20) as f32 * 5., (i / 20) as f32 * 5.);
+            parcels.insert(TestValue::new(x, x + 4., y, y + 4.));
+        }
+        for i in 0..60 {
+            let (x, y) = ((i * 37 % 97) as f32, (i * 53 % 89) as f32);
+            let building = Aabb::new(x, x + 0.5, y, y + 0.5);
+            buildings.insert(building);
+            all_buildings.push(building);
+        }
+        let mut expected: Vec<_> = parcels
+            .iter()
+            .filter(|parcel| {
+                !all_buildings
+                    .iter()
+                    .any(|building| Aabb::of(*parcel).overlaps(building))
+            })
+            .collect();
+        let mut found: Vec<_> = parcels.not_overlapping(&buildings).collect();
+        let address = |v: &&TestValue| *v as *const TestValue as usize;
+        expected.sort_by_key(address);
+        found.sort_by_key(address);
+        assert!(!found.is_empty() && found.len() < 400);
+        assert_eq!(found, expected);
+
+        //no parcel is yielded twice
+        let mut covered: Vec<_> = parcels.overlapping_any(&buildings).collect();
+        covered.sort_by_key(address);
+        assert_eq!(covered.len() + found.len(), 400);
+        assert!(covered.iter().all(|parcel| !found.contains(parcel)));
+
+        let empty = KdTree::<Aabb<f32>, 8>::default();
+        assert_eq!(parcels.not_overlapping(&empty).count(), 400);
+        assert_eq!(parcels.overlapping_any(&empty).count(), 0);
+    }
+}
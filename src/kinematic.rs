@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use crate::{Aabb, KdTree, KdValue, Scalar};
+
+/// The motion of a box clipped at its first collision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motion<'a, Value: KdValue> {
+    /// The offset the box can move by.
+    pub dx: Value::Position,
+    pub dy: Value::Position,
+    /// The fraction of the requested motion done before the collision, 1 if none.
+    pub time: f64,
+    pub hit: Option<&'a Value>,
+}
+
+/// A box moving at a constant velocity.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Mover<P> {
+    pub bounds: Aabb<P>,
+    pub vx: P,
+    pub vy: P,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Moves `bounds` by `(dx, dy)` and stops it at the first value it would hit.
+    ///
+    /// Values already overlapping the box, or only touching it while sliding along
+    /// them, do not block it, and `ignore` skips values such as the mover itself.
+    pub fn sweep_aabb(
+        &self,
+        bounds: &Aabb<Value::Position>,
+        dx: Value::Position,
+        dy: Value::Position,
+        ignore: impl Fn(&Value) -> bool,
+    ) -> Motion<'_, Value> {
+        let moved = Aabb::new(
+            bounds.min_x + dx,
+            bounds.max_x + dx,
+            bounds.min_y + dy,
+            bounds.max_y + dy,
+        );
+        let swept = bounds.union(&moved);
+        let mut first: Option<(f64, &Value)> = None;
+        for value in self.query_rect(swept.min_x, swept.max_x, swept.min_y, swept.max_y) {
+            if ignore(value) {
+                continue;
+            }
+            if let Some(time) = time_of_impact(bounds, &Aabb::of(value), dx, dy) {
+                if first.is_none_or(|(first, _)| time < first) {
+                    first = Some((time, value));
+                }
+            }
+        }
+        match first {
+            Some((time, hit)) => Motion {
+                dx: Value::Position::from_f64(dx.to_f64() * time),
+                dy: Value::Position::from_f64(dy.to_f64() * time),
+                time,
+                hit: Some(hit),
+            },
+            None => Motion {
+                dx,
+                dy,
+                time: 1.,
+                hit: None,
+            },
+        }
+    }
+
+    /// Sweeps every mover by its velocity times `dt` against the tree, and
+    /// returns their clipped motions, in order. `ignore` is given the index of the
+    /// mover and a candidate value.
+    pub fn sweep_step(
+        &self,
+        movers: &[Mover<Value::Position>],
+        dt: Value::Position,
+        ignore: impl Fn(usize, &Value) -> bool,
+    ) -> Vec<Motion<'_, Value>> {
+        movers
+            .iter()
+            .enumerate()
+            .map(|(index, mover)| {
+                self.sweep_aabb(&mover.bounds, mover.vx * dt, mover.vy * dt, |value| {
+                    ignore(index, value)
+                })
+            })
+            .collect()
+    }
+}
+
+//the slab test between a moving and a static box, in fractions of the motion
+fn time_of_impact<P: Scalar>(moving: &Aabb<P>, other: &Aabb<P>, dx: P, dy: P) -> Option<f64> {
+    let axis = |min: P, max: P, other_min: P, other_max: P, d: P| {
+        let (min, max, other_min, other_max, d) = (
+            min.to_f64(),
+            max.to_f64(),
+            other_min.to_f64(),
+            other_max.to_f64(),
+            d.to_f64(),
+        );
+        if d == 0. {
+            //touching along a still axis is sliding, not colliding
+            if min < other_max && other_min < max {
+                Some((f64::NEG_INFINITY, f64::INFINITY))
+            } else {
+                None
+            }
+        } else if d > 0. {
+            Some(((other_min - max) / d, (other_max - min) / d))
+        } else {
+            Some(((other_max - min) / d, (other_min - max) / d))
+        }
+    };
+    let (x_entry, x_exit) = axis(moving.min_x, moving.max_x, other.min_x, other.max_x, dx)?;
+    let (y_entry, y_exit) = axis(moving.min_y, moving.max_y, other.min_y, other.max_y, dy)?;
+    let entry = x_entry.max(y_entry);
+    let exit = x_exit.min(y_exit);
+    if entry < exit && (0. ..=1.).contains(&entry) {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mover;
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn sweep_step() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        let floor = Aabb::new(-100., 100., -1., 0.);
+        let wall = Aabb::new(10., 11., 0., 10.);
+        tree.insert(floor);
+        tree.insert(wall);
+        let player = Aabb::new(0., 1., 0., 2.);
+        let movers = [
+            Mover {
+                bounds: player,
+                vx: 20.,
+                vy: 0.,
+            },
+            Mover {
+                bounds: player,
+                vx: -4.,
+                vy: -4.,
+            },
+        ];
+        let motions = tree.sweep_step(&movers, 1., |_, _| false);
+        //slides on the floor until the wall
+        assert_eq!(motions[0].hit, Some(&wall));
+        assert_eq!((motions[0].dx, motions[0].dy), (9., 0.));
+        //lands on the floor right away
+        assert_eq!(motions[1].hit, Some(&floor));
+        assert_eq!(motions[1].time, 0.);
+        let free = tree.sweep_aabb(&Aabb::new(0., 1., 5., 6.), 3., 2., |_| false);
+        assert_eq!((free.dx, free.dy, free.hit), (3., 2., None));
+    }
+}
@@ -0,0 +1,78 @@
+use kurbo::{Point, Rect};
+
+use crate::{Aabb, KdTree, KdValue, PointQuery, RectQuery};
+
+//rects are not necessarily normalized, so the bounds go through kurbo's min/max
+impl KdValue for Rect {
+    type Position = f64;
+
+    fn min_x(&self) -> f64 {
+        Rect::min_x(self)
+    }
+
+    fn min_y(&self) -> f64 {
+        Rect::min_y(self)
+    }
+
+    fn max_x(&self) -> f64 {
+        Rect::max_x(self)
+    }
+
+    fn max_y(&self) -> f64 {
+        Rect::max_y(self)
+    }
+}
+
+impl From<Rect> for Aabb<f64> {
+    fn from(rect: Rect) -> Self {
+        Aabb::of(&rect)
+    }
+}
+
+impl From<Aabb<f64>> for Rect {
+    fn from(bounds: Aabb<f64>) -> Self {
+        Rect::new(bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y)
+    }
+}
+
+impl<Value: KdValue<Position = f64>, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Like `query_point`, with a kurbo point, for hit-testing.
+    pub fn query_kurbo_point(&self, point: Point) -> PointQuery<'_, Value, ISLAND_SIZE> {
+        self.query_point(point.x, point.y)
+    }
+
+    /// Like `query_rect`, with a kurbo rect, which does not need to be normalized.
+    pub fn query_kurbo_rect(&self, rect: Rect) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(rect.min_x(), rect.max_x(), rect.min_y(), rect.max_y())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::{Point, Rect};
+
+    use crate::{Aabb, KdTree, PayloadCell};
+
+    #[test]
+    fn kurbo_rects() {
+        let mut tree = KdTree::<PayloadCell<Rect, &str>, 4>::default();
+        for i in 0..20 {
+            let x = i as f64 * 30.;
+            tree.insert(PayloadCell::new(Rect::new(x, 0., x + 20., 10.), "button"));
+        }
+        //a rect given from its max corner
+        tree.insert(PayloadCell::new(Rect::new(100., 60., 40., 20.), "panel"));
+        let hits: Vec<_> = tree
+            .query_kurbo_point(Point::new(45., 30.))
+            .map(|value| *value.payload())
+            .collect();
+        assert_eq!(hits, vec!["panel"]);
+        assert_eq!(
+            tree.query_kurbo_rect(Rect::new(95., 15., 0., 5.)).count(),
+            4
+        );
+        let bounds = Aabb::from(Rect::new(3., 4., 1., 2.));
+        assert_eq!(bounds, Aabb::new(1., 3., 2., 4.));
+        assert_eq!(Rect::from(bounds), Rect::new(1., 2., 3., 4.));
+    }
+}
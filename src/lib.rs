@@ -1,4 +1,187 @@
-use std::{cmp::Ordering, fmt::Debug};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{cmp::Ordering, fmt::Debug};
+
+use stored::StoredBounds;
+
+mod aabb;
+mod aggregate;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "bevy")]
+mod bevy;
+mod bins;
+#[cfg(feature = "testing")]
+mod brute;
+mod build;
+mod bvh;
+#[cfg(feature = "std")]
+mod bytes;
+mod cancel;
+mod centered;
+#[cfg(feature = "cgmath")]
+mod cgmath;
+mod chunked;
+mod components;
+#[cfg(feature = "std")]
+mod concurrent;
+mod coverage;
+mod dynamic;
+mod error;
+#[cfg(feature = "euclid")]
+mod euclid;
+mod export;
+mod fat;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fixed;
+#[cfg(any(feature = "mmap", feature = "rkyv"))]
+mod flat;
+mod forest;
+#[cfg(feature = "std")]
+mod format;
+mod frozen;
+#[cfg(feature = "geo")]
+mod geo;
+#[cfg(feature = "geojson")]
+mod geojson;
+#[cfg(feature = "glam")]
+mod glam;
+mod gpu;
+mod grid;
+mod hull;
+mod index;
+mod indexed;
+mod join;
+mod kinematic;
+#[cfg(feature = "kurbo")]
+mod kurbo;
+#[cfg(feature = "mint")]
+mod mint;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod mutable;
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+mod observer;
+mod pairs;
+#[cfg(feature = "parry2d")]
+mod parry;
+mod payload;
+mod persistent;
+#[cfg(feature = "python")]
+mod python;
+mod quadtree;
+mod quantized;
+mod rebase;
+#[cfg(feature = "rstar")]
+mod rstar;
+#[cfg(feature = "bincode")]
+mod save;
+mod scalar;
+mod separation;
+mod shape;
+#[cfg(feature = "std")]
+mod sharded;
+mod simd;
+mod snapshot;
+mod sorted;
+mod spatial;
+mod stable;
+mod stored;
+mod sweep;
+mod sync;
+mod temporal;
+mod tolerance;
+mod top;
+mod toroidal;
+mod tuning;
+mod vector;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wkt")]
+mod wk;
+mod zones;
+
+pub use aabb::Aabb;
+pub use aggregate::{
+    AggregateKdTree, AggregateRectQuery, Count, MaxPriority, Monoid, PriorityRectQuery,
+};
+#[cfg(feature = "rkyv")]
+pub use archive::{ArchivedFlatKdTree, ArchivedRectQuery, FlatKdTree};
+#[cfg(feature = "bevy")]
+pub use bevy::{EntityBounds, KdTreePlugin, SpatialBounds, SpatialQuery, SpatialTree};
+#[cfg(feature = "testing")]
+pub use brute::{BruteForceIndex, BruteForceQuery};
+pub use build::{IncrementalBuild, KdTreeBuilder};
+pub use bvh::{Bvh, BvhRectQuery};
+pub use cancel::CancelToken;
+#[cfg(feature = "cgmath")]
+pub use cgmath::CgmathBounds;
+pub use chunked::{ChunkedKdTree, ChunkedRectQuery};
+pub use components::Reachable;
+#[cfg(feature = "std")]
+pub use concurrent::{ConcurrentKdTree, ReadGuard, WriteGuard};
+pub use dynamic::{DynamicAabbTree, DynamicRectQuery, ProxyId};
+pub use error::KdTreeError;
+pub use export::ColumnarBounds;
+pub use fat::Fat;
+pub use fixed::{StaticIter, StaticKdTree, StaticRectQuery};
+pub use forest::{ForestRectQuery, KdForest, Layer, LayerMask};
+#[cfg(feature = "std")]
+pub use format::FormatVersion;
+pub use frozen::{FrozenKdTree, FrozenRectQuery};
+#[cfg(feature = "geo")]
+pub use geo::{bounding_rect, GeoRect, GeoValue};
+pub use gpu::{GpuLeaf, GpuNode, GpuSnapshot, GPU_LEAF_FLAG};
+pub use grid::{GridRectQuery, UniformGrid};
+pub use hull::HullPoints;
+pub use index::SpatialIndex2D;
+pub use indexed::IndexedKdTree;
+pub use join::JoinQuery;
+pub use kinematic::{Motion, Mover};
+#[cfg(feature = "mmap")]
+pub use mmap::{MappedKdTree, MappedRectQuery};
+pub use mutable::{RectQueryMut, ValueMut};
+pub use observer::{ObservedKdTree, Observer};
+pub use pairs::{ContactEvent, PairManager};
+#[cfg(feature = "parry2d")]
+pub use parry::ParryQuery;
+pub use payload::PayloadCell;
+pub use persistent::{PersistentKdTree, PersistentNode, PersistentRectQuery};
+#[cfg(feature = "python")]
+pub use python::PyKdTree;
+pub use quadtree::{LooseQuadtree, LooseRectQuery};
+pub use quantized::{QuantizedKdTree, QuantizedLeaf, QuantizedNode, QuantizedRectQuery};
+pub use rebase::Translate;
+#[cfg(feature = "rstar")]
+pub use rstar::RStarValue;
+pub use scalar::Scalar;
+pub use separation::{pair_separation, separate};
+pub use shape::{QueryShape, ShapeQuery};
+#[cfg(feature = "std")]
+pub use sharded::ShardedKdTree;
+pub use snapshot::{Snapshot, VersionedKdTree};
+pub use sorted::SortedRectQuery;
+pub use spatial::Spatial;
+pub use stable::Stable;
+pub use sweep::{SweepAndPrune, SweepRectQuery};
+pub use sync::{SpatialSync, SyncStats};
+pub use temporal::Timed;
+pub use toroidal::WrappingRectQuery;
+pub use tuning::{Counted, InstrumentedKdTree, TuningReport};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmKdTree;
+#[cfg(feature = "wkt")]
+pub use wk::{wkb_bounds, wkt_bounds, GeometryError};
+pub use zones::{TriggerZones, ZoneEvent, ZoneId};
 
 pub trait KdValue: Default + Clone + Debug + PartialEq {
     type Position: PartialOrd + Debug;
@@ -8,116 +191,444 @@ pub trait KdValue: Default + Clone + Debug + PartialEq {
     fn max_y(&self) -> Self::Position;
 }
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Value: serde::Serialize, Value::Position: serde::Serialize",
+        deserialize = "Value: serde::Deserialize<'de>, Value::Position: serde::Deserialize<'de>"
+    ))
+)]
 pub enum KdTree<Value: KdValue, const ISLAND_SIZE: usize> {
-    Leaf(Vec<Value>),
+    /// The values, and in debug builds the bounds they were stored with.
+    Leaf(
+        Vec<Value>,
+        #[cfg_attr(feature = "serde", serde(skip))] StoredBounds<Value::Position>,
+    ),
     Node(Box<KdNode<Value, ISLAND_SIZE>>),
 }
 
 impl<Value: KdValue, const ISLAND_SIZE: usize> Default for KdTree<Value, ISLAND_SIZE> {
     fn default() -> Self {
-        Self::Leaf(Vec::with_capacity(ISLAND_SIZE))
+        Self::leaf(Vec::with_capacity(ISLAND_SIZE))
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            KdTree::Leaf(leaf, stored) => {
+                //leaves keep room for a full island
+                let mut copy = Vec::with_capacity(ISLAND_SIZE.max(leaf.len()));
+                copy.extend(leaf.iter().cloned());
+                KdTree::Leaf(copy, stored.clone())
+            }
+            KdTree::Node(node) => KdTree::Node(node.clone()),
+        }
     }
 }
 
 impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    //a leaf holding these values, recording their bounds in debug builds
+    pub(crate) fn leaf(values: Vec<Value>) -> Self {
+        let stored = StoredBounds::of(&values);
+        KdTree::Leaf(values, stored)
+    }
+
     pub fn insert(&mut self, value: Value) {
-        self.insert_internal(value, false)
+        self.insert_internal(value, false, &mut ())
+    }
+
+    pub fn iter(&self) -> Iter<'_, Value, ISLAND_SIZE> {
+        Iter {
+            queue: vec![self],
+            leaf: [].iter(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            KdTree::Leaf(leaf, ..) => leaf.len(),
+            KdTree::Node(node) => node.left.len() + node.right.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            KdTree::Leaf(leaf, ..) => leaf.is_empty(),
+            KdTree::Node(node) => node.left.is_empty() && node.right.is_empty(),
+        }
     }
 
+    /// Removes one value equal to `value`, returning whether one was found.
+    ///
+    /// In debug builds, the leaves record the bounds of their values when they are
+    /// stored, and queries and removals panic on a leaf holding a value whose bounds
+    /// were changed since, naming it.
     pub fn remove_one(&mut self, value: Value) -> bool {
         match self {
-            KdTree::Leaf(leaf) => {
-                let index = leaf
-                    .iter()
-                    .enumerate()
-                    .find(|(_, val)| val == &&value)
-                    .map(|t| t.0);
-                if let Some(index) = index {
-                    leaf.swap_remove(index);
-                    true
-                } else {
-                    false
+            KdTree::Leaf(leaf, stored) => {
+                stored.check(leaf);
+                match leaf.iter().position(|val| val == &value) {
+                    Some(index) => {
+                        leaf.swap_remove(index);
+                        stored.swap_removed(index, leaf.len());
+                        true
+                    }
+                    None => false,
                 }
             }
             KdTree::Node(node) => node.remove_one(value),
         }
     }
 
+    /// Panics if a value is not where its bounds place it, naming it, which
+    /// happens when its bounds were changed while it was in the tree.
+    ///
+    /// This walks the whole tree and compares every value with the medians and
+    /// `left_max` of the nodes above it, and in debug builds with the bounds it was
+    /// stored with.
+    pub fn validate(&self) {
+        let node = match self {
+            KdTree::Leaf(leaf, stored) => return stored.check(leaf),
+            KdTree::Node(node) => node,
+        };
+        let along = |value: &Value| {
+            if node.vertical {
+                (value.min_y(), value.max_y())
+            } else {
+                (value.min_x(), value.max_x())
+            }
+        };
+        for value in node.left.iter() {
+            let (min, max) = along(value);
+            assert!(
+                min < node.median && max.partial_cmp(&node.left_max) != Some(Ordering::Greater),
+                "{:?} is not where its bounds place it, they were changed while it was in the tree",
+                value
+            );
+        }
+        for value in node.right.iter() {
+            assert!(
+                along(value).0.partial_cmp(&node.median) != Some(Ordering::Less),
+                "{:?} is not where its bounds place it, they were changed while it was in the tree",
+                value
+            );
+        }
+        node.left.validate();
+        node.right.validate();
+    }
+
+    /// Whether an equal value is stored, looking in the only leaf it can be in.
+    pub fn contains(&self, value: &Value) -> bool {
+        match self {
+            KdTree::Leaf(leaf, ..) => leaf.contains(value),
+            KdTree::Node(node) => node.route(value).contains(value),
+        }
+    }
+
+    /// The values whose min corner is exactly `(min_x, min_y)`, following the
+    /// single path of the tree where they are stored.
+    pub fn find_at(
+        &self,
+        min_x: Value::Position,
+        min_y: Value::Position,
+    ) -> impl Iterator<Item = &Value> + '_ {
+        self.leaf_for(&min_x, &min_y)
+            .iter()
+            .filter(move |value| value.min_x() == min_x && value.min_y() == min_y)
+    }
+
+    //the only leaf which can hold values with this min corner
+    pub(crate) fn leaf_for(&self, min_x: &Value::Position, min_y: &Value::Position) -> &[Value] {
+        let mut tree = self;
+        loop {
+            match tree {
+                KdTree::Leaf(leaf, ..) => return leaf,
+                KdTree::Node(node) => {
+                    let position = if node.vertical { min_y } else { min_x };
+                    tree = if *position < node.median {
+                        &node.left
+                    } else {
+                        &node.right
+                    };
+                }
+            }
+        }
+    }
+
+    //the leaf at the end of the path, `true` going right
+    pub(crate) fn leaf_at(&self, path: &[bool]) -> &[Value] {
+        let mut tree = self;
+        for right in path {
+            let KdTree::Node(node) = tree else {
+                unreachable!("the path follows nodes")
+            };
+            tree = if *right { &node.right } else { &node.left };
+        }
+        let KdTree::Leaf(leaf, ..) = tree else {
+            unreachable!("the path ends on a leaf")
+        };
+        leaf
+    }
+
+    pub(crate) fn leaf_at_mut(
+        &mut self,
+        path: &[bool],
+    ) -> (&mut Vec<Value>, &mut StoredBounds<Value::Position>) {
+        let mut tree = self;
+        for right in path {
+            let KdTree::Node(node) = tree else {
+                unreachable!("the path follows nodes")
+            };
+            tree = if *right {
+                &mut node.right
+            } else {
+                &mut node.left
+            };
+        }
+        let KdTree::Leaf(leaf, stored) = tree else {
+            unreachable!("the path ends on a leaf")
+        };
+        (leaf, stored)
+    }
+
+    //inserts the value like `insert`, calling `placed` with the path to its leaf
+    //and its index there, then for each of the other values of the leaf if it
+    //was split
+    pub(crate) fn insert_placed(
+        &mut self,
+        value: Value,
+        mut placed: impl FnMut(&Value, &[bool], usize),
+    ) {
+        let mut tree = self;
+        let mut vertical = false;
+        let mut path = Vec::new();
+        while let KdTree::Node(node) = tree {
+            vertical = !node.vertical;
+            let position = if node.vertical {
+                value.min_y()
+            } else {
+                value.min_x()
+            };
+            path.push(position.partial_cmp(&node.median) != Some(Ordering::Less));
+            tree = node.choose_tree(&value);
+        }
+        let KdTree::Leaf(leaf, stored) = tree else {
+            unreachable!("the descent stops on a leaf")
+        };
+        leaf.push(value);
+        stored.pushed(leaf);
+        if !split_due(leaf.len(), ISLAND_SIZE) {
+            placed(&leaf[leaf.len() - 1], &path, leaf.len() - 1);
+            return;
+        }
+        //checked before the bounds are recorded again in the new leaves
+        stored.check(leaf);
+        let values = core::mem::take(leaf);
+        *tree = Self::build_internal(values, vertical);
+        Self::place_all(tree, &mut path, &mut placed);
+    }
+
+    fn place_all(
+        tree: &Self,
+        path: &mut Vec<bool>,
+        placed: &mut impl FnMut(&Value, &[bool], usize),
+    ) {
+        match tree {
+            KdTree::Leaf(leaf, ..) => {
+                for (index, value) in leaf.iter().enumerate() {
+                    placed(value, path, index);
+                }
+            }
+            KdTree::Node(node) => {
+                path.push(false);
+                Self::place_all(&node.left, path, placed);
+                path.pop();
+                path.push(true);
+                Self::place_all(&node.right, path, placed);
+                path.pop();
+            }
+        }
+    }
+
+    /// Inserts the value unless an equal one is already stored, returning whether
+    /// it was inserted.
+    pub fn insert_unique(&mut self, value: Value) -> bool {
+        if self.contains(&value) {
+            false
+        } else {
+            self.insert(value);
+            true
+        }
+    }
+
+    /// The stored value equal to `value`, inserting it first if there is none.
+    ///
+    /// The bounds of the value must not be changed through the reference.
+    pub fn get_or_insert(&mut self, value: Value) -> &mut Value {
+        //values with NaN bounds aren't equal to themselves, and are never found
+        if !self.contains(&value) {
+            return self.insert_slot(value);
+        }
+        let mut tree = self;
+        loop {
+            match tree {
+                KdTree::Leaf(leaf, ..) => {
+                    return leaf
+                        .iter_mut()
+                        .find(|stored| **stored == value)
+                        .expect("the value was just found")
+                }
+                KdTree::Node(node) => tree = node.route_mut(&value),
+            }
+        }
+    }
+
+    //inserts the value like `insert`, returning where it was stored
+    fn insert_slot(&mut self, value: Value) -> &mut Value {
+        let mut tree = self;
+        let mut vertical = false;
+        while let KdTree::Node(node) = tree {
+            vertical = !node.vertical;
+            tree = node.choose_tree(&value);
+        }
+        let KdTree::Leaf(leaf, stored) = &mut *tree else {
+            unreachable!("the descent stops on a leaf")
+        };
+        leaf.push(value);
+        stored.pushed(leaf);
+        if !split_due(leaf.len(), ISLAND_SIZE) {
+            let KdTree::Leaf(leaf, ..) = tree else {
+                unreachable!("the descent stops on a leaf")
+            };
+            return leaf.last_mut().unwrap();
+        }
+        //the leaf is split with the new value marked, to find it afterwards
+        let KdTree::Leaf(leaf, stored) = &mut *tree else {
+            unreachable!("the descent stops on a leaf")
+        };
+        //checked before the bounds are recorded again in the new leaves
+        stored.check(leaf);
+        let last = leaf.len() - 1;
+        let values = core::mem::take(leaf)
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| Inserted {
+                value,
+                new: index == last,
+            })
+            .collect();
+        let mut path = Vec::new();
+        let mut slot = None;
+        *tree = Self::unmark(
+            KdTree::build_internal(values, vertical),
+            &mut path,
+            &mut slot,
+        );
+        let (path, index) = slot.expect("the new value is in one of the leaves");
+        for right in path {
+            let KdTree::Node(node) = tree else {
+                unreachable!("the path follows nodes")
+            };
+            tree = if right {
+                &mut node.right
+            } else {
+                &mut node.left
+            };
+        }
+        let KdTree::Leaf(leaf, ..) = tree else {
+            unreachable!("the path ends on a leaf")
+        };
+        &mut leaf[index]
+    }
+
+    //the tree without the marks, and the path to the leaf holding the new value
+    //with its index there, `true` going right
+    fn unmark(
+        tree: KdTree<Inserted<Value>, ISLAND_SIZE>,
+        path: &mut Vec<bool>,
+        slot: &mut Option<(Vec<bool>, usize)>,
+    ) -> Self {
+        match tree {
+            KdTree::Leaf(values, ..) => {
+                if let Some(index) = values.iter().position(|value| value.new) {
+                    *slot = Some((path.clone(), index));
+                }
+                let mut leaf = Vec::with_capacity(ISLAND_SIZE.max(values.len()));
+                leaf.extend(values.into_iter().map(|value| value.value));
+                KdTree::leaf(leaf)
+            }
+            KdTree::Node(node) => {
+                let node = *node;
+                path.push(false);
+                let left = Self::unmark(node.left, path, slot);
+                path.pop();
+                path.push(true);
+                let right = Self::unmark(node.right, path, slot);
+                path.pop();
+                KdTree::Node(Box::new(KdNode {
+                    vertical: node.vertical,
+                    median: node.median,
+                    left_max: node.left_max,
+                    left,
+                    right,
+                }))
+            }
+        }
+    }
+
     pub fn remove_all(&mut self, value: Value) {
         match self {
-            KdTree::Leaf(leaf) => {
-                let indexes: Vec<usize> = leaf
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, val)| **val == value)
-                    .map(|t| t.0)
-                    .collect();
-                for index in indexes {
-                    leaf.swap_remove(index);
+            KdTree::Leaf(leaf, stored) => {
+                stored.check(leaf);
+                //a value swapped in is compared before going further
+                let mut index = 0;
+                while index < leaf.len() {
+                    if leaf[index] == value {
+                        leaf.swap_remove(index);
+                        stored.swap_removed(index, leaf.len());
+                    } else {
+                        index += 1;
+                    }
                 }
             }
             KdTree::Node(node) => node.remove_all(value),
         }
     }
 
-    fn insert_internal(&mut self, value: Value, vertical: bool) {
+    pub(crate) fn insert_internal<O: Observer<Value, ISLAND_SIZE>>(
+        &mut self,
+        value: Value,
+        vertical: bool,
+        observer: &mut O,
+    ) {
         let change = match self {
-            KdTree::Leaf(leaf) => {
-                assert!(leaf.len() < ISLAND_SIZE);
+            KdTree::Leaf(leaf, stored) => {
                 leaf.push(value);
-                if leaf.len() < ISLAND_SIZE {
+                stored.pushed(leaf);
+                if !split_due(leaf.len(), ISLAND_SIZE) {
                     None
                 } else {
-                    leaf.sort_unstable_by(if vertical {
-                        |a: &Value, b: &Value| {
-                            a.min_y().partial_cmp(&b.min_y()).unwrap_or(Ordering::Equal)
-                        }
-                    } else {
-                        |a: &Value, b: &Value| {
-                            a.min_x().partial_cmp(&b.min_x()).unwrap_or(Ordering::Equal)
-                        }
-                    });
-                    let median = if vertical {
-                        leaf[ISLAND_SIZE / 2].clone().min_y()
-                    } else {
-                        leaf[ISLAND_SIZE / 2].clone().min_x()
-                    };
-                    let right = KdTree::Leaf(leaf.split_off(ISLAND_SIZE / 2));
-                    let left = std::mem::take(leaf);
-                    let init = if vertical {
-                        left[0].max_y()
-                    } else {
-                        left[0].max_x()
-                    };
-                    let left_max = left.iter().fold(init, |prev, value| {
-                        let v_max = if vertical {
-                            value.max_y()
-                        } else {
-                            value.max_x()
-                        };
-                        if v_max > prev {
-                            v_max
-                        } else {
-                            prev
-                        }
-                    });
-                    let left = KdTree::Leaf(left);
-                    Some(KdTree::Node(Box::new(KdNode {
-                        left,
-                        right,
-                        median,
-                        vertical,
-                        left_max,
-                    })))
+                    //the bounds are recorded again in the new leaves
+                    stored.check(leaf);
+                    let values = core::mem::take(leaf);
+                    Some(Self::build_internal(values, vertical))
                 }
             }
             KdTree::Node(node) => {
-                node.insert(value);
+                node.insert(value, observer);
                 None
             }
         };
         if let Some(new_tree) = change {
             *self = new_tree;
+            if let KdTree::Node(_) = self {
+                observer.split(self);
+            }
         }
     }
     //false positive it seems
@@ -140,6 +651,103 @@ impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
     ) -> RectQuery<'a, Value, ISLAND_SIZE> {
         RectQuery::new(self, min_x, max_x, min_y, max_y)
     }
+    /// The values overlapping the bounds of `region`, such as an `Aabb` or a value.
+    pub fn query_overlaps<Region: KdValue<Position = Value::Position>>(
+        &self,
+        region: &Region,
+    ) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(
+            region.min_x(),
+            region.max_x(),
+            region.min_y(),
+            region.max_y(),
+        )
+    }
+}
+#[cfg(not(feature = "fast-compare"))]
+#[inline(always)]
+fn cmp_position<P: PartialOrd>(a: &P, b: &P) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+//with fast-compare, positions are assumed to never be NaN
+#[cfg(feature = "fast-compare")]
+#[inline(always)]
+fn cmp_position<P: PartialOrd>(a: &P, b: &P) -> Ordering {
+    if a < b {
+        Ordering::Less
+    } else if a > b {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+//whether a maximum replaces the current one: NaN maximums are unbounded, like in
+//the rectangle test, so that the nodes are still visited for them
+pub(crate) fn raises_max<P: PartialOrd>(max: &P, value: &P) -> bool {
+    value.partial_cmp(value).is_none() || (max.partial_cmp(max).is_some() && value > max)
+}
+//whether a leaf of this length is split: when it gets full, then as a leaf which
+//couldn't be split keeps growing, each time its length doubles, so that inserting
+//values which all start at the same position doesn't sort the leaf every time
+pub(crate) fn split_due(len: usize, island_size: usize) -> bool {
+    len == island_size || (len > island_size && len.is_power_of_two())
+}
+//the index splitting values sorted by minimum into two non empty halves, where
+//every value of the first one has a minimum strictly below the median, or `None`
+//if all the minimums are equal
+pub(crate) fn split_index<T, P: PartialOrd>(sorted: &[T], min: impl Fn(&T) -> P) -> Option<usize> {
+    //the split moves below duplicates of the median, or above those of the
+    //smallest minimum
+    let mut middle = sorted.len() / 2;
+    while middle > 0 && min(&sorted[middle - 1]) >= min(&sorted[middle]) {
+        middle -= 1;
+    }
+    if middle > 0 {
+        return Some(middle);
+    }
+    let first = min(sorted.first()?);
+    (sorted.len() / 2..sorted.len()).find(|&index| min(&sorted[index]) > first)
+}
+#[cfg(not(feature = "fast-compare"))]
+#[inline(always)]
+fn leaf_at<Value>(leaf: &[Value], index: usize) -> &Value {
+    &leaf[index]
+}
+//callers only pass indexes below the leaf length
+#[cfg(feature = "fast-compare")]
+#[inline(always)]
+fn leaf_at<Value>(leaf: &[Value], index: usize) -> &Value {
+    debug_assert!(index < leaf.len());
+    unsafe { leaf.get_unchecked(index) }
+}
+//islands at least this big are scanned with a match mask
+const WIDE_ISLAND: usize = 32;
+//on wide leaves, the overlap tests are done without branching and the matches
+//are pushed afterwards from a bitmask
+#[inline(always)]
+fn scan_leaf<'a, Value, const ISLAND_SIZE: usize>(
+    leaves: &'a [Value],
+    items_to_yield: &mut Vec<&'a Value>,
+    matches: impl Fn(&Value) -> bool,
+) {
+    if ISLAND_SIZE < WIDE_ISLAND {
+        for leaf in leaves {
+            if matches(leaf) {
+                items_to_yield.push(leaf)
+            }
+        }
+        return;
+    }
+    for chunk in leaves.chunks(64) {
+        let mut mask = 0u64;
+        for (i, leaf) in chunk.iter().enumerate() {
+            mask |= (matches(leaf) as u64) << i;
+        }
+        while mask != 0 {
+            items_to_yield.push(leaf_at(chunk, mask.trailing_zeros() as usize));
+            mask &= mask - 1;
+        }
+    }
 }
 pub struct RectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
     max_x: Value::Position,
@@ -148,6 +756,7 @@ pub struct RectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
     min_y: Value::Position,
     queue: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
     items_to_yield: Vec<&'a Value>,
+    cancel: Option<&'a CancelToken>,
 }
 impl<'a, Value: KdValue, const ISLAND_SIZE: usize> RectQuery<'a, Value, ISLAND_SIZE> {
     fn new(
@@ -160,56 +769,114 @@ impl<'a, Value: KdValue, const ISLAND_SIZE: usize> RectQuery<'a, Value, ISLAND_S
         Self {
             queue: vec![tree],
             items_to_yield: Vec::new(),
+            cancel: None,
             min_x,
             max_x,
             min_y,
             max_y,
         }
     }
+
+    /// Stops the traversal once the token is cancelled.
+    pub fn with_cancel(mut self, token: &'a CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(CancelToken::is_cancelled)
+    }
+}
+/// Forks the query: the copy yields the values the query has left to yield,
+/// without walking the tree again from the root.
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for RectQuery<'_, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            items_to_yield: self.items_to_yield.clone(),
+            cancel: self.cancel,
+            min_x: self.min_x.clone(),
+            max_x: self.max_x.clone(),
+            min_y: self.min_y.clone(),
+            max_y: self.max_y.clone(),
+        }
+    }
+}
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> RectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// Splits off about half of the remaining work into a new, independent query,
+    /// or returns `None` when there is too little left to share.
+    pub fn split(&mut self) -> Option<Self> {
+        while self.queue.len() == 1 {
+            let tree = self.queue.pop().unwrap();
+            self.visit(tree);
+        }
+        if self.queue.len() < 2 && self.items_to_yield.len() < 2 {
+            return None;
+        }
+        Some(Self {
+            queue: self.queue.split_off(self.queue.len() / 2),
+            items_to_yield: self.items_to_yield.split_off(self.items_to_yield.len() / 2),
+            cancel: self.cancel,
+            min_x: self.min_x.clone(),
+            max_x: self.max_x.clone(),
+            min_y: self.min_y.clone(),
+            max_y: self.max_y.clone(),
+        })
+    }
+}
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> RectQuery<'a, Value, ISLAND_SIZE> {
+    fn visit(&mut self, tree: &'a KdTree<Value, ISLAND_SIZE>) {
+        match tree {
+            KdTree::Leaf(leaves, stored) => {
+                stored.check(leaves);
+                let (min_x, max_x, min_y, max_y) =
+                    (&self.min_x, &self.max_x, &self.min_y, &self.max_y);
+                //written as not being apart, like before the bitmask, so that values
+                //with NaN bounds still match
+                scan_leaf::<_, ISLAND_SIZE>(leaves, &mut self.items_to_yield, |leaf| {
+                    !((leaf.min_x() > *max_x)
+                        | (*min_x > leaf.max_x())
+                        | (leaf.min_y() > *max_y)
+                        | (*min_y > leaf.max_y()))
+                });
+            }
+            KdTree::Node(node) => {
+                let (min, max) = if node.vertical {
+                    (&self.min_y, &self.max_y)
+                } else {
+                    (&self.min_x, &self.max_x)
+                };
+                if min.partial_cmp(&node.left_max) != Some(Ordering::Greater) {
+                    self.queue.push(&node.left)
+                }
+                if *max >= node.median {
+                    self.queue.push(&node.right)
+                }
+            }
+        }
+    }
 }
 impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for RectQuery<'a, Value, ISLAND_SIZE> {
     type Item = &'a Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.items_to_yield.pop();
-        if item.is_some() {
-            return item;
-        }
         loop {
-            if self.queue.is_empty() {
-                return None;
+            let item = self.items_to_yield.pop();
+            if item.is_some() {
+                return item;
             }
-            let tree = self.queue.pop().unwrap();
-            match tree {
-                KdTree::Leaf(leaves) => {
-                    for leaf in leaves {
-                        if !(leaf.min_x() > self.max_x
-                            || self.min_x > leaf.max_x()
-                            || leaf.min_y() > self.max_y
-                            || self.min_y > leaf.max_y())
-                        {
-                            self.items_to_yield.push(leaf)
-                        }
-                    }
-                    let item = self.items_to_yield.pop();
-                    if item.is_some() {
-                        return item;
-                    }
-                }
-                KdTree::Node(node) => {
-                    let (min, max) = if node.vertical {
-                        (&self.min_y, &self.max_y)
-                    } else {
-                        (&self.min_x, &self.max_x)
-                    };
-                    if *min <= node.left_max {
-                        self.queue.push(&node.left)
-                    }
-                    if *max >= node.median {
-                        self.queue.push(&node.right)
-                    }
-                }
+            if self.is_cancelled() {
+                self.queue.clear();
+                return None;
             }
+            let tree = self.queue.pop()?;
+            self.visit(tree);
         }
     }
 }
@@ -218,16 +885,43 @@ pub struct PointQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
     y: Value::Position,
     queue: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
     items_to_yield: Vec<&'a Value>,
+    cancel: Option<&'a CancelToken>,
 }
 impl<'a, Value: KdValue, const ISLAND_SIZE: usize> PointQuery<'a, Value, ISLAND_SIZE> {
     fn new(tree: &'a KdTree<Value, ISLAND_SIZE>, x: Value::Position, y: Value::Position) -> Self {
         Self {
             queue: vec![tree],
             items_to_yield: Vec::new(),
+            cancel: None,
             x,
             y,
         }
     }
+
+    /// Stops the traversal once the token is cancelled.
+    pub fn with_cancel(mut self, token: &'a CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(CancelToken::is_cancelled)
+    }
+}
+/// Forks the query, like for `RectQuery`.
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for PointQuery<'_, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            items_to_yield: self.items_to_yield.clone(),
+            cancel: self.cancel,
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
 }
 impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for PointQuery<'a, Value, ISLAND_SIZE> {
     type Item = &'a Value;
@@ -241,18 +935,22 @@ impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for PointQuery<'a, V
             if self.queue.is_empty() {
                 return None;
             }
+            if self.is_cancelled() {
+                self.queue.clear();
+                return None;
+            }
             let tree = self.queue.pop().unwrap();
             match tree {
-                KdTree::Leaf(leaves) => {
-                    for leaf in leaves {
-                        if leaf.min_x() <= self.x
-                            && leaf.max_x() >= self.x
-                            && leaf.min_y() <= self.y
-                            && leaf.max_y() >= self.y
-                        {
-                            self.items_to_yield.push(leaf)
-                        }
-                    }
+                KdTree::Leaf(leaves, stored) => {
+                    stored.check(leaves);
+                    let (x, y) = (&self.x, &self.y);
+                    //not being apart, like `query_rect`
+                    scan_leaf::<_, ISLAND_SIZE>(leaves, &mut self.items_to_yield, |leaf| {
+                        !((leaf.min_x() > *x)
+                            | (*x > leaf.max_x())
+                            | (leaf.min_y() > *y)
+                            | (*y > leaf.max_y()))
+                    });
                     let item = self.items_to_yield.pop();
                     if item.is_some() {
                         return item;
@@ -260,7 +958,7 @@ impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for PointQuery<'a, V
                 }
                 KdTree::Node(node) => {
                     let dim = if node.vertical { &self.y } else { &self.x };
-                    if *dim <= node.left_max {
+                    if dim.partial_cmp(&node.left_max) != Some(Ordering::Greater) {
                         self.queue.push(&node.left)
                     }
                     if *dim >= node.median {
@@ -271,7 +969,45 @@ impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for PointQuery<'a, V
         }
     }
 }
+pub struct Iter<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    queue: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
+    leaf: core::slice::Iter<'a, Value>,
+}
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator for Iter<'a, Value, ISLAND_SIZE> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.leaf.next() {
+                return Some(value);
+            }
+            match self.queue.pop()? {
+                KdTree::Leaf(leaf, ..) => self.leaf = leaf.iter(),
+                KdTree::Node(node) => {
+                    self.queue.push(&node.right);
+                    self.queue.push(&node.left);
+                }
+            }
+        }
+    }
+}
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> IntoIterator for &'a KdTree<Value, ISLAND_SIZE> {
+    type Item = &'a Value;
+    type IntoIter = Iter<'a, Value, ISLAND_SIZE>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Value: serde::Serialize, Value::Position: serde::Serialize",
+        deserialize = "Value: serde::Deserialize<'de>, Value::Position: serde::Deserialize<'de>"
+    ))
+)]
 pub struct KdNode<Value: KdValue, const ISLAND_SIZE: usize> {
     vertical: bool,
     median: Value::Position,
@@ -280,7 +1016,64 @@ pub struct KdNode<Value: KdValue, const ISLAND_SIZE: usize> {
     right: KdTree<Value, ISLAND_SIZE>,
 }
 
+/// Trees are equal when they hold the same values the same number of times,
+/// whatever their structure.
+impl<Value: KdValue, const ISLAND_SIZE: usize> PartialEq for KdTree<Value, ISLAND_SIZE> {
+    fn eq(&self, other: &Self) -> bool {
+        //counts the copies of a value among the ones sharing its bounds
+        let count = |tree: &Self, value: &Value| {
+            tree.query_rect(value.min_x(), value.max_x(), value.min_y(), value.max_y())
+                .filter(|other| *other == value)
+                .count()
+        };
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|value| count(self, value) == count(other, value))
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for KdNode<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            vertical: self.vertical,
+            median: self.median.clone(),
+            left_max: self.left_max.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
 impl<Value: KdValue, const ISLAND_SIZE: usize> KdNode<Value, ISLAND_SIZE> {
+    //the subtree a value is stored in, without updating the node
+    fn route(&self, value: &Value) -> &KdTree<Value, ISLAND_SIZE> {
+        let position = if self.vertical {
+            value.min_y()
+        } else {
+            value.min_x()
+        };
+        if position < self.median {
+            &self.left
+        } else {
+            &self.right
+        }
+    }
+    fn route_mut(&mut self, value: &Value) -> &mut KdTree<Value, ISLAND_SIZE> {
+        let position = if self.vertical {
+            value.min_y()
+        } else {
+            value.min_x()
+        };
+        if position < self.median {
+            &mut self.left
+        } else {
+            &mut self.right
+        }
+    }
     fn choose_tree(&mut self, value: &Value) -> &mut KdTree<Value, ISLAND_SIZE> {
         let cmp_position = if self.vertical {
             value.min_y()
@@ -293,7 +1086,7 @@ impl<Value: KdValue, const ISLAND_SIZE: usize> KdNode<Value, ISLAND_SIZE> {
             } else {
                 value.max_x()
             };
-            if max > self.left_max {
+            if raises_max(&self.left_max, &max) {
                 self.left_max = max
             }
             &mut self.left
@@ -301,9 +1094,10 @@ impl<Value: KdValue, const ISLAND_SIZE: usize> KdNode<Value, ISLAND_SIZE> {
             &mut self.right
         }
     }
-    fn insert(&mut self, value: Value) {
+    fn insert<O: Observer<Value, ISLAND_SIZE>>(&mut self, value: Value, observer: &mut O) {
         let vertical = self.vertical;
-        self.choose_tree(&value).insert_internal(value, !vertical);
+        self.choose_tree(&value)
+            .insert_internal(value, !vertical, observer);
     }
     fn remove_one(&mut self, value: Value) -> bool {
         self.choose_tree(&value).remove_one(value)
@@ -313,20 +1107,41 @@ impl<Value: KdValue, const ISLAND_SIZE: usize> KdNode<Value, ISLAND_SIZE> {
     }
 }
 
+//a value being inserted, told apart from the others while its leaf is split
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Inserted<Value> {
+    value: Value,
+    new: bool,
+}
+impl<Value: KdValue> KdValue for Inserted<Value> {
+    type Position = Value::Position;
+    fn min_x(&self) -> Self::Position {
+        self.value.min_x()
+    }
+    fn min_y(&self) -> Self::Position {
+        self.value.min_y()
+    }
+    fn max_x(&self) -> Self::Position {
+        self.value.max_x()
+    }
+    fn max_y(&self) -> Self::Position {
+        self.value.max_y()
+    }
+}
 #[cfg(test)]
 mod tests {
     use core::f32;
 
-    use crate::{KdTree, KdValue};
+    use crate::{Aabb, KdTree, KdValue, PayloadCell};
     #[derive(Debug, Default, Clone, PartialEq)]
-    struct TestValue {
-        min_x: f32,
-        max_x: f32,
-        min_y: f32,
-        max_y: f32,
+    pub(crate) struct TestValue {
+        pub(crate) min_x: f32,
+        pub(crate) max_x: f32,
+        pub(crate) min_y: f32,
+        pub(crate) max_y: f32,
     }
     impl TestValue {
-        fn new(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Self {
+        pub(crate) fn new(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Self {
             Self {
                 min_x,
                 max_x,
@@ -353,6 +1168,67 @@ mod tests {
             self.max_y
         }
     }
+    //values scattered over 101 by 97, `size` giving the width and height of each
+    pub(crate) fn scattered(count: usize, size: impl Fn(usize) -> (f32, f32)) -> Vec<TestValue> {
+        (0..count)
+            .map(|i| {
+                let (x, y) = ((i * 37 % 101) as f32, (i * 11 % 97) as f32);
+                let (width, height) = size(i);
+                TestValue::new(x, x + width, y, y + height)
+            })
+            .collect()
+    }
+    //the lower corners of the rectangles the scattered values are queried with
+    pub(crate) fn query_corners() -> impl Iterator<Item = (f32, f32)> {
+        (0..20).map(|i| ((i * 13 % 90) as f32, (i * 29 % 90) as f32))
+    }
+    //the values overlapping the rectangle, by a linear scan
+    pub(crate) fn overlapping(
+        values: &[TestValue],
+        min_x: f32,
+        max_x: f32,
+        min_y: f32,
+        max_y: f32,
+    ) -> Vec<&TestValue> {
+        values
+            .iter()
+            .filter(|v| {
+                v.min_x <= max_x && min_x <= v.max_x && v.min_y <= max_y && min_y <= v.max_y
+            })
+            .collect()
+    }
+    //whether a query found the expected values, in any order
+    pub(crate) fn assert_same(found: &[&TestValue], expected: &[&TestValue]) {
+        assert_eq!(found.len(), expected.len());
+        //NaN bounds make the values unequal to themselves
+        assert!(found
+            .iter()
+            .all(|value| value.max_y.is_nan() || expected.contains(value)));
+    }
+    //inserts 20 boxes of size 2 at (3 * i, i) made by `new` from their min corner,
+    //and checks that a point query finds the one at (30, 10) and that 4 of them
+    //overlap (0, 0)..(10, 10), returning the first
+    #[cfg(any(
+        feature = "cgmath",
+        feature = "euclid",
+        feature = "glam",
+        feature = "mint",
+        feature = "nalgebra"
+    ))]
+    pub(crate) fn interop_boxes<Value: KdValue>(
+        new: impl Fn(f64, f64) -> Value,
+        query_point: impl Fn(&KdTree<Value, 4>, f64, f64) -> Vec<Value>,
+        query_rect: impl Fn(&KdTree<Value, 4>, [f64; 4]) -> usize,
+    ) -> Value {
+        let mut tree = KdTree::default();
+        for i in 0..20 {
+            tree.insert(new(i as f64 * 3., i as f64));
+        }
+        let mut hits = query_point(&tree, 31., 11.);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(query_rect(&tree, [0., 10., 0., 10.]), 4);
+        hits.remove(0)
+    }
     #[test]
     fn rect() {
         let mut tree = KdTree::<TestValue, 3>::default();
@@ -393,4 +1269,338 @@ mod tests {
         tree.insert(TestValue::new(6., 8., 1., 3.));
         assert_eq!(tree.query_point(7.5, 4.5).count(), 6);
     }
+    #[test]
+    fn iter() {
+        let mut tree = KdTree::<TestValue, 3>::default();
+        for i in 0..20 {
+            tree.insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+        }
+        assert_eq!(tree.len(), 20);
+        let mut xs: Vec<f32> = tree.iter().map(|v| v.min_x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(xs, (0..20).map(|i| i as f32).collect::<Vec<_>>());
+    }
+    #[test]
+    fn split_query() {
+        fn assert_send<T: Send>(_: &T) {}
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..200 {
+            let x = (i * 17 % 61) as f32;
+            tree.insert(TestValue::new(x, x + 2., 0., 1.));
+        }
+        let expected = tree.query_rect(10., 40., 0., 1.).count();
+        let mut query = tree.query_rect(10., 40., 0., 1.);
+        let other = query.split().expect("there is enough work to split");
+        assert_send(&other);
+        let counts = std::thread::scope(|scope| {
+            let other = scope.spawn(move || other.count());
+            query.count() + other.join().unwrap()
+        });
+        assert_eq!(counts, expected);
+    }
+    #[test]
+    fn infinite_bounds() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let ground = TestValue::new(f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY, 0.);
+        let wall = TestValue::new(f32::NEG_INFINITY, -100., f32::NEG_INFINITY, f32::INFINITY);
+        for _ in 0..10 {
+            tree.insert(ground.clone());
+        }
+        tree.insert(wall.clone());
+        for i in 0..100 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x + 1., x, x + 1.));
+        }
+        //the infinite values don't end up as medians
+        let mut queue = vec![&tree];
+        while let Some(KdTree::Node(node)) = queue.pop() {
+            assert!(node.median.is_finite());
+            queue.push(&node.left);
+            queue.push(&node.right);
+        }
+        assert_eq!(tree.query_rect(1e30, 2e30, -1e30, -1e29).count(), 10);
+        assert_eq!(tree.query_point(-1e9, 5.).count(), 1);
+        assert_eq!(tree.query_rect(50.5, 50.5, 50.5, 50.5).count(), 1);
+        assert_eq!(
+            tree.query_rect(f32::NEG_INFINITY, f32::INFINITY, 0., 0.)
+                .count(),
+            12
+        );
+        assert!(tree.remove_one(wall));
+        for _ in 0..10 {
+            assert!(tree.remove_one(ground.clone()));
+        }
+        assert_eq!(tree.len(), 100);
+    }
+    #[test]
+    fn equal_positions() {
+        //values sharing a min along an axis are still found for removal
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..50 {
+            let y = i as f32;
+            tree.insert(TestValue::new(0., 1., y, y + 1.));
+            tree.insert(TestValue::new(y, y + 1., 0., 1.));
+        }
+        //the same box many times can't be split at all
+        for _ in 0..20 {
+            tree.insert(TestValue::new(5., 6., 5., 6.));
+        }
+        assert_eq!(tree.len(), 120);
+        for i in 0..50 {
+            let y = i as f32;
+            assert!(tree.remove_one(TestValue::new(0., 1., y, y + 1.)));
+        }
+        for _ in 0..20 {
+            assert!(tree.remove_one(TestValue::new(5., 6., 5., 6.)));
+        }
+        assert_eq!(tree.len(), 50);
+        let mut tree = KdTree::<TestValue, 1>::default();
+        for i in 0..10 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x, x, x));
+        }
+        assert_eq!(tree.query_rect(2., 4., 2., 4.).count(), 3);
+    }
+    #[test]
+    fn clone() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(TestValue::new(x, x + 1., x, x + 1.));
+        }
+        let mut copy = tree.clone();
+        assert_eq!(format!("{:?}", copy), format!("{:?}", tree));
+        assert!(copy == tree);
+        //the copy is independent
+        assert!(copy.remove_one(TestValue::new(0., 1., 0., 1.)));
+        copy.insert(TestValue::new(500., 501., 500., 501.));
+        assert_eq!(tree.query_point(0.5, 0.5).count(), 1);
+        assert_eq!(tree.query_point(500.5, 500.5).count(), 0);
+        assert_eq!(copy.query_point(0.5, 0.5).count(), 0);
+        assert_eq!(copy.len(), 100);
+    }
+    #[test]
+    fn equality() {
+        let values: Vec<_> = (0..100)
+            .map(|i| {
+                let x = (i * 37 % 101) as f32;
+                TestValue::new(x, x + 1., (i % 3) as f32, 5.)
+            })
+            .collect();
+        let mut a = KdTree::<TestValue, 4>::default();
+        let mut b = KdTree::<TestValue, 4>::default();
+        for value in &values {
+            a.insert(value.clone());
+        }
+        for value in values.iter().rev() {
+            b.insert(value.clone());
+        }
+        assert!(a == b);
+        //same length, but a duplicate instead of another value
+        b.remove_one(values[3].clone());
+        b.insert(values[4].clone());
+        assert!(a != b);
+        b.remove_one(values[4].clone());
+        b.insert(values[3].clone());
+        assert!(a == b);
+        b.insert(values[3].clone());
+        assert!(a != b);
+        assert!(KdTree::<TestValue, 4>::default() == KdTree::default());
+    }
+    #[test]
+    fn get_or_insert() {
+        let mut tree = KdTree::<PayloadCell<Aabb<f32>, u32>, 4>::default();
+        for i in 0..50 {
+            let x = i as f32;
+            tree.insert(PayloadCell::new(Aabb::new(x, x + 1., 0., 1.), i));
+        }
+        let existing = PayloadCell::new(Aabb::new(7., 8., 0., 1.), 7);
+        assert!(tree.contains(&existing));
+        assert_eq!(*tree.get_or_insert(existing.clone()), existing);
+        assert_eq!(tree.len(), 50);
+        let new = PayloadCell::new(Aabb::new(7., 8., 0., 1.), 70);
+        assert!(!tree.contains(&new));
+        assert_eq!(*tree.get_or_insert(new.clone()), new);
+        assert_eq!(tree.len(), 51);
+        assert!(tree.contains(&new));
+        assert_eq!(*tree.get_or_insert(new.clone()), new);
+        assert_eq!(tree.len(), 51);
+    }
+    #[test]
+    fn fork_queries() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i % 10) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        let mut query = tree.query_rect(2., 5., 0., 1.);
+        let expected = query.clone().count();
+        assert_eq!(expected, 50);
+        for _ in 0..7 {
+            query.next();
+        }
+        let fork = query.clone();
+        assert_eq!(fork.count(), expected - 7);
+        assert_eq!(query.count(), expected - 7);
+
+        let mut query = tree.query_point(3.5, 0.5);
+        query.next();
+        assert_eq!(query.clone().count(), 9);
+        assert_eq!(query.count(), 9);
+    }
+    #[test]
+    fn query_overlaps() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        let region = Aabb::new(10.5, 12.5, 0.5, 0.5);
+        assert_eq!(tree.query_overlaps(&region).count(), 3);
+        let value = TestValue::new(20., 21., 0., 1.);
+        assert_eq!(tree.query_overlaps(&value).count(), 3);
+    }
+    #[test]
+    fn find_at() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..400 {
+            let (x, y) = ((i % 20) as f32, (i / 20) as f32);
+            tree.insert(TestValue::new(x, x + 1., y, y + 1.));
+        }
+        tree.insert(TestValue::new(3., 10., 4., 5.));
+        assert_eq!(tree.find_at(3., 4.).count(), 2);
+        assert_eq!(tree.find_at(19., 19.).count(), 1);
+        //touching neighbours don't count
+        assert!(tree.find_at(3.5, 4.).next().is_none());
+        assert!(tree.find_at(-1., 0.).next().is_none());
+    }
+    #[test]
+    fn insert_unique() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i % 40) as f32;
+            assert_eq!(
+                tree.insert_unique(TestValue::new(x, x + 1., 0., 1.)),
+                i < 40
+            );
+        }
+        assert_eq!(tree.len(), 40);
+        assert!(tree.remove_one(TestValue::new(3., 4., 0., 1.)));
+        assert!(tree.insert_unique(TestValue::new(3., 4., 0., 1.)));
+    }
+    #[test]
+    fn nan_bounds() {
+        //the rectangle test only rejects values known to be apart from it
+        for island in [4, 64] {
+            let mut values = vec![TestValue::new(f32::NAN, 1., 0., 1.)];
+            values.extend((0..island - 2).map(|i| TestValue::new(i as f32, i as f32, 5., 5.)));
+            let found = |values: &Vec<TestValue>| {
+                if island == 4 {
+                    let mut tree = KdTree::<TestValue, 4>::default();
+                    values.iter().for_each(|v| tree.insert(v.clone()));
+                    tree.query_rect(100., 200., 100., 200.).count()
+                } else {
+                    let mut tree = KdTree::<TestValue, 64>::default();
+                    values.iter().for_each(|v| tree.insert(v.clone()));
+                    tree.query_rect(100., 200., 100., 200.).count()
+                }
+            };
+            assert_eq!(found(&values), 0);
+            values[0] = TestValue::new(f32::NAN, f32::NAN, f32::NAN, f32::NAN);
+            assert_eq!(found(&values), 1);
+        }
+    }
+    #[test]
+    fn wide_islands() {
+        let mut tree = KdTree::<TestValue, 128>::default();
+        let mut values = Vec::new();
+        for i in 0..500 {
+            let x = (i * 37 % 101) as f32;
+            let y = (i * 53 % 97) as f32;
+            let value = TestValue::new(x, x + 3., y, y + 2.);
+            tree.insert(value.clone());
+            values.push(value);
+        }
+        let expected = values
+            .iter()
+            .filter(|v| v.min_x <= 60. && v.max_x >= 20. && v.min_y <= 50. && v.max_y >= 10.)
+            .count();
+        assert_eq!(tree.query_rect(20., 60., 10., 50.).count(), expected);
+        let expected = values
+            .iter()
+            .filter(|v| v.min_x <= 42. && v.max_x >= 42. && v.min_y <= 17. && v.max_y >= 17.)
+            .count();
+        assert_eq!(tree.query_point(42., 17.).count(), expected);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::Aabb;
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..50 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(Aabb::new(x, x + 3., i as f32, i as f32 + 2.));
+        }
+        let json = serde_json::to_string(&tree).unwrap();
+        let loaded: KdTree<Aabb<f32>, 4> = serde_json::from_str(&json).unwrap();
+        //the structure is kept as is, not rebuilt
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+        assert_eq!(
+            loaded.query_rect(10., 30., 5., 20.).count(),
+            tree.query_rect(10., 30., 5., 20.).count()
+        );
+    }
+    #[test]
+    #[should_panic(expected = "changed while it was in the tree")]
+    fn moved_value() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        tree.validate();
+        let value = tree.get_or_insert(TestValue::new(50., 51., 0., 1.));
+        value.min_x = -50.;
+        value.max_x = -49.;
+        tree.validate();
+    }
+    #[test]
+    fn get_or_insert_nan() {
+        //values with NaN bounds are never equal, so each one is inserted, and
+        //the slot returned is the one it went to, splits included
+        let mut tree = KdTree::<PayloadCell<Aabb<f32>, u32>, 4>::default();
+        for i in 0..100 {
+            let max_y = if i % 3 == 0 { f32::NAN } else { 1. };
+            let value =
+                tree.get_or_insert(PayloadCell::new(Aabb::new(i as f32, 200., 0., max_y), i));
+            assert_eq!(*value.payload(), i);
+            let value = tree.get_or_insert(PayloadCell::new(Aabb::new(5., 6., 0., 1.), 1000));
+            assert_eq!(*value.payload(), 1000);
+        }
+        assert_eq!(tree.len(), 101);
+    }
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "were changed while it was in the tree")]
+    fn changed_bounds() {
+        //the value stays on the same side of every split, only the leaf notices
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        tree.get_or_insert(TestValue::new(50., 51., 0., 1.)).max_x = 50.5;
+        tree.query_point(50.2, 0.5).count();
+    }
+    #[test]
+    fn equal_values() {
+        //linear overall, as the leaf is only sorted again when its length doubles
+        let mut tree = KdTree::<TestValue, 8>::default();
+        for _ in 0..20000 {
+            tree.insert(TestValue::new(1., 2., 1., 2.));
+        }
+        tree.insert(TestValue::new(5., 6., 1., 2.));
+        assert_eq!(tree.query_point(1.5, 1.5).count(), 20000);
+        assert_eq!(tree.len(), 20001);
+    }
 }
@@ -0,0 +1,59 @@
+use mint::Point2;
+
+use crate::Aabb;
+
+//boxes given as [min, max]
+impl<P> From<[Point2<P>; 2]> for Aabb<P> {
+    fn from([min, max]: [Point2<P>; 2]) -> Self {
+        Aabb {
+            min_x: min.x,
+            max_x: max.x,
+            min_y: min.y,
+            max_y: max.y,
+        }
+    }
+}
+
+impl<P> From<Aabb<P>> for [Point2<P>; 2] {
+    fn from(bounds: Aabb<P>) -> Self {
+        [
+            Point2 {
+                x: bounds.min_x,
+                y: bounds.min_y,
+            },
+            Point2 {
+                x: bounds.max_x,
+                y: bounds.max_y,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mint::Point2;
+
+    use crate::{tests::interop_boxes, Aabb};
+
+    fn point(x: f64, y: f64) -> Point2<f32> {
+        Point2 {
+            x: x as f32,
+            y: y as f32,
+        }
+    }
+
+    #[test]
+    fn mint_points() {
+        let hit = interop_boxes(
+            |x, y| Aabb::from([point(x, y), point(x + 2., y + 2.)]),
+            |tree, x, y| tree.query_point_vec(point(x, y)).cloned().collect(),
+            |tree, [min_x, max_x, min_y, max_y]| {
+                tree.query_rect_vec(point(min_x, min_y), point(max_x, max_y))
+                    .count()
+            },
+        );
+        assert_eq!(hit, Aabb::new(30., 32., 10., 12.));
+        let [min, max] = <[Point2<f32>; 2]>::from(hit);
+        assert_eq!((min, max), (point(30., 10.), point(32., 12.)));
+    }
+}
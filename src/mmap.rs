@@ -0,0 +1,355 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use memmap2::Mmap;
+
+use crate::{
+    bytes::{read_f64, read_u32, read_u64},
+    flat::{overlaps, union, EMPTY},
+    KdTree, KdValue, Scalar,
+};
+
+const MAGIC: &[u8; 4] = b"KDTM";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 48;
+const NODE_SIZE: usize = 56;
+const ENTRY_SIZE: usize = 48;
+const LEAF_KIND: u32 = 1;
+
+//file layout, every number being little endian:
+//  header: magic, version: u32, root: u32, nodes: u64, entries: u64, blob: u64, padding
+//  nodes: bounds: [f64; 4], kind: u32, padding: u32, then left and right node
+//         indexes for inner nodes, or first entry and entry count for leaves
+//  entries: bounds: [f64; 4], offset in the blob: u64, length: u64
+//  blob: the encoded values
+//bounds are always [min_x, max_x, min_y, max_y], and tight for nodes.
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Writes the tree in the layout read by `MappedKdTree`, encoding every value
+    /// with `encode`.
+    pub fn write_mapped<W: Write>(
+        &self,
+        mut writer: W,
+        mut encode: impl FnMut(&Value, &mut Vec<u8>),
+    ) -> io::Result<()> {
+        let mut layout = Layout::default();
+        let root = layout.push(self, &mut encode);
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&root.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&((layout.nodes.len() / NODE_SIZE) as u64).to_le_bytes());
+        header.extend_from_slice(&((layout.entries.len() / ENTRY_SIZE) as u64).to_le_bytes());
+        header.extend_from_slice(&(layout.blob.len() as u64).to_le_bytes());
+        header.resize(HEADER_SIZE, 0);
+        writer.write_all(&header)?;
+        writer.write_all(&layout.nodes)?;
+        writer.write_all(&layout.entries)?;
+        writer.write_all(&layout.blob)?;
+        writer.flush()
+    }
+}
+
+#[derive(Default)]
+struct Layout {
+    nodes: Vec<u8>,
+    entries: Vec<u8>,
+    blob: Vec<u8>,
+}
+
+impl Layout {
+    //nodes are written in pre-order, so the root is always the first one
+    fn push<Value: KdValue, const ISLAND_SIZE: usize>(
+        &mut self,
+        tree: &KdTree<Value, ISLAND_SIZE>,
+        encode: &mut impl FnMut(&Value, &mut Vec<u8>),
+    ) -> u32
+    where
+        Value::Position: Scalar,
+    {
+        let index = self.nodes.len() / NODE_SIZE;
+        self.nodes.resize(self.nodes.len() + NODE_SIZE, 0);
+        let (bounds, kind, first, second) = match tree {
+            KdTree::Leaf(leaf, ..) => {
+                let first = self.entries.len() / ENTRY_SIZE;
+                let mut bounds = EMPTY;
+                for value in leaf {
+                    let value_bounds = [
+                        value.min_x().to_f64(),
+                        value.max_x().to_f64(),
+                        value.min_y().to_f64(),
+                        value.max_y().to_f64(),
+                    ];
+                    bounds = union(bounds, value_bounds);
+                    let offset = self.blob.len();
+                    encode(value, &mut self.blob);
+                    write_bounds(&mut self.entries, value_bounds);
+                    self.entries
+                        .extend_from_slice(&(offset as u64).to_le_bytes());
+                    self.entries
+                        .extend_from_slice(&((self.blob.len() - offset) as u64).to_le_bytes());
+                }
+                (bounds, LEAF_KIND, first as u64, leaf.len() as u64)
+            }
+            KdTree::Node(node) => {
+                let left = self.push(&node.left, encode);
+                let right = self.push(&node.right, encode);
+                let bounds = union(self.node_bounds(left), self.node_bounds(right));
+                (bounds, 0, left as u64, right as u64)
+            }
+        };
+        let mut record = Vec::with_capacity(NODE_SIZE);
+        write_bounds(&mut record, bounds);
+        record.extend_from_slice(&kind.to_le_bytes());
+        record.extend_from_slice(&[0; 4]);
+        record.extend_from_slice(&first.to_le_bytes());
+        record.extend_from_slice(&second.to_le_bytes());
+        self.nodes[index * NODE_SIZE..(index + 1) * NODE_SIZE].copy_from_slice(&record);
+        index as u32
+    }
+
+    fn node_bounds(&self, index: u32) -> [f64; 4] {
+        read_bounds(&self.nodes[index as usize * NODE_SIZE..])
+    }
+}
+
+/// A read-only tree queried straight from a memory-mapped file written by
+/// `KdTree::write_mapped`, so that it never has to be loaded in memory.
+///
+/// Queries yield the encoded values, to be decoded by the caller.
+pub struct MappedKdTree {
+    map: Mmap,
+    root: usize,
+    nodes: usize,
+    entries: usize,
+    blob: usize,
+}
+
+impl MappedKdTree {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        //the file is expected not to be modified while it is mapped
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(map)
+    }
+
+    pub fn from_mmap(map: Mmap) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if map.len() < HEADER_SIZE || &map[..4] != MAGIC {
+            return Err(invalid("not a memory-mapped kd-tree"));
+        }
+        if read_u32(&map[4..]) != VERSION {
+            return Err(invalid("unsupported memory-mapped kd-tree version"));
+        }
+        let tree = Self {
+            root: read_u32(&map[8..]) as usize,
+            nodes: read_u64(&map[16..]) as usize,
+            entries: read_u64(&map[24..]) as usize,
+            blob: read_u64(&map[32..]) as usize,
+            map,
+        };
+        let size = HEADER_SIZE
+            .checked_add(tree.nodes.saturating_mul(NODE_SIZE))
+            .and_then(|size| size.checked_add(tree.entries.checked_mul(ENTRY_SIZE)?))
+            .and_then(|size| size.checked_add(tree.blob));
+        if size != Some(tree.map.len()) || tree.root >= tree.nodes || !tree.is_consistent() {
+            return Err(invalid("truncated or corrupted memory-mapped kd-tree"));
+        }
+        Ok(tree)
+    }
+
+    //whether every index and range stays in the file, with children always after
+    //their parent, as written in pre-order, so that there are no cycles
+    fn is_consistent(&self) -> bool {
+        let nodes_valid = (0..self.nodes).all(|index| {
+            let node = self.node(index);
+            let (first, second) = (read_u64(&node[40..]), read_u64(&node[48..]));
+            if read_u32(&node[32..]) == LEAF_KIND {
+                first
+                    .checked_add(second)
+                    .is_some_and(|end| end <= self.entries as u64)
+            } else {
+                [first, second]
+                    .iter()
+                    .all(|&child| index < child as usize && (child as usize) < self.nodes)
+            }
+        });
+        nodes_valid
+            && (0..self.entries).all(|index| {
+                let entry = self.entry(index);
+                let (offset, len) = (read_u64(&entry[32..]), read_u64(&entry[40..]));
+                offset
+                    .checked_add(len)
+                    .is_some_and(|end| end <= self.blob as u64)
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    pub fn query_point(&self, x: f64, y: f64) -> MappedRectQuery<'_> {
+        self.query_rect(x, x, y, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: f64,
+        max_x: f64,
+        min_y: f64,
+        max_y: f64,
+    ) -> MappedRectQuery<'_> {
+        MappedRectQuery {
+            tree: self,
+            query: [min_x, max_x, min_y, max_y],
+            queue: vec![self.root],
+            leaf: 0..0,
+        }
+    }
+
+    fn node(&self, index: usize) -> &[u8] {
+        let start = HEADER_SIZE + index * NODE_SIZE;
+        &self.map[start..start + NODE_SIZE]
+    }
+
+    fn entry(&self, index: usize) -> &[u8] {
+        let start = HEADER_SIZE + self.nodes * NODE_SIZE + index * ENTRY_SIZE;
+        &self.map[start..start + ENTRY_SIZE]
+    }
+
+    fn value(&self, entry: &[u8]) -> &[u8] {
+        let start = HEADER_SIZE + self.nodes * NODE_SIZE + self.entries * ENTRY_SIZE;
+        let offset = read_u64(&entry[32..]) as usize;
+        let len = read_u64(&entry[40..]) as usize;
+        &self.map[start + offset..start + offset + len]
+    }
+}
+
+pub struct MappedRectQuery<'a> {
+    tree: &'a MappedKdTree,
+    query: [f64; 4],
+    queue: Vec<usize>,
+    leaf: std::ops::Range<usize>,
+}
+
+impl<'a> Iterator for MappedRectQuery<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for index in &mut self.leaf {
+                let entry = self.tree.entry(index);
+                if overlaps(read_bounds(entry), self.query) {
+                    return Some(self.tree.value(entry));
+                }
+            }
+            let node = self.tree.node(self.queue.pop()?);
+            if !overlaps(read_bounds(node), self.query) {
+                continue;
+            }
+            let first = read_u64(&node[40..]) as usize;
+            let second = read_u64(&node[48..]) as usize;
+            if read_u32(&node[32..]) == LEAF_KIND {
+                self.leaf = first..first + second;
+            } else {
+                self.queue.push(second);
+                self.queue.push(first);
+            }
+        }
+    }
+}
+
+fn write_bounds(buffer: &mut Vec<u8>, bounds: [f64; 4]) {
+    for bound in &bounds {
+        buffer.extend_from_slice(&bound.to_le_bytes());
+    }
+}
+
+fn read_bounds(bytes: &[u8]) -> [f64; 4] {
+    let mut bounds = [0.; 4];
+    for (i, bound) in bounds.iter_mut().enumerate() {
+        *bound = read_f64(&bytes[i * 8..]);
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryInto, fs::File};
+
+    use super::MappedKdTree;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn mapped() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = (i * 7 % 31) as f32;
+            tree.insert(TestValue::new(x, x + 1., i as f32, i as f32 + 0.5));
+        }
+        let path = std::env::temp_dir().join(format!("kdtree-mapped-{}", std::process::id()));
+        tree.write_mapped(File::create(&path).unwrap(), |value, blob| {
+            blob.extend_from_slice(&value.min_y.to_le_bytes())
+        })
+        .unwrap();
+        let mapped = MappedKdTree::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mapped.len(), 100);
+        let decode = |bytes: &[u8]| f32::from_le_bytes(bytes.try_into().unwrap());
+        let mut found: Vec<f32> = mapped.query_rect(3., 10., 20., 60.).map(decode).collect();
+        let mut expected: Vec<f32> = tree
+            .query_rect(3., 10., 20., 60.)
+            .map(|v| v.min_y)
+            .collect();
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..20 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        let mut bytes = Vec::new();
+        tree.write_mapped(&mut bytes, |_, blob| blob.push(0))
+            .unwrap();
+        let root_children = super::HEADER_SIZE + 40;
+        let leaf = (0..)
+            .map(|index| super::HEADER_SIZE + index * super::NODE_SIZE)
+            .find(|&node| super::read_u32(&bytes[node + 32..]) == super::LEAF_KIND)
+            .unwrap();
+        let leaf_entries = leaf + 48;
+        //a child out of range, a child pointing back to the root, too many entries
+        for (offset, corrupted) in [
+            (root_children, 1000u64),
+            (root_children, 0),
+            (leaf_entries, 99),
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + 8].copy_from_slice(&corrupted.to_le_bytes());
+            let path = std::env::temp_dir().join(format!(
+                "kdtree-corrupted-{}-{}",
+                std::process::id(),
+                offset + corrupted as usize
+            ));
+            std::fs::write(&path, &bytes).unwrap();
+            let error = MappedKdTree::open(&path).err().unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{KdTree, KdValue};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// Like `query_rect`, giving mutable access to the values, to update their
+    /// other fields in place.
+    ///
+    /// The bounds of the values must not change, which is checked in debug builds
+    /// when each `ValueMut` is dropped.
+    pub fn query_rect_mut(
+        &mut self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> RectQueryMut<'_, Value, ISLAND_SIZE> {
+        RectQueryMut {
+            rect: [min_x, max_x, min_y, max_y],
+            queue: vec![self],
+            leaf: [].iter_mut(),
+        }
+    }
+
+    /// Like `query_point`, giving mutable access to the values like
+    /// `query_rect_mut`.
+    pub fn query_point_mut(
+        &mut self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> RectQueryMut<'_, Value, ISLAND_SIZE> {
+        self.query_rect_mut(x.clone(), x, y.clone(), y)
+    }
+}
+
+/// A value found by a mutable query, whose bounds must be left unchanged.
+pub struct ValueMut<'a, Value: KdValue> {
+    value: &'a mut Value,
+    #[cfg(debug_assertions)]
+    bounds: [Value::Position; 4],
+}
+
+impl<'a, Value: KdValue> ValueMut<'a, Value> {
+    pub(crate) fn new(value: &'a mut Value) -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            bounds: [value.min_x(), value.max_x(), value.min_y(), value.max_y()],
+            value,
+        }
+    }
+}
+
+impl<Value: KdValue> Deref for ValueMut<'_, Value> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        self.value
+    }
+}
+
+impl<Value: KdValue> DerefMut for ValueMut<'_, Value> {
+    fn deref_mut(&mut self) -> &mut Value {
+        self.value
+    }
+}
+
+impl<Value: KdValue> Drop for ValueMut<'_, Value> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            let value = &self.value;
+            let bounds = [value.min_x(), value.max_x(), value.min_y(), value.max_y()];
+            //NaN bounds stay the same when they are still NaN
+            let unchanged = bounds
+                .iter()
+                .zip(&self.bounds)
+                .all(|(a, b)| a == b || (a.partial_cmp(a).is_none() && b.partial_cmp(b).is_none()));
+            assert!(
+                unchanged,
+                "the bounds of a value changed during a mutable query"
+            );
+        }
+    }
+}
+
+/// The values overlapping a rectangle, mutably.
+pub struct RectQueryMut<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    //min x, max x, min y, max y
+    rect: [Value::Position; 4],
+    queue: Vec<&'a mut KdTree<Value, ISLAND_SIZE>>,
+    leaf: core::slice::IterMut<'a, Value>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for RectQueryMut<'a, Value, ISLAND_SIZE>
+{
+    type Item = ValueMut<'a, Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let [min_x, max_x, min_y, max_y] = &self.rect;
+        loop {
+            for value in self.leaf.by_ref() {
+                //not being apart, like `query_rect`, so that values with NaN
+                //bounds still match
+                if !(value.min_x() > *max_x
+                    || *min_x > value.max_x()
+                    || value.min_y() > *max_y
+                    || *min_y > value.max_y())
+                {
+                    return Some(ValueMut::new(value));
+                }
+            }
+            match self.queue.pop()? {
+                KdTree::Leaf(leaf, ..) => self.leaf = leaf.iter_mut(),
+                KdTree::Node(node) => {
+                    let (min, max) = if node.vertical {
+                        (min_y, max_y)
+                    } else {
+                        (min_x, max_x)
+                    };
+                    let visit_left = min.partial_cmp(&node.left_max) != Some(Ordering::Greater);
+                    let visit_right = *max >= node.median;
+                    if visit_left {
+                        self.queue.push(&mut node.left);
+                    }
+                    if visit_right {
+                        self.queue.push(&mut node.right);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree, KdValue};
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct Unit {
+        bounds: Aabb<f32>,
+        health: u32,
+    }
+
+    impl KdValue for Unit {
+        type Position = f32;
+        fn min_x(&self) -> f32 {
+            self.bounds.min_x
+        }
+        fn min_y(&self) -> f32 {
+            self.bounds.min_y
+        }
+        fn max_x(&self) -> f32 {
+            self.bounds.max_x
+        }
+        fn max_y(&self) -> f32 {
+            self.bounds.max_y
+        }
+    }
+
+    #[test]
+    fn mutable_queries() {
+        let mut tree = KdTree::<Unit, 4>::default();
+        for i in 0..100 {
+            let (x, y) = ((i % 10) as f32, (i / 10) as f32);
+            tree.insert(Unit {
+                bounds: Aabb::new(x, x + 0.5, y, y + 0.5),
+                health: 100,
+            });
+        }
+        //damage in a blast radius
+        for mut unit in tree.query_rect_mut(2., 4., 2., 4.) {
+            unit.health -= 30;
+        }
+        for mut unit in tree.query_point_mut(3.25, 3.25) {
+            unit.health -= 1;
+        }
+        let health: u32 = tree.iter().map(|unit| unit.health).sum();
+        assert_eq!(health, 100 * 100 - 9 * 30 - 1);
+        assert_eq!(
+            tree.query_point(3.25, 3.25).next().map(|unit| unit.health),
+            Some(69)
+        );
+    }
+
+    #[test]
+    fn mutable_queries_nan_bounds() {
+        let mut tree = KdTree::<Unit, 4>::default();
+        tree.insert(Unit {
+            bounds: Aabb::new(0., 1., 0., f32::NAN),
+            health: 100,
+        });
+        for mut unit in tree.query_rect_mut(0., 1., 0., 1.) {
+            unit.health -= 30;
+        }
+        assert_eq!(tree.iter().next().map(|unit| unit.health), Some(70));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "bounds of a value changed")]
+    fn mutable_queries_keep_bounds() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        tree.insert(Aabb::new(0., 1., 0., 1.));
+        for mut value in tree.query_point_mut(0.5, 0.5) {
+            value.max_x = 2.;
+        }
+    }
+}
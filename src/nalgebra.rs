@@ -0,0 +1,92 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::{Aabb, KdValue};
+
+//boxes given as [min, max]
+macro_rules! impl_nalgebra {
+    ($scalar:ty) => {
+        impl KdValue for [Point2<$scalar>; 2] {
+            type Position = $scalar;
+
+            fn min_x(&self) -> $scalar {
+                self[0].x
+            }
+
+            fn min_y(&self) -> $scalar {
+                self[0].y
+            }
+
+            fn max_x(&self) -> $scalar {
+                self[1].x
+            }
+
+            fn max_y(&self) -> $scalar {
+                self[1].y
+            }
+        }
+
+        impl KdValue for [Vector2<$scalar>; 2] {
+            type Position = $scalar;
+
+            fn min_x(&self) -> $scalar {
+                self[0].x
+            }
+
+            fn min_y(&self) -> $scalar {
+                self[0].y
+            }
+
+            fn max_x(&self) -> $scalar {
+                self[1].x
+            }
+
+            fn max_y(&self) -> $scalar {
+                self[1].y
+            }
+        }
+
+        impl From<[Point2<$scalar>; 2]> for Aabb<$scalar> {
+            fn from([min, max]: [Point2<$scalar>; 2]) -> Self {
+                Aabb::new(min.x, max.x, min.y, max.y)
+            }
+        }
+
+        impl From<Aabb<$scalar>> for [Point2<$scalar>; 2] {
+            fn from(bounds: Aabb<$scalar>) -> Self {
+                [
+                    Point2::new(bounds.min_x, bounds.min_y),
+                    Point2::new(bounds.max_x, bounds.max_y),
+                ]
+            }
+        }
+    };
+}
+
+impl_nalgebra!(f32);
+impl_nalgebra!(f64);
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point2, Vector2};
+
+    use crate::{tests::interop_boxes, Aabb};
+
+    #[test]
+    fn nalgebra_boxes() {
+        let hit = interop_boxes(
+            |x, y| {
+                let min = Point2::new(x, y);
+                [min, min + Vector2::new(2., 2.)]
+            },
+            |tree, x, y| tree.query_point_vec(Point2::new(x, y)).cloned().collect(),
+            |tree, [min_x, max_x, min_y, max_y]| {
+                tree.query_rect_vec(Vector2::new(min_x, min_y), Vector2::new(max_x, max_y))
+                    .count()
+            },
+        );
+        assert_eq!(hit, [Point2::new(30., 10.), Point2::new(32., 12.)]);
+        let bounds = Aabb::from(hit);
+        assert_eq!(bounds, Aabb::new(30., 32., 10., 12.));
+        assert_eq!(<[Point2<f64>; 2]>::from(bounds), hit);
+    }
+}
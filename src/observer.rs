@@ -0,0 +1,130 @@
+use crate::{KdTree, KdValue};
+
+/// Callbacks for the changes of an `ObservedKdTree`, all doing nothing by
+/// default.
+pub trait Observer<Value: KdValue, const ISLAND_SIZE: usize> {
+    fn inserted(&mut self, _value: &Value) {}
+
+    fn removed(&mut self, _value: &Value) {}
+
+    /// A full leaf was turned into `subtree` during an insertion.
+    fn split(&mut self, _subtree: &KdTree<Value, ISLAND_SIZE>) {}
+
+    fn rebuilt(&mut self, _tree: &KdTree<Value, ISLAND_SIZE>) {}
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Observer<Value, ISLAND_SIZE> for () {}
+
+/// A tree telling an observer about each of its changes.
+#[derive(Debug, Default)]
+pub struct ObservedKdTree<Value: KdValue, O, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    observer: O,
+}
+
+impl<Value: KdValue, O: Observer<Value, ISLAND_SIZE>, const ISLAND_SIZE: usize>
+    ObservedKdTree<Value, O, ISLAND_SIZE>
+{
+    pub fn new(observer: O) -> Self {
+        Self {
+            tree: KdTree::default(),
+            observer,
+        }
+    }
+
+    /// The tree, to query it.
+    pub fn tree(&self) -> &KdTree<Value, ISLAND_SIZE> {
+        &self.tree
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn into_parts(self) -> (KdTree<Value, ISLAND_SIZE>, O) {
+        (self.tree, self.observer)
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.observer.inserted(&value);
+        self.tree.insert_internal(value, false, &mut self.observer);
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        let removed = self.tree.remove_one(value.clone());
+        if removed {
+            self.observer.removed(&value);
+        }
+        removed
+    }
+
+    /// Removes every value equal to `value`, telling the observer about each.
+    pub fn remove_all(&mut self, value: Value) {
+        while self.remove_one(value.clone()) {}
+    }
+
+    pub fn rebuild(&mut self) {
+        self.tree.rebuild();
+        self.observer.rebuilt(&self.tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ObservedKdTree, Observer};
+    use crate::{tests::TestValue, KdTree};
+
+    #[derive(Default)]
+    struct Log {
+        len: usize,
+        splits: usize,
+        rebuilds: usize,
+    }
+
+    impl Observer<TestValue, 4> for Log {
+        fn inserted(&mut self, _value: &TestValue) {
+            self.len += 1;
+        }
+
+        fn removed(&mut self, _value: &TestValue) {
+            self.len -= 1;
+        }
+
+        fn split(&mut self, subtree: &KdTree<TestValue, 4>) {
+            assert!(matches!(subtree, KdTree::Node(_)));
+            self.splits += 1;
+        }
+
+        fn rebuilt(&mut self, _tree: &KdTree<TestValue, 4>) {
+            self.rebuilds += 1;
+        }
+    }
+
+    #[test]
+    fn observer() {
+        let mut tree = ObservedKdTree::new(Log::default());
+        for i in 0..100 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        tree.insert(TestValue::new(5., 6., 0., 1.));
+        assert_eq!(tree.observer().len, 101);
+        assert!(tree.observer().splits > 10);
+        tree.remove_all(TestValue::new(5., 6., 0., 1.));
+        assert!(!tree.remove_one(TestValue::new(-1., 0., 0., 1.)));
+        assert_eq!(tree.observer().len, 99);
+        tree.rebuild();
+        let (tree, log) = tree.into_parts();
+        assert_eq!(log.rebuilds, 1);
+        assert_eq!(tree.len(), log.len);
+
+        //the unit observer ignores everything
+        let mut tree = ObservedKdTree::<TestValue, (), 4>::new(());
+        tree.insert(TestValue::new(0., 1., 0., 1.));
+        assert_eq!(tree.tree().len(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+
+use crate::{KdTree, KdValue, SpatialIndex2D};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Calls `f` once for every pair of distinct values overlapping each other.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&Value, &Value)) {
+        for value in self.iter() {
+            let query = self.query_rect(value.min_x(), value.max_x(), value.min_y(), value.max_y());
+            for other in query {
+                //each pair is found from both sides, only keep one of them
+                if (value as *const Value) < (other as *const Value) {
+                    f(value, other)
+                }
+            }
+        }
+    }
+}
+
+/// A contact change between two values, identified by their ids (the smallest id
+/// first).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ContactEvent<Id> {
+    Started(Id, Id),
+    Ended(Id, Id),
+}
+
+/// Keeps track of the overlapping pairs of a tree from one tick to the next, and
+/// reports the contacts that started and ended in between.
+///
+/// Values are identified by the `id` closure; values sharing an id never make a
+/// pair.
+#[derive(Debug, Clone)]
+pub struct PairManager<Id, F> {
+    id: F,
+    pairs: BTreeSet<(Id, Id)>,
+}
+
+impl<Id: Ord + Clone, F> PairManager<Id, F> {
+    pub fn new<Value>(id: F) -> Self
+    where
+        F: Fn(&Value) -> Id,
+    {
+        Self {
+            id,
+            pairs: BTreeSet::new(),
+        }
+    }
+
+    /// Computes the overlapping pairs of `index`, and returns the events since the
+    /// previous update: ended contacts first, then started ones, each in id order.
+    pub fn update<Index: SpatialIndex2D>(&mut self, index: &Index) -> Vec<ContactEvent<Id>>
+    where
+        F: Fn(&Index::Value) -> Id,
+    {
+        let mut pairs = BTreeSet::new();
+        index.for_each_pair(&mut |a, b| {
+            let (a, b) = ((self.id)(a), (self.id)(b));
+            match a.cmp(&b) {
+                core::cmp::Ordering::Less => pairs.insert((a, b)),
+                core::cmp::Ordering::Greater => pairs.insert((b, a)),
+                core::cmp::Ordering::Equal => false,
+            };
+        });
+        let mut events: Vec<_> = self
+            .pairs
+            .difference(&pairs)
+            .map(|(a, b)| ContactEvent::Ended(a.clone(), b.clone()))
+            .collect();
+        events.extend(
+            pairs
+                .difference(&self.pairs)
+                .map(|(a, b)| ContactEvent::Started(a.clone(), b.clone())),
+        );
+        self.pairs = pairs;
+        events
+    }
+
+    pub fn is_touching(&self, a: &Id, b: &Id) -> bool {
+        let pair = if a < b {
+            (a.clone(), b.clone())
+        } else {
+            (b.clone(), a.clone())
+        };
+        self.pairs.contains(&pair)
+    }
+
+    /// The pairs in contact as of the last update.
+    pub fn pairs(&self) -> impl Iterator<Item = &(Id, Id)> {
+        self.pairs.iter()
+    }
+
+    /// Forgets every pair, so that the next update reports them all as started.
+    pub fn clear(&mut self) {
+        self.pairs.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContactEvent, PairManager};
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn contact_events() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..10 {
+            let x = i as f32 * 2.;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        let mut manager = PairManager::new(|value: &TestValue| (value.min_x * 10.) as i32);
+        assert!(manager.update(&tree).is_empty());
+        let moving = TestValue::new(4.5, 5.5, 0., 1.);
+        tree.insert(moving.clone());
+        assert_eq!(manager.update(&tree), vec![ContactEvent::Started(40, 45)]);
+        assert!(manager.is_touching(&45, &40));
+        tree.remove_one(moving);
+        tree.insert(TestValue::new(5.5, 6.5, 0., 1.));
+        assert_eq!(
+            manager.update(&tree),
+            vec![ContactEvent::Ended(40, 45), ContactEvent::Started(55, 60)],
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+use parry2d::{
+    bounding_volume,
+    math::{Isometry, Point, Real},
+    partitioning::{Bvh, BvhBuildStrategy},
+    query,
+    shape::Shape,
+};
+
+use crate::{Aabb, KdTree, KdValue, RectQuery, Scalar};
+
+impl From<bounding_volume::Aabb> for Aabb<Real> {
+    fn from(aabb: bounding_volume::Aabb) -> Self {
+        Aabb::new(aabb.mins.x, aabb.maxs.x, aabb.mins.y, aabb.maxs.y)
+    }
+}
+
+impl From<Aabb<Real>> for bounding_volume::Aabb {
+    fn from(bounds: Aabb<Real>) -> Self {
+        bounding_volume::Aabb::new(
+            Point::new(bounds.min_x, bounds.min_y),
+            Point::new(bounds.max_x, bounds.max_y),
+        )
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// The values intersecting a parry shape: the tree is queried with the bounding
+    /// box of the shape, then `shape_of` gives the exact shape of each candidate to
+    /// be tested by parry. Candidates without a shape are skipped, and the pairs of
+    /// shapes parry does not support are kept.
+    pub fn query_parry_shape<'a, 's, F>(
+        &'a self,
+        position: &'a Isometry<Real>,
+        shape: &'a dyn Shape,
+        shape_of: F,
+    ) -> ParryQuery<'a, Value, F, ISLAND_SIZE>
+    where
+        F: FnMut(&Value) -> Option<(Isometry<Real>, &'s dyn Shape)>,
+    {
+        let bounds = Aabb::from(shape.compute_aabb(position));
+        let p = Value::Position::from_f64;
+        ParryQuery {
+            query: self.query_rect(
+                p(bounds.min_x as f64),
+                p(bounds.max_x as f64),
+                p(bounds.min_y as f64),
+                p(bounds.max_y as f64),
+            ),
+            position,
+            shape,
+            shape_of,
+        }
+    }
+
+    /// Exports the boxes of the tree to a parry `Bvh`, whose leaf indexes are the
+    /// indexes of the values in the returned list.
+    pub fn to_parry_bvh(&self) -> (Bvh, Vec<&Value>) {
+        let values: Vec<&Value> = self.iter().collect();
+        let leaves = values.iter().enumerate().map(|(index, value)| {
+            let bounds = Aabb::new(
+                value.min_x().to_f64() as Real,
+                value.max_x().to_f64() as Real,
+                value.min_y().to_f64() as Real,
+                value.max_y().to_f64() as Real,
+            );
+            (index, bounding_volume::Aabb::from(bounds))
+        });
+        (Bvh::from_iter(BvhBuildStrategy::Binned, leaves), values)
+    }
+}
+
+/// The values intersecting a parry shape.
+pub struct ParryQuery<'a, Value: KdValue, F, const ISLAND_SIZE: usize> {
+    query: RectQuery<'a, Value, ISLAND_SIZE>,
+    position: &'a Isometry<Real>,
+    shape: &'a dyn Shape,
+    shape_of: F,
+}
+
+impl<'a, 's, Value: KdValue, F, const ISLAND_SIZE: usize> Iterator
+    for ParryQuery<'a, Value, F, ISLAND_SIZE>
+where
+    F: FnMut(&Value) -> Option<(Isometry<Real>, &'s dyn Shape)>,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.query.next()?;
+            let (position, shape) = match (self.shape_of)(value) {
+                Some(shape) => shape,
+                None => continue,
+            };
+            if query::intersection_test(self.position, self.shape, &position, shape).unwrap_or(true)
+            {
+                return Some(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parry2d::{
+        bounding_volume,
+        math::{Isometry, Point, Vector},
+        shape::{Ball, Cuboid},
+    };
+
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn parry_shapes() {
+        let balls: Vec<_> = (0..10)
+            .map(|i| (Vector::new(i as f32 * 3., 0.), Ball::new(1.)))
+            .collect();
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for (center, _) in &balls {
+            tree.insert(Aabb::new(
+                center.x - 1.,
+                center.x + 1.,
+                center.y - 1.,
+                center.y + 1.,
+            ));
+        }
+        let shape_of = |bounds: &Aabb<f32>| {
+            let (center, ball) = balls
+                .iter()
+                .find(|(center, _)| center.x == bounds.min_x + 1.)?;
+            Some((
+                Isometry::translation(center.x, center.y),
+                ball as &dyn parry2d::shape::Shape,
+            ))
+        };
+        //a box touching the corner of the box of a ball, but not the ball
+        let cuboid = Cuboid::new(Vector::new(0.2, 0.2));
+        let position = Isometry::translation(4.1, 1.1);
+        assert_eq!(tree.query_rect(3.9, 4.3, 0.9, 1.3).count(), 1);
+        assert_eq!(
+            tree.query_parry_shape(&position, &cuboid, shape_of).count(),
+            0
+        );
+        let position = Isometry::translation(3.8, 0.8);
+        let hits: Vec<_> = tree
+            .query_parry_shape(&position, &cuboid, shape_of)
+            .collect();
+        assert_eq!(hits, vec![&Aabb::new(2., 4., -1., 1.)]);
+
+        let (bvh, values) = tree.to_parry_bvh();
+        assert_eq!(bvh.leaf_count(), 10);
+        let aabb = bounding_volume::Aabb::new(Point::new(8.5, 0.), Point::new(9.5, 0.5));
+        let hits: Vec<_> = bvh
+            .intersect_aabb(&aabb)
+            .map(|leaf| values[leaf as usize])
+            .collect();
+        assert_eq!(hits, vec![&Aabb::new(8., 10., -1., 1.)]);
+        assert_eq!(Aabb::from(aabb), Aabb::new(8.5, 9.5, 0., 0.5));
+    }
+}
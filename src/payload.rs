@@ -0,0 +1,135 @@
+use core::cell::{Ref, RefCell, RefMut};
+
+use crate::{KdTree, KdValue};
+
+/// A value made of immutable bounds and a payload that can be mutated through a
+/// shared reference, so that query results can be updated while iterating.
+///
+/// Only the payload is behind the `RefCell`, so the tree can never be corrupted
+/// by mutating it:
+///
+/// ```
+/// use kdtree_collisions::{Aabb, KdTree, PayloadCell};
+///
+/// let mut tree = KdTree::<PayloadCell<Aabb<f32>, u32>, 8>::default();
+/// tree.insert(PayloadCell::new(Aabb::new(0., 1., 0., 1.), 100));
+/// for value in tree.query_point(0.5, 0.5) {
+///     *value.payload_mut() -= 10;
+/// }
+/// assert_eq!(*tree.iter().next().unwrap().payload(), 90);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PayloadCell<Bounds, T> {
+    pub(crate) bounds: Bounds,
+    payload: RefCell<T>,
+}
+
+impl<Bounds: KdValue, T> PayloadCell<Bounds, T> {
+    pub fn new(bounds: Bounds, payload: T) -> Self {
+        Self {
+            bounds,
+            payload: RefCell::new(payload),
+        }
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the payload is currently mutably borrowed.
+    pub fn payload(&self) -> Ref<'_, T> {
+        self.payload.borrow()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the payload is currently borrowed.
+    pub fn payload_mut(&self) -> RefMut<'_, T> {
+        self.payload.borrow_mut()
+    }
+
+    pub fn replace_payload(&self, payload: T) -> T {
+        self.payload.replace(payload)
+    }
+
+    pub fn into_parts(self) -> (Bounds, T) {
+        (self.bounds, self.payload.into_inner())
+    }
+}
+
+impl<Bounds: KdValue, T: Default + Clone + core::fmt::Debug + PartialEq> KdValue
+    for PayloadCell<Bounds, T>
+{
+    type Position = Bounds::Position;
+
+    fn min_x(&self) -> Self::Position {
+        self.bounds.min_x()
+    }
+
+    fn min_y(&self) -> Self::Position {
+        self.bounds.min_y()
+    }
+
+    fn max_x(&self) -> Self::Position {
+        self.bounds.max_x()
+    }
+
+    fn max_y(&self) -> Self::Position {
+        self.bounds.max_y()
+    }
+}
+
+impl<
+        Bounds: KdValue,
+        T: Default + Clone + core::fmt::Debug + PartialEq,
+        const ISLAND_SIZE: usize,
+    > KdTree<PayloadCell<Bounds, T>, ISLAND_SIZE>
+{
+    /// Calls `f` with the bounds and the mutable payload of every value overlapping
+    /// the rectangle.
+    pub fn for_each_payload_in_rect(
+        &self,
+        min_x: Bounds::Position,
+        max_x: Bounds::Position,
+        min_y: Bounds::Position,
+        max_y: Bounds::Position,
+        mut f: impl FnMut(&Bounds, &mut T),
+    ) {
+        for value in self.query_rect(min_x, max_x, min_y, max_y) {
+            f(&value.bounds, &mut value.payload_mut())
+        }
+    }
+
+    /// Calls `f` with the bounds and the mutable payload of every value containing
+    /// the point.
+    pub fn for_each_payload_at_point(
+        &self,
+        x: Bounds::Position,
+        y: Bounds::Position,
+        mut f: impl FnMut(&Bounds, &mut T),
+    ) {
+        for value in self.query_point(x, y) {
+            f(&value.bounds, &mut value.payload_mut())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PayloadCell;
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn payload_cells() {
+        let mut tree = KdTree::<PayloadCell<Aabb<f32>, i32>, 4>::default();
+        for i in 0..20 {
+            let x = i as f32;
+            tree.insert(PayloadCell::new(Aabb::new(x, x + 1., 0., 1.), 100));
+        }
+        tree.for_each_payload_in_rect(4.5, 8.5, 0., 1., |_, health| *health -= 30);
+        let damaged = tree.iter().filter(|value| *value.payload() == 70).count();
+        assert_eq!(damaged, 5);
+    }
+}
@@ -0,0 +1,301 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use crate::{split_due, KdTree, KdValue};
+
+/// A copy-on-write tree sharing its nodes between clones.
+///
+/// Cloning is O(1), and a mutation only copies the nodes on the path to the
+/// modified leaf, leaving the other clones untouched. This makes snapshots free
+/// for rollback and undo systems.
+#[derive(Debug)]
+pub enum PersistentKdTree<Value: KdValue, const ISLAND_SIZE: usize> {
+    Leaf(Arc<Vec<Value>>),
+    Node(Arc<PersistentNode<Value, ISLAND_SIZE>>),
+}
+
+#[derive(Debug)]
+pub struct PersistentNode<Value: KdValue, const ISLAND_SIZE: usize> {
+    vertical: bool,
+    median: Value::Position,
+    left_max: Value::Position,
+    left: PersistentKdTree<Value, ISLAND_SIZE>,
+    right: PersistentKdTree<Value, ISLAND_SIZE>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for PersistentKdTree<Value, ISLAND_SIZE> {
+    fn clone(&self) -> Self {
+        match self {
+            PersistentKdTree::Leaf(leaf) => PersistentKdTree::Leaf(Arc::clone(leaf)),
+            PersistentKdTree::Node(node) => PersistentKdTree::Node(Arc::clone(node)),
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for PersistentNode<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            vertical: self.vertical,
+            median: self.median.clone(),
+            left_max: self.left_max.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Default for PersistentKdTree<Value, ISLAND_SIZE> {
+    fn default() -> Self {
+        PersistentKdTree::Leaf(Arc::new(Vec::with_capacity(ISLAND_SIZE)))
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> From<KdTree<Value, ISLAND_SIZE>>
+    for PersistentKdTree<Value, ISLAND_SIZE>
+{
+    fn from(tree: KdTree<Value, ISLAND_SIZE>) -> Self {
+        match tree {
+            KdTree::Leaf(leaf, ..) => PersistentKdTree::Leaf(Arc::new(leaf)),
+            KdTree::Node(node) => {
+                let node = *node;
+                PersistentKdTree::Node(Arc::new(PersistentNode {
+                    vertical: node.vertical,
+                    median: node.median,
+                    left_max: node.left_max,
+                    left: node.left.into(),
+                    right: node.right.into(),
+                }))
+            }
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PersistentKdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// Returns a new tree containing the value, sharing everything it can with `self`.
+    pub fn with(&self, value: Value) -> Self {
+        let mut tree = self.clone();
+        tree.insert(value);
+        tree
+    }
+
+    /// Returns a new tree without one occurrence of the value, or `None` if it was not found.
+    pub fn without(&self, value: Value) -> Option<Self> {
+        let mut tree = self.clone();
+        if tree.remove_one(value) {
+            Some(tree)
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.insert_internal(value, false)
+    }
+
+    fn insert_internal(&mut self, value: Value, vertical: bool) {
+        let change = match self {
+            PersistentKdTree::Leaf(leaf) => {
+                let leaf = Arc::make_mut(leaf);
+                leaf.push(value);
+                if !split_due(leaf.len(), ISLAND_SIZE) {
+                    None
+                } else {
+                    let values = core::mem::take(leaf);
+                    Some(KdTree::<Value, ISLAND_SIZE>::build_internal(values, vertical).into())
+                }
+            }
+            PersistentKdTree::Node(node) => {
+                let node = Arc::make_mut(node);
+                let vertical = node.vertical;
+                node.choose_tree(&value).insert_internal(value, !vertical);
+                None
+            }
+        };
+        if let Some(new_tree) = change {
+            *self = new_tree;
+        }
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        //checking first avoids copying the path when the value is not there
+        if !self.contains(&value) {
+            return false;
+        }
+        self.remove_one_internal(value);
+        true
+    }
+
+    //the value is known to be there, the descent copies the path to its leaf
+    fn remove_one_internal(&mut self, value: Value) {
+        match self {
+            PersistentKdTree::Leaf(leaf) => {
+                let leaf = Arc::make_mut(leaf);
+                if let Some(index) = leaf.iter().position(|val| val == &value) {
+                    leaf.swap_remove(index);
+                }
+            }
+            PersistentKdTree::Node(node) => {
+                let node = Arc::make_mut(node);
+                node.choose_tree(&value).remove_one_internal(value)
+            }
+        }
+    }
+
+    pub fn contains(&self, value: &Value) -> bool {
+        match self {
+            PersistentKdTree::Leaf(leaf) => leaf.contains(value),
+            PersistentKdTree::Node(node) => {
+                let position = if node.vertical {
+                    value.min_y()
+                } else {
+                    value.min_x()
+                };
+                if position < node.median {
+                    node.left.contains(value)
+                } else {
+                    node.right.contains(value)
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            PersistentKdTree::Leaf(leaf) => leaf.len(),
+            PersistentKdTree::Node(node) => node.left.len() + node.right.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(x.clone(), x, y.clone(), y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        PersistentRectQuery {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            queue: vec![self],
+            items_to_yield: Vec::new(),
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PersistentNode<Value, ISLAND_SIZE> {
+    fn choose_tree(&mut self, value: &Value) -> &mut PersistentKdTree<Value, ISLAND_SIZE> {
+        let cmp_position = if self.vertical {
+            value.min_y()
+        } else {
+            value.min_x()
+        };
+        if cmp_position < self.median {
+            let max = if self.vertical {
+                value.max_y()
+            } else {
+                value.max_x()
+            };
+            if max > self.left_max {
+                self.left_max = max
+            }
+            &mut self.left
+        } else {
+            &mut self.right
+        }
+    }
+}
+
+pub struct PersistentRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    min_x: Value::Position,
+    max_x: Value::Position,
+    min_y: Value::Position,
+    max_y: Value::Position,
+    queue: Vec<&'a PersistentKdTree<Value, ISLAND_SIZE>>,
+    items_to_yield: Vec<&'a Value>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for PersistentRectQuery<'a, Value, ISLAND_SIZE>
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items_to_yield.pop() {
+                return Some(item);
+            }
+            match self.queue.pop()? {
+                PersistentKdTree::Leaf(leaves) => {
+                    for leaf in leaves.iter() {
+                        if leaf.min_x() <= self.max_x
+                            && self.min_x <= leaf.max_x()
+                            && leaf.min_y() <= self.max_y
+                            && self.min_y <= leaf.max_y()
+                        {
+                            self.items_to_yield.push(leaf)
+                        }
+                    }
+                }
+                PersistentKdTree::Node(node) => {
+                    let (min, max) = if node.vertical {
+                        (&self.min_y, &self.max_y)
+                    } else {
+                        (&self.min_x, &self.max_x)
+                    };
+                    if *min <= node.left_max {
+                        self.queue.push(&node.left)
+                    }
+                    if *max >= node.median {
+                        self.queue.push(&node.right)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentKdTree;
+    use crate::tests::TestValue;
+
+    #[test]
+    fn persistent() {
+        let mut tree = PersistentKdTree::<TestValue, 4>::default();
+        for i in 0..50 {
+            let x = (i * 7 % 23) as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        let snapshot = tree.clone();
+        let extra = TestValue::new(100., 101., 0., 1.);
+        let tree = tree.with(extra.clone());
+        assert_eq!(tree.query_point(100.5, 0.5).count(), 1);
+        assert_eq!(snapshot.query_point(100.5, 0.5).count(), 0);
+        let removed = snapshot
+            .without(TestValue::new(0., 1., 0., 1.))
+            .expect("the value is in the tree");
+        assert_eq!(removed.len(), 49);
+        assert_eq!(snapshot.len(), 50);
+        assert_eq!(tree.len(), 51);
+        assert!(snapshot.without(extra).is_none());
+    }
+}
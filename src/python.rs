@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use numpy::{
+    ndarray::{ArrayView1, ArrayView2},
+    PyArray1, PyReadonlyArray1, PyReadonlyArray2,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{KdTree, KdValue};
+
+type Ids<'py> = Bound<'py, PyArray1<u64>>;
+
+//a box and its id
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct IdBox {
+    bounds: [f64; 4],
+    id: u64,
+}
+
+impl KdValue for IdBox {
+    type Position = f64;
+
+    fn min_x(&self) -> f64 {
+        self.bounds[0]
+    }
+
+    fn min_y(&self) -> f64 {
+        self.bounds[1]
+    }
+
+    fn max_x(&self) -> f64 {
+        self.bounds[2]
+    }
+
+    fn max_y(&self) -> f64 {
+        self.bounds[3]
+    }
+}
+
+/// A tree of boxes identified by `u64` ids, exported to Python as `KdTree`.
+///
+/// Boxes are given as `[min_x, min_y, max_x, max_y]`, in bulk as `(n, 4)` numpy
+/// arrays, and query results are returned as numpy arrays of ids.
+#[pyclass(name = "KdTree", module = "kdtree_collisions")]
+#[derive(Debug, Default)]
+pub struct PyKdTree {
+    tree: KdTree<IdBox, 16>,
+    //the bounds of every id, to remove by id
+    bounds: BTreeMap<u64, [f64; 4]>,
+}
+
+impl PyKdTree {
+    fn insert_one(&mut self, id: u64, bounds: [f64; 4]) {
+        self.remove(id);
+        self.bounds.insert(id, bounds);
+        self.tree.insert(IdBox { bounds, id });
+    }
+
+    fn insert_rows(&mut self, ids: ArrayView1<u64>, boxes: ArrayView2<f64>) -> PyResult<()> {
+        if boxes.ncols() != 4 || boxes.nrows() != ids.len() {
+            return Err(PyValueError::new_err(
+                "expected one [min_x, min_y, max_x, max_y] row per id",
+            ));
+        }
+        for (&id, row) in ids.iter().zip(boxes.rows()) {
+            self.insert_one(id, [row[0], row[1], row[2], row[3]]);
+        }
+        Ok(())
+    }
+
+    //the hits of every rectangle, concatenated, and the offsets of each one's
+    //hits, with a final offset at the end
+    fn query_rows(&self, rects: ArrayView2<f64>) -> PyResult<(Vec<u64>, Vec<u64>)> {
+        if rects.ncols() != 4 {
+            return Err(PyValueError::new_err(
+                "expected [min_x, min_y, max_x, max_y] rows",
+            ));
+        }
+        let mut offsets = Vec::with_capacity(rects.nrows() + 1);
+        let mut ids = Vec::new();
+        for row in rects.rows() {
+            offsets.push(ids.len() as u64);
+            ids.extend(
+                self.tree
+                    .query_rect(row[0], row[2], row[1], row[3])
+                    .map(|value| value.id),
+            );
+        }
+        offsets.push(ids.len() as u64);
+        Ok((offsets, ids))
+    }
+}
+
+#[pymethods]
+impl PyKdTree {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Inserts a box, replacing the previous one with the same id.
+    fn insert(&mut self, id: u64, min_x: f64, min_y: f64, max_x: f64, max_y: f64) {
+        self.insert_one(id, [min_x, min_y, max_x, max_y])
+    }
+
+    /// Inserts an `(n, 4)` array of boxes with their `n` ids.
+    fn insert_many(
+        &mut self,
+        ids: PyReadonlyArray1<u64>,
+        boxes: PyReadonlyArray2<f64>,
+    ) -> PyResult<()> {
+        self.insert_rows(ids.as_array(), boxes.as_array())
+    }
+
+    /// Removes the box with this id, returning whether there was one.
+    fn remove(&mut self, id: u64) -> bool {
+        match self.bounds.remove(&id) {
+            Some(bounds) => self.tree.remove_one(IdBox { bounds, id }),
+            None => false,
+        }
+    }
+
+    fn query_rect<'py>(
+        &self,
+        py: Python<'py>,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Ids<'py> {
+        let ids = self
+            .tree
+            .query_rect(min_x, max_x, min_y, max_y)
+            .map(|value| value.id)
+            .collect();
+        PyArray1::from_vec(py, ids)
+    }
+
+    fn query_point<'py>(&self, py: Python<'py>, x: f64, y: f64) -> Ids<'py> {
+        let ids = self.tree.query_point(x, y).map(|value| value.id).collect();
+        PyArray1::from_vec(py, ids)
+    }
+
+    /// Queries an `(n, 4)` array of rectangles. Returns `(offsets, ids)`, the hits
+    /// of rectangle `i` being `ids[offsets[i]:offsets[i + 1]]`.
+    fn query_rects<'py>(
+        &self,
+        py: Python<'py>,
+        rects: PyReadonlyArray2<f64>,
+    ) -> PyResult<(Ids<'py>, Ids<'py>)> {
+        let (offsets, ids) = self.query_rows(rects.as_array())?;
+        Ok((PyArray1::from_vec(py, offsets), PyArray1::from_vec(py, ids)))
+    }
+}
+
+/// The `kdtree_collisions` Python module.
+#[pymodule]
+fn kdtree_collisions(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyKdTree>()
+}
+
+#[cfg(test)]
+mod tests {
+    use numpy::ndarray::{arr1, arr2, Array2};
+
+    use super::PyKdTree;
+
+    #[test]
+    fn python_bulk_methods() {
+        let mut tree = PyKdTree::new();
+        let ids = arr1(&[1u64, 2, 3, 4]);
+        let boxes = arr2(&[
+            [0., 0., 1., 1.],
+            [2., 0., 3., 1.],
+            [0., 5., 1., 6.],
+            [8., 8., 9., 9.],
+        ]);
+        tree.insert_rows(ids.view(), boxes.view()).unwrap();
+        assert!(tree
+            .insert_rows(ids.view(), Array2::zeros((4, 3)).view())
+            .is_err());
+        tree.insert(4, 2.5, 0.5, 2.6, 0.6);
+        assert_eq!(tree.__len__(), 4);
+        let rects = arr2(&[[0., 0., 3., 1.], [100., 100., 101., 101.], [0., 4., 1., 5.]]);
+        let (offsets, mut ids) = tree.query_rows(rects.view()).unwrap();
+        assert_eq!(offsets, vec![0, 3, 3, 4]);
+        ids[..3].sort_unstable();
+        assert_eq!(ids, vec![1, 2, 4, 3]);
+        assert!(tree.remove(2));
+        assert!(!tree.remove(2));
+        assert_eq!(tree.query_rows(rects.view()).unwrap().0, vec![0, 2, 2, 3]);
+    }
+}
@@ -0,0 +1,244 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Aabb, KdValue, Scalar};
+
+/// A loose quadtree: every cell accepts values whose center it contains, as long
+/// as they are no bigger than the cell, so its bounds are loosened by half a cell
+/// on every side.
+///
+/// The depth of a value only depends on its size, which keeps the tree shallow
+/// around big values and deep around small ones, and never requires splitting or
+/// merging cells as values move. Values centered outside of the root bounds are
+/// kept at the root.
+#[derive(Debug)]
+pub struct LooseQuadtree<Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    nodes: Vec<QuadNode<Value>>,
+    max_depth: usize,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct QuadNode<Value: KdValue> {
+    cell: Aabb<Value::Position>,
+    values: Vec<Value>,
+    //index of the first of the 4 children, allocated together
+    children: Option<usize>,
+}
+
+impl<Value: KdValue> LooseQuadtree<Value>
+where
+    Value::Position: Scalar,
+{
+    /// Creates a tree covering `bounds`, whose cells are split at most `max_depth`
+    /// times.
+    pub fn new(bounds: Aabb<Value::Position>, max_depth: usize) -> Self {
+        Self {
+            nodes: vec![QuadNode {
+                cell: bounds,
+                values: Vec::new(),
+                children: None,
+            }],
+            max_depth,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        let node = self.node_for(&value, true);
+        self.nodes[node].values.push(value);
+        self.len += 1;
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        let node = self.node_for(&value, false);
+        let values = &mut self.nodes[node].values;
+        match values.iter().position(|v| *v == value) {
+            Some(index) => {
+                values.swap_remove(index);
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&Value, &Value)) {
+        for value in self.nodes.iter().flat_map(|node| &node.values) {
+            let query = self.query_rect(value.min_x(), value.max_x(), value.min_y(), value.max_y());
+            for other in query {
+                //each pair is found from both sides, only keep one of them
+                if (value as *const Value) < (other as *const Value) {
+                    f(value, other)
+                }
+            }
+        }
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> LooseRectQuery<'_, Value> {
+        LooseRectQuery {
+            tree: self,
+            stack: vec![0],
+            values: [].iter(),
+            rect: Aabb::new(min_x, max_x, min_y, max_y),
+        }
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> LooseRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    //walks down to the cell where the value belongs, creating the missing ones
+    //when inserting, and stopping at the deepest existing one otherwise
+    fn node_for(&mut self, value: &Value, create: bool) -> usize {
+        let two = Value::Position::from_f64(2.);
+        let center_x = (value.min_x() + value.max_x()) / two;
+        let center_y = (value.min_y() + value.max_y()) / two;
+        let size = max(value.max_x() - value.min_x(), value.max_y() - value.min_y());
+        let mut node = 0;
+        if !self.nodes[0].cell.contains_point(&center_x, &center_y) {
+            return 0;
+        }
+        for _ in 0..self.max_depth {
+            let cell = self.nodes[node].cell;
+            let half_width = (cell.max_x - cell.min_x) / two;
+            let half_height = (cell.max_y - cell.min_y) / two;
+            if size > half_width || size > half_height {
+                break;
+            }
+            let first = match self.nodes[node].children {
+                Some(first) => first,
+                None if create => self.split(node),
+                None => break,
+            };
+            let right = center_x >= cell.min_x + half_width;
+            let top = center_y >= cell.min_y + half_height;
+            node = first + right as usize + 2 * top as usize;
+        }
+        node
+    }
+
+    fn split(&mut self, node: usize) -> usize {
+        let cell = self.nodes[node].cell;
+        let two = Value::Position::from_f64(2.);
+        let middle_x = cell.min_x + (cell.max_x - cell.min_x) / two;
+        let middle_y = cell.min_y + (cell.max_y - cell.min_y) / two;
+        let first = self.nodes.len();
+        for (min_y, max_y) in [(cell.min_y, middle_y), (middle_y, cell.max_y)] {
+            for (min_x, max_x) in [(cell.min_x, middle_x), (middle_x, cell.max_x)] {
+                self.nodes.push(QuadNode {
+                    cell: Aabb::new(min_x, max_x, min_y, max_y),
+                    values: Vec::new(),
+                    children: None,
+                });
+            }
+        }
+        self.nodes[node].children = Some(first);
+        first
+    }
+}
+
+fn max<P: PartialOrd>(a: P, b: P) -> P {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+/// The values of a `LooseQuadtree` overlapping a rectangle.
+#[derive(Debug)]
+pub struct LooseRectQuery<'a, Value: KdValue>
+where
+    Value::Position: Scalar,
+{
+    tree: &'a LooseQuadtree<Value>,
+    stack: Vec<usize>,
+    values: core::slice::Iter<'a, Value>,
+    rect: Aabb<Value::Position>,
+}
+
+impl<'a, Value: KdValue> Iterator for LooseRectQuery<'a, Value>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for value in self.values.by_ref() {
+                if Aabb::of(value).overlaps(&self.rect) {
+                    return Some(value);
+                }
+            }
+            let node = &self.tree.nodes[self.stack.pop()?];
+            if let Some(first) = node.children {
+                for child in first..first + 4 {
+                    let cell = self.tree.nodes[child].cell;
+                    let two = Value::Position::from_f64(2.);
+                    let (half_width, half_height) = (
+                        (cell.max_x - cell.min_x) / two,
+                        (cell.max_y - cell.min_y) / two,
+                    );
+                    let loose = Aabb::new(
+                        cell.min_x - half_width,
+                        cell.max_x + half_width,
+                        cell.min_y - half_height,
+                        cell.max_y + half_height,
+                    );
+                    if loose.overlaps(&self.rect) {
+                        self.stack.push(child);
+                    }
+                }
+            }
+            self.values = node.values.iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LooseQuadtree;
+    use crate::{tests::TestValue, Aabb, KdTree};
+
+    #[test]
+    fn loose_quadtree() {
+        let mut quadtree = LooseQuadtree::<TestValue>::new(Aabb::new(0., 64., 0., 64.), 6);
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let (x, y) = ((i * 17 % 70) as f32 - 3., (i * 29 % 67) as f32 - 2.);
+            let size = [0.5, 2., 9., 40.][i % 4];
+            let value = TestValue::new(x, x + size, y, y + size);
+            quadtree.insert(value.clone());
+            tree.insert(value);
+        }
+        for (min_x, max_x, min_y, max_y) in
+            [(10., 20., 30., 35.), (63., 80., -5., 2.), (5., 5., 5., 5.)]
+        {
+            assert_eq!(
+                quadtree.query_rect(min_x, max_x, min_y, max_y).count(),
+                tree.query_rect(min_x, max_x, min_y, max_y).count()
+            );
+        }
+        assert!(quadtree.remove_one(TestValue::new(14., 16., 27., 29.)));
+        assert!(!quadtree.remove_one(TestValue::new(14., 16., 27., 29.)));
+        assert_eq!(quadtree.len(), 99);
+    }
+}
@@ -0,0 +1,284 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    scalar::{ceil, floor},
+    KdTree, KdValue, Scalar,
+};
+
+const QUANTIZATION_STEPS: f64 = u16::MAX as f64;
+
+/// A read-only tree storing the bounds of its values as 16 bits offsets inside
+/// the box of their leaf.
+///
+/// Quantized bounds are rounded outward, so they never reject a value touching the
+/// query. Leaf scans read the compact bounds first, and only check the exact bounds
+/// of the values they let through, so queries return the same values as `KdTree`.
+///
+/// The values are kept whole next to their quantized bounds, so this takes 8 more
+/// bytes per value than the `KdTree` it was built from, not less.
+#[derive(Debug)]
+pub enum QuantizedKdTree<Value: KdValue> {
+    Leaf(QuantizedLeaf<Value>),
+    Node(Box<QuantizedNode<Value>>),
+}
+
+#[derive(Debug)]
+pub struct QuantizedLeaf<Value: KdValue> {
+    origin: [f64; 2],
+    step: [f64; 2],
+    //[min_x, max_x, min_y, max_y] for each value
+    bounds: Vec<[u16; 4]>,
+    values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct QuantizedNode<Value: KdValue> {
+    vertical: bool,
+    median: Value::Position,
+    left_max: Value::Position,
+    left: QuantizedKdTree<Value>,
+    right: QuantizedKdTree<Value>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    pub fn into_quantized(self) -> QuantizedKdTree<Value> {
+        match self {
+            KdTree::Leaf(leaf, ..) => QuantizedKdTree::Leaf(QuantizedLeaf::new(leaf)),
+            KdTree::Node(node) => {
+                let node = *node;
+                QuantizedKdTree::Node(Box::new(QuantizedNode {
+                    vertical: node.vertical,
+                    median: node.median,
+                    left_max: node.left_max,
+                    left: node.left.into_quantized(),
+                    right: node.right.into_quantized(),
+                }))
+            }
+        }
+    }
+}
+
+impl<Value: KdValue> QuantizedLeaf<Value>
+where
+    Value::Position: Scalar,
+{
+    fn new(values: Vec<Value>) -> Self {
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for value in &values {
+            min[0] = min[0].min(value.min_x().to_f64());
+            min[1] = min[1].min(value.min_y().to_f64());
+            max[0] = max[0].max(value.max_x().to_f64());
+            max[1] = max[1].max(value.max_y().to_f64());
+        }
+        let step = |axis: usize| {
+            let extent = max[axis] - min[axis];
+            if !extent.is_finite() {
+                //unbounded axes collapse to a single cell that matches everything
+                f64::INFINITY
+            } else if extent > 0. {
+                extent / QUANTIZATION_STEPS
+            } else {
+                1.
+            }
+        };
+        let mut leaf = Self {
+            origin: [
+                if min[0].is_finite() { min[0] } else { 0. },
+                if min[1].is_finite() { min[1] } else { 0. },
+            ],
+            step: [step(0), step(1)],
+            bounds: Vec::with_capacity(values.len()),
+            values: Vec::new(),
+        };
+        for value in &values {
+            let bounds = [
+                leaf.quantize_down(value.min_x().to_f64(), 0),
+                leaf.quantize_up(value.max_x().to_f64(), 0),
+                leaf.quantize_down(value.min_y().to_f64(), 1),
+                leaf.quantize_up(value.max_y().to_f64(), 1),
+            ];
+            leaf.bounds.push(bounds);
+        }
+        leaf.values = values;
+        leaf
+    }
+
+    //whether the box covered by the quantization grid touches the query
+    fn touches(&self, min: [f64; 2], max: [f64; 2]) -> bool {
+        (0..2).all(|axis| {
+            self.step[axis].is_infinite()
+                || !(max[axis] < self.origin[axis]
+                    || min[axis] > self.origin[axis] + self.step[axis] * QUANTIZATION_STEPS)
+        })
+    }
+
+    fn quantize_down(&self, position: f64, axis: usize) -> u16 {
+        if self.step[axis].is_infinite() {
+            return 0;
+        }
+        let steps = floor((position - self.origin[axis]) / self.step[axis]);
+        //NaN saturates to 0, which keeps the value reachable
+        steps.clamp(0., QUANTIZATION_STEPS) as u16
+    }
+
+    fn quantize_up(&self, position: f64, axis: usize) -> u16 {
+        let steps = ceil((position - self.origin[axis]) / self.step[axis]);
+        if steps.is_nan() || self.step[axis].is_infinite() {
+            return u16::MAX;
+        }
+        steps.clamp(0., QUANTIZATION_STEPS) as u16
+    }
+}
+
+impl<Value: KdValue> QuantizedKdTree<Value>
+where
+    Value::Position: Scalar,
+{
+    pub fn len(&self) -> usize {
+        match self {
+            QuantizedKdTree::Leaf(leaf) => leaf.values.len(),
+            QuantizedKdTree::Node(node) => node.left.len() + node.right.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> QuantizedRectQuery<'_, Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> QuantizedRectQuery<'_, Value> {
+        QuantizedRectQuery {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            queue: vec![self],
+            items_to_yield: Vec::new(),
+        }
+    }
+}
+
+pub struct QuantizedRectQuery<'a, Value: KdValue> {
+    min_x: Value::Position,
+    max_x: Value::Position,
+    min_y: Value::Position,
+    max_y: Value::Position,
+    queue: Vec<&'a QuantizedKdTree<Value>>,
+    items_to_yield: Vec<&'a Value>,
+}
+
+impl<'a, Value: KdValue> Iterator for QuantizedRectQuery<'a, Value>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items_to_yield.pop() {
+                return Some(item);
+            }
+            match self.queue.pop()? {
+                QuantizedKdTree::Leaf(leaf) => {
+                    if !leaf.touches(
+                        [self.min_x.to_f64(), self.min_y.to_f64()],
+                        [self.max_x.to_f64(), self.max_y.to_f64()],
+                    ) {
+                        continue;
+                    }
+                    //the query is rounded outward too, so the comparisons stay conservative
+                    let query = [
+                        leaf.quantize_down(self.min_x.to_f64(), 0),
+                        leaf.quantize_up(self.max_x.to_f64(), 0),
+                        leaf.quantize_down(self.min_y.to_f64(), 1),
+                        leaf.quantize_up(self.max_y.to_f64(), 1),
+                    ];
+                    for (bounds, value) in leaf.bounds.iter().zip(&leaf.values) {
+                        if (bounds[0] <= query[1])
+                            & (query[0] <= bounds[1])
+                            & (bounds[2] <= query[3])
+                            & (query[2] <= bounds[3])
+                            //same test as KdTree, so that NaN bounds match the same way
+                            && !(value.min_x() > self.max_x
+                                || self.min_x > value.max_x()
+                                || value.min_y() > self.max_y
+                                || self.min_y > value.max_y())
+                        {
+                            self.items_to_yield.push(value);
+                        }
+                    }
+                }
+                QuantizedKdTree::Node(node) => {
+                    let (min, max) = if node.vertical {
+                        (&self.min_y, &self.max_y)
+                    } else {
+                        (&self.min_x, &self.max_x)
+                    };
+                    if *min <= node.left_max {
+                        self.queue.push(&node.left)
+                    }
+                    if *max >= node.median {
+                        self.queue.push(&node.right)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn quantized() {
+        let mut tree = KdTree::<TestValue, 8>::default();
+        let mut values = Vec::new();
+        for i in 0..300 {
+            let x = (i * 31 % 97) as f32 * 0.37;
+            let y = (i * 17 % 89) as f32 * 0.61;
+            let value = TestValue::new(x, x + 1.3, y, y + 0.4);
+            tree.insert(value.clone());
+            values.push(value);
+        }
+        let tree = tree.into_quantized();
+        assert_eq!(tree.len(), 300);
+        let found: Vec<_> = tree.query_rect(5., 12., 8., 20.).collect();
+        let touching =
+            |v: &TestValue| v.min_x <= 12. && v.max_x >= 5. && v.min_y <= 20. && v.max_y >= 8.;
+        //conservative: every real match is found
+        let expected = values.iter().filter(|v| touching(v)).count();
+        assert!(found.iter().all(|v| touching(v)));
+        assert_eq!(found.len(), expected);
+    }
+
+    #[test]
+    fn infinite_bounds() {
+        let mut tree = KdTree::<TestValue, 8>::default();
+        tree.insert(TestValue::new(f32::NEG_INFINITY, 5., 0., 1.));
+        tree.insert(TestValue::new(10., f32::INFINITY, 0., 1.));
+        tree.insert(TestValue::new(0., 1., 0., 1.));
+        let tree = tree.into_quantized();
+        assert_eq!(tree.query_rect(-100., -50., 0., 1.).count(), 1);
+        assert_eq!(tree.query_rect(1000., 2000., 0., 1.).count(), 1);
+        assert_eq!(tree.query_rect(0.5, 0.5, 0., 1.).count(), 2);
+        assert_eq!(tree.query_rect(6., 7., 0., 1.).count(), 0);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Aabb, Fat, KdTree, KdValue, PayloadCell, Scalar};
+
+/// Values that can be moved by an offset.
+pub trait Translate: KdValue {
+    fn translate(&mut self, dx: Self::Position, dy: Self::Position);
+}
+
+impl<P: Scalar> Translate for Aabb<P> {
+    fn translate(&mut self, dx: P, dy: P) {
+        self.min_x = self.min_x + dx;
+        self.max_x = self.max_x + dx;
+        self.min_y = self.min_y + dy;
+        self.max_y = self.max_y + dy;
+    }
+}
+
+impl<Value: Default + Clone + core::fmt::Debug + PartialEq, P: Scalar> Translate for Fat<Value, P> {
+    fn translate(&mut self, dx: P, dy: P) {
+        self.bounds.translate(dx, dy)
+    }
+}
+
+impl<Bounds: Translate, T: Default + Clone + core::fmt::Debug + PartialEq> Translate
+    for PayloadCell<Bounds, T>
+{
+    fn translate(&mut self, dx: Bounds::Position, dy: Bounds::Position) {
+        self.bounds.translate(dx, dy)
+    }
+}
+
+impl<Value: Translate, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Re-expresses every value relative to a new origin, placed at
+    /// `(origin_x, origin_y)` in the current coordinates, to keep precision in
+    /// large f32 worlds.
+    ///
+    /// The tree is rebuilt, as rounding may change how values compare to the
+    /// splits.
+    pub fn rebase(&mut self, origin_x: Value::Position, origin_y: Value::Position) {
+        let zero = Value::Position::default();
+        let mut values = core::mem::take(self).into_values();
+        for value in &mut values {
+            value.translate(zero - origin_x, zero - origin_y);
+        }
+        *self = Self::build(values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn rebase() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..20 {
+            let x = 1_000_000. + i as f32 * 4.;
+            tree.insert(Aabb::new(x, x + 2., -5. + i as f32, 5.));
+        }
+        tree.rebase(1_000_000., 0.);
+        assert_eq!(tree.len(), 20);
+        assert_eq!(
+            tree.query_point(41., 5.).collect::<Vec<_>>(),
+            vec![&Aabb::new(40., 42., 5., 5.)]
+        );
+        assert!(tree.remove_one(Aabb::new(0., 2., -5., 5.)));
+    }
+}
@@ -0,0 +1,70 @@
+use rstar::{RTree, RTreeNum, RTreeObject, AABB};
+
+use crate::{KdTree, KdValue};
+
+/// A value usable in an rstar `RTree`, with its box as envelope.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RStarValue<Value>(pub Value);
+
+impl<Value: KdValue> RTreeObject for RStarValue<Value>
+where
+    Value::Position: RTreeNum,
+{
+    type Envelope = AABB<[Value::Position; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(
+            [self.0.min_x(), self.0.min_y()],
+            [self.0.max_x(), self.0.max_y()],
+        )
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: RTreeNum,
+{
+    /// Bulk-loads an rstar `RTree` with copies of the values.
+    pub fn to_rtree(&self) -> RTree<RStarValue<Value>> {
+        RTree::bulk_load(self.iter().cloned().map(RStarValue).collect())
+    }
+
+    /// Bulk-loads an rstar `RTree` with the values.
+    pub fn into_rtree(self) -> RTree<RStarValue<Value>> {
+        RTree::bulk_load(self.into_values().into_iter().map(RStarValue).collect())
+    }
+
+    /// Builds a tree of the values of an rstar `RTree`.
+    pub fn from_rtree(tree: RTree<RStarValue<Value>>) -> Self {
+        Self::build(tree.into_iter().map(|RStarValue(value)| value).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstar::AABB;
+
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn rstar_bridge() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..50 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(Aabb::new(x, x + 3., i as f32, i as f32 + 2.));
+        }
+        let rtree = tree.to_rtree();
+        assert_eq!(rtree.size(), 50);
+        let envelope = AABB::from_corners([10., 5.], [30., 20.]);
+        assert_eq!(
+            rtree.locate_in_envelope_intersecting(&envelope).count(),
+            tree.query_rect(10., 30., 5., 20.).count()
+        );
+        let back = KdTree::<Aabb<f32>, 4>::from_rtree(tree.into_rtree());
+        assert_eq!(back.len(), 50);
+        assert_eq!(
+            back.query_rect(10., 30., 5., 20.).count(),
+            rtree.locate_in_envelope_intersecting(&envelope).count()
+        );
+    }
+}
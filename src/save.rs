@@ -0,0 +1,258 @@
+use std::{
+    cmp::Ordering,
+    io::{self, Read, Write},
+};
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{KdNode, KdTree, KdValue};
+
+const MAGIC: &[u8; 4] = b"KDTS";
+const VERSION: u32 = 2;
+//deeper files are rejected, like binary files
+const MAX_DEPTH: usize = 1024;
+
+//file layout: magic, version: u32 little endian, then the nodes in pre-order, each
+//encoded by bincode as a record
+
+//records are written from references, and read back as owned values
+#[derive(Serialize, Deserialize)]
+enum Record<Value, P> {
+    Split {
+        vertical: bool,
+        median: P,
+        left_max: P,
+    },
+    Leaf(Vec<Value>),
+}
+
+#[derive(Serialize)]
+enum RecordRef<'a, Value, P> {
+    Split {
+        vertical: bool,
+        median: &'a P,
+        left_max: &'a P,
+    },
+    Leaf(&'a [Value]),
+}
+
+//a split whose subtrees are being read
+struct Open<Value: KdValue, const ISLAND_SIZE: usize> {
+    vertical: bool,
+    median: Value::Position,
+    left_max: Value::Position,
+    left: Option<KdTree<Value, ISLAND_SIZE>>,
+}
+
+fn options() -> impl Options {
+    bincode::options()
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value: Serialize + DeserializeOwned,
+    Value::Position: Serialize + DeserializeOwned,
+{
+    /// Writes the tree, structure included, in a compact binary encoding preceded
+    /// by a version header.
+    pub fn save_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let mut stack = vec![self];
+        while let Some(tree) = stack.pop() {
+            let record = match tree {
+                KdTree::Leaf(leaf, ..) => RecordRef::Leaf(leaf),
+                KdTree::Node(node) => {
+                    stack.push(&node.right);
+                    stack.push(&node.left);
+                    RecordRef::Split {
+                        vertical: node.vertical,
+                        median: &node.median,
+                        left_max: &node.left_max,
+                    }
+                }
+            };
+            options()
+                .serialize_into(&mut writer, &record)
+                .map_err(|error| into_io(*error))?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a tree written by `save_to`, without rebuilding it, reading at most
+    /// 4 GiB.
+    pub fn load_from<R: Read>(reader: R) -> io::Result<Self> {
+        Self::load_from_limited(reader, u32::MAX.into())
+    }
+
+    /// Like `load_from`, reading at most `limit` bytes.
+    ///
+    /// Files which are too big, too deep or whose values are not where their
+    /// splits place them are rejected.
+    pub fn load_from_limited<R: Read>(reader: R, limit: u64) -> io::Result<Self> {
+        let mut reader = reader.take(limit);
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a saved kd-tree"));
+        }
+        if header[4..] != VERSION.to_le_bytes() {
+            return Err(invalid("unsupported saved kd-tree version"));
+        }
+        //nodes are read with an explicit stack of the open splits, so that a
+        //crafted file can't overflow the call stack
+        let mut splits: Vec<Open<Value, ISLAND_SIZE>> = Vec::new();
+        loop {
+            let record = options()
+                .with_limit(limit)
+                .deserialize_from(&mut reader)
+                .map_err(|error| into_io(*error))?;
+            let leaf = match record {
+                Record::Leaf(leaf) => leaf,
+                Record::Split {
+                    vertical,
+                    median,
+                    left_max,
+                } => {
+                    if splits.len() == MAX_DEPTH {
+                        return Err(invalid("saved kd-tree too deep"));
+                    }
+                    splits.push(Open {
+                        vertical,
+                        median,
+                        left_max,
+                        left: None,
+                    });
+                    continue;
+                }
+            };
+            //the values must be where the splits place them, as queries rely on it
+            for value in &leaf {
+                if splits.iter().any(|split| misplaced(split, value)) {
+                    return Err(invalid("corrupted saved kd-tree"));
+                }
+            }
+            let mut subtree = KdTree::leaf(leaf);
+            //closes every split whose right subtree was just completed
+            loop {
+                let split = match splits.last_mut() {
+                    Some(split) => split,
+                    None => return Ok(subtree),
+                };
+                if split.left.is_none() {
+                    split.left = Some(subtree);
+                    break;
+                }
+                let split = splits.pop().unwrap();
+                subtree = KdTree::Node(Box::new(KdNode {
+                    vertical: split.vertical,
+                    median: split.median,
+                    left_max: split.left_max,
+                    left: split.left.unwrap(),
+                    right: subtree,
+                }));
+            }
+        }
+    }
+}
+
+//whether the value is known to be on the wrong side of the split, the left one
+//while it is being read
+fn misplaced<Value: KdValue, const ISLAND_SIZE: usize>(
+    split: &Open<Value, ISLAND_SIZE>,
+    value: &Value,
+) -> bool {
+    let (min, max) = if split.vertical {
+        (value.min_y(), value.max_y())
+    } else {
+        (value.min_x(), value.max_x())
+    };
+    if split.left.is_none() {
+        matches!(
+            min.partial_cmp(&split.median),
+            Some(Ordering::Greater | Ordering::Equal)
+        ) || max.partial_cmp(&split.left_max) == Some(Ordering::Greater)
+    } else {
+        min.partial_cmp(&split.median) == Some(Ordering::Less)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn into_io(error: bincode::ErrorKind) -> io::Error {
+    match error {
+        bincode::ErrorKind::Io(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode::Options;
+
+    use super::Record;
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn save_and_load() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..50 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(Aabb::new(x, x + 3., i as f32, i as f32 + 2.));
+        }
+        let mut file = Vec::new();
+        tree.save_to(&mut file).unwrap();
+        let loaded = KdTree::<Aabb<f32>, 4>::load_from(&file[..]).unwrap();
+        assert_eq!(loaded.len(), 50);
+        assert_eq!(
+            loaded.query_rect(10., 30., 5., 20.).count(),
+            tree.query_rect(10., 30., 5., 20.).count()
+        );
+        file[4] = 3;
+        assert!(KdTree::<Aabb<f32>, 4>::load_from(&file[..]).is_err());
+        assert!(KdTree::<Aabb<f32>, 4>::load_from(&file[..6]).is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..50 {
+            let x = i as f32;
+            tree.insert(Aabb::new(x, x + 1., 0., 1.));
+        }
+        let mut file = Vec::new();
+        tree.save_to(&mut file).unwrap();
+        assert!(KdTree::<Aabb<f32>, 4>::load_from_limited(&file[..], file.len() as u64).is_ok());
+        assert!(KdTree::<Aabb<f32>, 4>::load_from_limited(&file[..], 100).is_err());
+        //a chain of splits deeper than any tree
+        let mut deep = file[..8].to_vec();
+        for _ in 0..2000 {
+            deep.extend(
+                bincode::options()
+                    .serialize(&Record::<Aabb<f32>, f32>::Split {
+                        vertical: false,
+                        median: 0.,
+                        left_max: 0.,
+                    })
+                    .unwrap(),
+            );
+        }
+        assert!(KdTree::<Aabb<f32>, 4>::load_from(&deep[..]).is_err());
+        //a value on the wrong side of its split
+        let mut misplaced = file[..8].to_vec();
+        let split = Record::<Aabb<f32>, f32>::Split {
+            vertical: false,
+            median: 10.,
+            left_max: 20.,
+        };
+        misplaced.extend(bincode::options().serialize(&split).unwrap());
+        for x in [15., 12.] {
+            let leaf = Record::<Aabb<f32>, f32>::Leaf(vec![Aabb::new(x, x + 1., 0., 1.)]);
+            misplaced.extend(bincode::options().serialize(&leaf).unwrap());
+        }
+        assert!(KdTree::<Aabb<f32>, 4>::load_from(&misplaced[..]).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use core::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Sub},
+};
+
+/// Positions that support arithmetic, needed by the features that compute
+/// with coordinates rather than just comparing them.
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + Debug
+    + Default
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                #[inline(always)]
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+                #[inline(always)]
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(f32, f64, i16, i32, i64, isize);
+
+//float rounding lives in std, without it this is enough for bucketing coordinates
+#[cfg(feature = "std")]
+pub(crate) fn floor(value: f64) -> f64 {
+    value.floor()
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn floor(value: f64) -> f64 {
+    //from 2^52 on every float is an integer, NaN and infinities are kept as is
+    if value.is_nan() || value.abs() >= 4_503_599_627_370_496. {
+        return value;
+    }
+    let truncated = value as i64 as f64;
+    if truncated > value {
+        truncated - 1.
+    } else {
+        truncated
+    }
+}
+
+pub(crate) fn ceil(value: f64) -> f64 {
+    -floor(-value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ceil, floor};
+
+    #[test]
+    fn rounding() {
+        assert_eq!(floor(2.5), 2.);
+        assert_eq!(floor(-2.5), -3.);
+        assert_eq!(floor(-3.), -3.);
+        assert_eq!(ceil(2.1), 3.);
+        assert_eq!(ceil(-2.9), -2.);
+        assert_eq!(floor(1e300), 1e300);
+        assert!(floor(f64::NAN).is_nan());
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Aabb, KdTree, KdValue, Scalar};
+
+/// The smallest offset moving `a` out of `b`, along a single axis, or `None` if
+/// they do not overlap (touching boxes do not need separating).
+pub fn pair_separation<P: Scalar>(a: &Aabb<P>, b: &Aabb<P>) -> Option<(P, P)> {
+    let min = |a: P, b: P| if b < a { b } else { a };
+    let max = |a: P, b: P| if b > a { b } else { a };
+    let zero = P::default();
+    let overlap_x = min(a.max_x, b.max_x) - max(a.min_x, b.min_x);
+    let overlap_y = min(a.max_y, b.max_y) - max(a.min_y, b.min_y);
+    if !(overlap_x > zero && overlap_y > zero) {
+        return None;
+    }
+    //pushes along the axis with the least penetration, away from the other center
+    Some(if overlap_x < overlap_y {
+        let left = a.min_x + a.max_x < b.min_x + b.max_x;
+        (if left { zero - overlap_x } else { overlap_x }, zero)
+    } else {
+        let below = a.min_y + a.max_y < b.min_y + b.max_y;
+        (zero, if below { zero - overlap_y } else { overlap_y })
+    })
+}
+
+/// Iteratively pushes overlapping boxes apart, and returns the total offset of
+/// each one.
+///
+/// Each iteration finds the overlapping pairs, and splits the separation of every
+/// pair between its two boxes, or gives all of it to the movable one if the other
+/// one is static. More iterations resolve crowded areas better.
+pub fn separate<P: Scalar>(
+    boxes: &[Aabb<P>],
+    is_static: impl Fn(usize) -> bool,
+    iterations: usize,
+) -> Vec<(P, P)> {
+    let zero = P::default();
+    let two = P::from_f64(2.);
+    let mut offsets = vec![(zero, zero); boxes.len()];
+    let mut bodies: Vec<Body<P>> = boxes
+        .iter()
+        .enumerate()
+        .map(|(index, bounds)| Body {
+            index,
+            bounds: *bounds,
+        })
+        .collect();
+    for _ in 0..iterations {
+        let tree = KdTree::<Body<P>, 16>::build(bodies.clone());
+        let mut moves = vec![(zero, zero); boxes.len()];
+        let mut moved = false;
+        tree.for_each_pair(|a, b| {
+            let (dx, dy) = match pair_separation(&a.bounds, &b.bounds) {
+                Some(offset) => offset,
+                None => return,
+            };
+            let (a_static, b_static) = (is_static(a.index), is_static(b.index));
+            let (a_share, b_share) = match (a_static, b_static) {
+                (true, true) => return,
+                (false, true) => ((dx, dy), (zero, zero)),
+                (true, false) => ((zero, zero), (zero - dx, zero - dy)),
+                (false, false) => ((dx / two, dy / two), (zero - dx / two, zero - dy / two)),
+            };
+            for (index, (dx, dy)) in [(a.index, a_share), (b.index, b_share)] {
+                moves[index].0 = moves[index].0 + dx;
+                moves[index].1 = moves[index].1 + dy;
+            }
+            moved = true;
+        });
+        if !moved {
+            break;
+        }
+        for body in &mut bodies {
+            let (dx, dy) = moves[body.index];
+            body.bounds = Aabb::new(
+                body.bounds.min_x + dx,
+                body.bounds.max_x + dx,
+                body.bounds.min_y + dy,
+                body.bounds.max_y + dy,
+            );
+            offsets[body.index] = (offsets[body.index].0 + dx, offsets[body.index].1 + dy);
+        }
+    }
+    offsets
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Body<P> {
+    index: usize,
+    bounds: Aabb<P>,
+}
+
+impl<P: Scalar> KdValue for Body<P> {
+    type Position = P;
+
+    fn min_x(&self) -> P {
+        self.bounds.min_x
+    }
+
+    fn min_y(&self) -> P {
+        self.bounds.min_y
+    }
+
+    fn max_x(&self) -> P {
+        self.bounds.max_x
+    }
+
+    fn max_y(&self) -> P {
+        self.bounds.max_y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pair_separation, separate};
+    use crate::Aabb;
+
+    #[test]
+    fn separation() {
+        let a = Aabb::new(0., 2., 0., 2.);
+        assert_eq!(
+            pair_separation(&a, &Aabb::new(1.5, 3.5, 0., 2.)),
+            Some((-0.5, 0.))
+        );
+        assert_eq!(pair_separation(&a, &Aabb::new(2., 4., 0., 2.)), None);
+        let floor: Aabb<f32> = Aabb::new(-10., 10., -1., 0.);
+        let boxes = [
+            floor,
+            Aabb::new(0., 1., -0.25, 0.75),
+            Aabb::new(0.5, 1.5, 0.5, 1.5),
+        ];
+        let offsets = separate(&boxes, |index| index == 0, 20);
+        assert_eq!(offsets[0], (0., 0.));
+        let moved: Vec<_> = boxes
+            .iter()
+            .zip(&offsets)
+            .map(|(b, (dx, dy))| Aabb::new(b.min_x + dx, b.max_x + dx, b.min_y + dy, b.max_y + dy))
+            .collect();
+        for (i, a) in moved.iter().enumerate() {
+            for b in &moved[i + 1..] {
+                //each iteration only halves the remaining penetration of some pairs
+                let (dx, dy) = pair_separation(a, b).unwrap_or((0., 0.));
+                assert!(dx.abs() + dy.abs() < 1e-3);
+            }
+        }
+    }
+}
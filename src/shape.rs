@@ -0,0 +1,234 @@
+use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{Aabb, KdTree, KdValue};
+
+/// A query region the tree can be searched with, beyond plain rectangles and
+/// points.
+///
+/// Every hit has a cost, like the time of impact of a moving shape, or 0 for
+/// plain overlap shapes: `query_shape` yields all the hits, and `cast_shape` the
+/// cheapest one.
+pub trait QueryShape<P> {
+    /// Tests the box of a value: `None` if the shape misses it, or else the cost
+    /// of the hit.
+    fn hit(&self, bounds: &Aabb<P>) -> Option<f64>;
+
+    /// Tests the half plane below `at` (if `below`) or from `at` on the y axis if
+    /// `vertical`, or else on the x axis: `None` if the shape cannot hit anything
+    /// there, or else a lower bound of the cost of its hits there.
+    fn reach(&self, vertical: bool, at: &P, below: bool) -> Option<f64>;
+}
+
+impl<P: PartialOrd + Clone> QueryShape<P> for Aabb<P> {
+    fn hit(&self, bounds: &Aabb<P>) -> Option<f64> {
+        self.overlaps(bounds).then_some(0.)
+    }
+
+    fn reach(&self, vertical: bool, at: &P, below: bool) -> Option<f64> {
+        let (min, max) = if vertical {
+            (&self.min_y, &self.max_y)
+        } else {
+            (&self.min_x, &self.max_x)
+        };
+        let reached = if below { min <= at } else { max >= at };
+        reached.then_some(0.)
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// The values hit by `shape`, in no particular order.
+    pub fn query_shape<'a, Shape: QueryShape<Value::Position>>(
+        &'a self,
+        shape: &'a Shape,
+    ) -> ShapeQuery<'a, Value, Shape, ISLAND_SIZE> {
+        ShapeQuery {
+            shape,
+            queue: vec![self],
+            items_to_yield: Vec::new(),
+        }
+    }
+
+    /// The cheapest hit of `shape` and its cost, visiting the subtrees from the
+    /// cheapest lower bound and stopping as soon as no subtree can beat the best
+    /// hit.
+    pub fn cast_shape<Shape: QueryShape<Value::Position>>(
+        &self,
+        shape: &Shape,
+    ) -> Option<(f64, &Value)> {
+        let mut best: Option<(f64, &Value)> = None;
+        let mut heap = BinaryHeap::new();
+        heap.push(Candidate {
+            bound: 0.,
+            tree: self,
+        });
+        while let Some(Candidate { bound, tree }) = heap.pop() {
+            if best.is_some_and(|(cost, _)| cost <= bound) {
+                break;
+            }
+            match tree {
+                KdTree::Leaf(leaves, ..) => {
+                    for value in leaves {
+                        if let Some(cost) = shape.hit(&Aabb::of(value)) {
+                            if best.is_none_or(|(best, _)| cost < best) {
+                                best = Some((cost, value));
+                            }
+                        }
+                    }
+                }
+                KdTree::Node(node) => {
+                    let left = shape.reach(node.vertical, &node.left_max, true);
+                    let right = shape.reach(node.vertical, &node.median, false);
+                    for (reach, tree) in [(left, &node.left), (right, &node.right)] {
+                        if let Some(reach) = reach {
+                            heap.push(Candidate {
+                                bound: reach.max(bound),
+                                tree,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+//a subtree to visit in cast_shape, the cheapest bound first
+struct Candidate<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    bound: f64,
+    tree: &'a KdTree<Value, ISLAND_SIZE>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PartialEq for Candidate<'_, Value, ISLAND_SIZE> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Eq for Candidate<'_, Value, ISLAND_SIZE> {}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PartialOrd for Candidate<'_, Value, ISLAND_SIZE> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Ord for Candidate<'_, Value, ISLAND_SIZE> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed, as the heap pops the greatest
+        other.bound.total_cmp(&self.bound)
+    }
+}
+
+/// The values hit by a custom shape.
+pub struct ShapeQuery<'a, Value: KdValue, Shape, const ISLAND_SIZE: usize> {
+    shape: &'a Shape,
+    queue: Vec<&'a KdTree<Value, ISLAND_SIZE>>,
+    items_to_yield: Vec<&'a Value>,
+}
+
+impl<'a, Value: KdValue, Shape: QueryShape<Value::Position>, const ISLAND_SIZE: usize> Iterator
+    for ShapeQuery<'a, Value, Shape, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.items_to_yield.pop();
+            if item.is_some() {
+                return item;
+            }
+            match self.queue.pop()? {
+                KdTree::Leaf(leaves, ..) => {
+                    for value in leaves {
+                        if self.shape.hit(&Aabb::of(value)).is_some() {
+                            self.items_to_yield.push(value);
+                        }
+                    }
+                }
+                KdTree::Node(node) => {
+                    if self
+                        .shape
+                        .reach(node.vertical, &node.left_max, true)
+                        .is_some()
+                    {
+                        self.queue.push(&node.left)
+                    }
+                    if self
+                        .shape
+                        .reach(node.vertical, &node.median, false)
+                        .is_some()
+                    {
+                        self.queue.push(&node.right)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryShape;
+    use crate::{Aabb, KdTree};
+
+    //a disk, whose hits cost their distance to its center
+    struct Disk {
+        x: f32,
+        y: f32,
+        radius: f32,
+    }
+
+    impl QueryShape<f32> for Disk {
+        fn hit(&self, bounds: &Aabb<f32>) -> Option<f64> {
+            let dx = (bounds.min_x - self.x).max(self.x - bounds.max_x).max(0.);
+            let dy = (bounds.min_y - self.y).max(self.y - bounds.max_y).max(0.);
+            let distance = (dx * dx + dy * dy).sqrt();
+            (distance <= self.radius).then_some(distance as f64)
+        }
+
+        fn reach(&self, vertical: bool, at: &f32, below: bool) -> Option<f64> {
+            let center = if vertical { self.y } else { self.x };
+            let distance = if below { center - at } else { at - center }.max(0.);
+            (distance <= self.radius).then_some(distance as f64)
+        }
+    }
+
+    #[test]
+    fn custom_shapes() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        for i in 0..10 {
+            for j in 0..10 {
+                let (x, y) = (i as f32 * 10., j as f32 * 10. + i as f32 * 0.1);
+                tree.insert(Aabb::new(x, x + 1., y, y + 1.));
+            }
+        }
+        let rect = Aabb::new(15., 42., 5., 33.);
+        assert_eq!(
+            tree.query_shape(&rect).count(),
+            tree.query_rect(15., 42., 5., 33.).count()
+        );
+        let disk = Disk {
+            x: 45.,
+            y: 45.,
+            radius: 10.,
+        };
+        assert_eq!(tree.query_shape(&disk).count(), 4);
+        let (cost, nearest) = tree.cast_shape(&disk).unwrap();
+        assert_eq!(nearest, &Aabb::new(40., 41., 40.4, 41.4));
+        assert!((cost - 5.38).abs() < 1e-2);
+        assert!(tree
+            .cast_shape(&Disk {
+                x: 200.,
+                y: 200.,
+                radius: 5.
+            })
+            .is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::RwLock;
+
+use crate::{Aabb, KdTree, KdValue, Scalar};
+
+/// A tree split in a grid of independently locked shards, so that many threads
+/// can insert and query at once.
+///
+/// Values go to the shard containing their minimum corner (values outside of the
+/// grid go to the closest shard), and each shard tracks the box covering its
+/// values, so queries only lock the shards they can find something in.
+#[derive(Debug)]
+pub struct ShardedKdTree<Value: KdValue, const ISLAND_SIZE: usize>
+where
+    Value::Position: Scalar,
+{
+    bounds: Aabb<Value::Position>,
+    columns: usize,
+    rows: usize,
+    shards: Vec<RwLock<Shard<Value, ISLAND_SIZE>>>,
+}
+
+#[derive(Debug)]
+struct Shard<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    //only grows, so it stays valid when values are removed
+    content: Option<Aabb<Value::Position>>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> ShardedKdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Splits `bounds` in `columns * rows` shards.
+    pub fn new(bounds: Aabb<Value::Position>, columns: usize, rows: usize) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        Self {
+            bounds,
+            columns,
+            rows,
+            shards: (0..columns * rows)
+                .map(|_| {
+                    RwLock::new(Shard {
+                        tree: KdTree::default(),
+                        content: None,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn insert(&self, value: Value) {
+        let mut shard = self.write_shard(&value);
+        let bounds = Aabb::of(&value);
+        shard.content = Some(match &shard.content {
+            Some(content) => content.union(&bounds),
+            None => bounds,
+        });
+        shard.tree.insert(value);
+    }
+
+    pub fn remove_one(&self, value: Value) -> bool {
+        self.write_shard(&value).tree.remove_one(value)
+    }
+
+    pub fn remove_all(&self, value: Value) {
+        self.write_shard(&value).tree.remove_all(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(|e| e.into_inner()).tree.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls `f` on every value overlapping the rectangle, one shard at a time.
+    ///
+    /// The shard is read-locked while `f` runs on its values, so `f` must not
+    /// insert or remove values in this tree: that deadlocks as soon as it writes to
+    /// the shard being read. Use `query_rect`, which returns clones, to do so.
+    pub fn for_each_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        mut f: impl FnMut(&Value),
+    ) {
+        let query = Aabb::new(min_x, max_x, min_y, max_y);
+        for shard in &self.shards {
+            let shard = shard.read().unwrap_or_else(|e| e.into_inner());
+            match &shard.content {
+                Some(content) if content.overlaps(&query) => {}
+                _ => continue,
+            }
+            shard
+                .tree
+                .query_rect(query.min_x, query.max_x, query.min_y, query.max_y)
+                .for_each(&mut f);
+        }
+    }
+
+    /// Like `for_each_in_rect`, with the same restriction on `f`.
+    pub fn for_each_at_point(&self, x: Value::Position, y: Value::Position, f: impl FnMut(&Value)) {
+        self.for_each_in_rect(x, x, y, y, f)
+    }
+
+    /// Clones the values overlapping the rectangle.
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Vec<Value> {
+        let mut values = Vec::new();
+        self.for_each_in_rect(min_x, max_x, min_y, max_y, |value| {
+            values.push(value.clone())
+        });
+        values
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> Vec<Value> {
+        self.query_rect(x, x, y, y)
+    }
+
+    fn write_shard(
+        &self,
+        value: &Value,
+    ) -> std::sync::RwLockWriteGuard<'_, Shard<Value, ISLAND_SIZE>> {
+        let index = self.shard_index(value.min_x(), value.min_y());
+        self.shards[index]
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn shard_index(&self, x: Value::Position, y: Value::Position) -> usize {
+        let cell = |position: Value::Position,
+                    min: Value::Position,
+                    max: Value::Position,
+                    count: usize| {
+            let ratio = (position.to_f64() - min.to_f64()) / (max.to_f64() - min.to_f64());
+            //NaN, out of bounds and empty grids all end up clamped
+            let cell = (ratio * count as f64).floor();
+            if cell >= 1. {
+                (cell as usize).min(count - 1)
+            } else {
+                0
+            }
+        };
+        let column = cell(x, self.bounds.min_x, self.bounds.max_x, self.columns);
+        let row = cell(y, self.bounds.min_y, self.bounds.max_y, self.rows);
+        row * self.columns + column
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedKdTree;
+    use crate::{tests::TestValue, Aabb};
+
+    #[test]
+    fn sharded() {
+        let tree = ShardedKdTree::<TestValue, 4>::new(Aabb::new(0., 100., 0., 100.), 4, 4);
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let tree = &tree;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        let x = (thread * 50 + i) as f32 * 0.5;
+                        tree.insert(TestValue::new(x, x + 10., x, x + 10.));
+                    }
+                });
+            }
+        });
+        assert_eq!(tree.len(), 200);
+        //values spanning several shards are still found from the other ones
+        let found = tree.query_point(30., 30.);
+        assert_eq!(found.len(), 21);
+        assert!(tree.remove_one(TestValue::new(25., 35., 25., 35.)));
+        assert_eq!(tree.query_point(30., 30.).len(), 20);
+        assert_eq!(tree.query_point(-50., 30.).len(), 0);
+    }
+}
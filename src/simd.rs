@@ -0,0 +1,124 @@
+use alloc::vec::Vec;
+
+use crate::{ColumnarBounds, KdValue};
+
+impl<'a, Value: KdValue<Position = f32>> ColumnarBounds<'a, Value> {
+    /// Rows whose bounds overlap the rectangle, tested four at a time with SIMD
+    /// on x86_64, and on wasm32 when built with the `simd128` target feature.
+    ///
+    /// This is the only SIMD path: the leaves of a `KdTree` store the values
+    /// themselves, not columns of bounds, so `KdTree::query_rect` stays scalar.
+    /// Export the bounds once with `export_soa` to scan them all this way.
+    pub fn query_rect(&self, min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Vec<usize> {
+        let query = [min_x, max_x, min_y, max_y];
+        let len = self.len();
+        let mut rows = Vec::new();
+        let mut row = 0;
+        while row + 4 <= len {
+            let mut mask = overlap_mask4(self, row, query);
+            while mask != 0 {
+                rows.push(row + mask.trailing_zeros() as usize);
+                mask &= mask - 1;
+            }
+            row += 4;
+        }
+        for row in row..len {
+            if overlap_mask1(self, row, query) {
+                rows.push(row);
+            }
+        }
+        rows
+    }
+}
+
+fn overlap_mask1<Value: KdValue<Position = f32>>(
+    columns: &ColumnarBounds<'_, Value>,
+    row: usize,
+    query: [f32; 4],
+) -> bool {
+    columns.min_x[row] <= query[1]
+        && query[0] <= columns.max_x[row]
+        && columns.min_y[row] <= query[3]
+        && query[2] <= columns.max_y[row]
+}
+
+#[cfg(target_arch = "x86_64")]
+fn overlap_mask4<Value: KdValue<Position = f32>>(
+    columns: &ColumnarBounds<'_, Value>,
+    row: usize,
+    query: [f32; 4],
+) -> u32 {
+    use core::arch::x86_64::*;
+    let load = |column: &[f32]| {
+        let column = &column[row..row + 4];
+        //sse is always available on x86_64, and the load is unaligned
+        unsafe { _mm_loadu_ps(column.as_ptr()) }
+    };
+    unsafe {
+        let min_x = _mm_cmple_ps(load(&columns.min_x), _mm_set1_ps(query[1]));
+        let max_x = _mm_cmple_ps(_mm_set1_ps(query[0]), load(&columns.max_x));
+        let min_y = _mm_cmple_ps(load(&columns.min_y), _mm_set1_ps(query[3]));
+        let max_y = _mm_cmple_ps(_mm_set1_ps(query[2]), load(&columns.max_y));
+        _mm_movemask_ps(_mm_and_ps(
+            _mm_and_ps(min_x, max_x),
+            _mm_and_ps(min_y, max_y),
+        )) as u32
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn overlap_mask4<Value: KdValue<Position = f32>>(
+    columns: &ColumnarBounds<'_, Value>,
+    row: usize,
+    query: [f32; 4],
+) -> u32 {
+    use core::arch::wasm32::*;
+    let load = |column: &[f32]| {
+        let column = &column[row..row + 4];
+        //wasm loads do not need to be aligned
+        unsafe { v128_load(column.as_ptr() as *const v128) }
+    };
+    let min_x = f32x4_le(load(&columns.min_x), f32x4_splat(query[1]));
+    let max_x = f32x4_le(f32x4_splat(query[0]), load(&columns.max_x));
+    let min_y = f32x4_le(load(&columns.min_y), f32x4_splat(query[3]));
+    let max_y = f32x4_le(f32x4_splat(query[2]), load(&columns.max_y));
+    i32x4_bitmask(v128_and(v128_and(min_x, max_x), v128_and(min_y, max_y))) as u32
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+fn overlap_mask4<Value: KdValue<Position = f32>>(
+    columns: &ColumnarBounds<'_, Value>,
+    row: usize,
+    query: [f32; 4],
+) -> u32 {
+    (0..4).fold(0, |mask, i| {
+        mask | ((overlap_mask1(columns, row + i, query) as u32) << i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn columnar_query() {
+        let mut tree = KdTree::<TestValue, 8>::default();
+        for i in 0..103 {
+            let x = (i * 11 % 37) as f32;
+            let y = (i * 5 % 41) as f32;
+            tree.insert(TestValue::new(x, x + 2., y, y + 3.));
+        }
+        let columns = tree.export_soa();
+        let rows = columns.query_rect(10., 20., 5., 15.);
+        let expected = tree.query_rect(10., 20., 5., 15.).count();
+        assert_eq!(rows.len(), expected);
+        for row in rows {
+            let value = columns.values[row];
+            assert!(value.min_x <= 20. && value.max_x >= 10.);
+            assert!(value.min_y <= 15. && value.max_y >= 5.);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{KdValue, PersistentKdTree, PersistentRectQuery};
+
+/// A tree counting its mutations, handing out frozen snapshots that keep serving
+/// consistent queries while it is being modified.
+///
+/// Snapshots share the unmodified nodes with the tree, so taking one is O(1), and
+/// they can be sent to other threads when the values are `Send + Sync`.
+#[derive(Debug)]
+pub struct VersionedKdTree<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: PersistentKdTree<Value, ISLAND_SIZE>,
+    version: u64,
+}
+
+/// A frozen, read-only view of a `VersionedKdTree`.
+#[derive(Debug)]
+pub struct Snapshot<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: PersistentKdTree<Value, ISLAND_SIZE>,
+    version: u64,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Default for VersionedKdTree<Value, ISLAND_SIZE> {
+    fn default() -> Self {
+        Self {
+            tree: PersistentKdTree::default(),
+            version: 0,
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Clone for Snapshot<Value, ISLAND_SIZE> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            version: self.version,
+        }
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> VersionedKdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    pub fn new(tree: PersistentKdTree<Value, ISLAND_SIZE>) -> Self {
+        Self { tree, version: 0 }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn snapshot(&self) -> Snapshot<Value, ISLAND_SIZE> {
+        Snapshot {
+            tree: self.tree.clone(),
+            version: self.version,
+        }
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.version += 1;
+        self.tree.insert(value)
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        let removed = self.tree.remove_one(value);
+        if removed {
+            self.version += 1;
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.tree.query_point(x, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.tree.query_rect(min_x, max_x, min_y, max_y)
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Snapshot<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// The version of the tree when the snapshot was taken.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.tree.query_point(x, y)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> PersistentRectQuery<'_, Value, ISLAND_SIZE> {
+        self.tree.query_rect(min_x, max_x, min_y, max_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionedKdTree;
+    use crate::tests::TestValue;
+
+    #[test]
+    fn snapshot() {
+        let mut tree = VersionedKdTree::<TestValue, 4>::default();
+        for i in 0..40 {
+            tree.insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+        }
+        let snapshot = tree.snapshot();
+        std::thread::scope(|scope| {
+            let reader = scope.spawn(|| snapshot.query_rect(0., 100., 0., 1.).count());
+            for i in 40..80 {
+                tree.insert(TestValue::new(i as f32, i as f32 + 1., 0., 1.));
+            }
+            assert_eq!(reader.join().unwrap(), 40);
+        });
+        assert_eq!(snapshot.version(), 40);
+        assert_eq!(tree.version(), 80);
+        assert_eq!(snapshot.len(), 40);
+        assert_eq!(tree.query_rect(0., 100., 0., 1.).count(), 80);
+    }
+}
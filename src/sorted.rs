@@ -0,0 +1,259 @@
+use alloc::{collections::BinaryHeap, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{cmp_position, KdTree, KdValue};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    /// The values overlapping the rectangle, by increasing min x.
+    ///
+    /// Subtrees are opened lazily from the smallest min x they can hold, so values
+    /// are yielded as soon as nothing left can come before them, for sweep lines.
+    pub fn query_rect_sorted_x(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> SortedRectQuery<'_, Value, ISLAND_SIZE> {
+        SortedRectQuery::new(self, [min_x, max_x, min_y, max_y], false)
+    }
+
+    /// Like `query_rect_sorted_x`, by increasing min y.
+    pub fn query_rect_sorted_y(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> SortedRectQuery<'_, Value, ISLAND_SIZE> {
+        SortedRectQuery::new(self, [min_x, max_x, min_y, max_y], true)
+    }
+
+    /// The values overlapping the rectangle in an order only depending on the
+    /// contents of the tree, and not on the history of insertions and removals
+    /// which shaped it: by min x, min y, max x, max y, then `tie_break`.
+    ///
+    /// Values sharing their bounds and their `tie_break` key are interchangeable for
+    /// it, so two trees holding the same values always give the same order for them.
+    pub fn query_rect_ordered<K: Ord>(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        tie_break: impl Fn(&Value) -> K,
+    ) -> Vec<&Value> {
+        let mut values: Vec<_> = self.query_rect(min_x, max_x, min_y, max_y).collect();
+        values.sort_by(|a, b| {
+            cmp_position(&a.min_x(), &b.min_x())
+                .then_with(|| cmp_position(&a.min_y(), &b.min_y()))
+                .then_with(|| cmp_position(&a.max_x(), &b.max_x()))
+                .then_with(|| cmp_position(&a.max_y(), &b.max_y()))
+                .then_with(|| tie_break(a).cmp(&tie_break(b)))
+        });
+        values
+    }
+
+    /// Like `query_rect_ordered`, for the values containing the point.
+    pub fn query_point_ordered<K: Ord>(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+        tie_break: impl Fn(&Value) -> K,
+    ) -> Vec<&Value> {
+        self.query_rect_ordered(x.clone(), x, y.clone(), y, tie_break)
+    }
+}
+
+//a subtree or a value to yield, with the smallest min along the axis it can have,
+//unbounded when `None`
+struct Entry<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    min: Option<Value::Position>,
+    item: Item<'a, Value, ISLAND_SIZE>,
+}
+
+enum Item<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    Tree(&'a KdTree<Value, ISLAND_SIZE>),
+    Value(&'a Value),
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PartialEq for Entry<'_, Value, ISLAND_SIZE> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Eq for Entry<'_, Value, ISLAND_SIZE> {}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> PartialOrd for Entry<'_, Value, ISLAND_SIZE> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Ord for Entry<'_, Value, ISLAND_SIZE> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed, as the heap pops the greatest, and subtrees are opened before
+        //values with the same min
+        let ordering = match (&self.min, &other.min) {
+            (Some(a), Some(b)) => cmp_position(b, a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        };
+        let is_tree = |entry: &Self| matches!(entry.item, Item::Tree(_));
+        ordering.then_with(|| is_tree(self).cmp(&is_tree(other)))
+    }
+}
+
+/// The values overlapping a rectangle, sorted along an axis.
+pub struct SortedRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    //min x, max x, min y, max y
+    rect: [Value::Position; 4],
+    vertical: bool,
+    heap: BinaryHeap<Entry<'a, Value, ISLAND_SIZE>>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> SortedRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    fn new(
+        tree: &'a KdTree<Value, ISLAND_SIZE>,
+        rect: [Value::Position; 4],
+        vertical: bool,
+    ) -> Self {
+        let mut heap = BinaryHeap::new();
+        heap.push(Entry {
+            min: None,
+            item: Item::Tree(tree),
+        });
+        Self {
+            rect,
+            vertical,
+            heap,
+        }
+    }
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for SortedRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Clone,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let [min_x, max_x, min_y, max_y] = &self.rect;
+        loop {
+            let Entry { min, item } = self.heap.pop()?;
+            match item {
+                Item::Value(value) => return Some(value),
+                Item::Tree(KdTree::Leaf(values, ..)) => {
+                    for value in values {
+                        if value.min_x() <= *max_x
+                            && *min_x <= value.max_x()
+                            && value.min_y() <= *max_y
+                            && *min_y <= value.max_y()
+                        {
+                            let min = if self.vertical {
+                                value.min_y()
+                            } else {
+                                value.min_x()
+                            };
+                            self.heap.push(Entry {
+                                min: Some(min),
+                                item: Item::Value(value),
+                            });
+                        }
+                    }
+                }
+                Item::Tree(KdTree::Node(node)) => {
+                    let (low, high) = if node.vertical {
+                        (min_y, max_y)
+                    } else {
+                        (min_x, max_x)
+                    };
+                    if *low <= node.left_max {
+                        self.heap.push(Entry {
+                            min: min.clone(),
+                            item: Item::Tree(&node.left),
+                        });
+                    }
+                    if *high >= node.median {
+                        //the right values start at the median along its axis
+                        let min = if node.vertical == self.vertical {
+                            Some(node.median.clone())
+                        } else {
+                            min
+                        };
+                        self.heap.push(Entry {
+                            min,
+                            item: Item::Tree(&node.right),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tests::{scattered, TestValue},
+        Aabb, KdTree, PayloadCell,
+    };
+
+    #[test]
+    fn sorted_queries() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for value in scattered(300, |i| ((i % 5) as f32, 2.)) {
+            tree.insert(value);
+        }
+        let mut expected: Vec<_> = tree.query_rect(20., 70., 10., 60.).collect();
+        let found: Vec<_> = tree.query_rect_sorted_x(20., 70., 10., 60.).collect();
+        assert_eq!(found.len(), expected.len());
+        assert!(found.windows(2).all(|pair| pair[0].min_x <= pair[1].min_x));
+        expected.sort_by(|a, b| a.min_x.total_cmp(&b.min_x));
+        let xs = |values: &[&TestValue]| values.iter().map(|v| v.min_x).collect::<Vec<_>>();
+        assert_eq!(xs(&found), xs(&expected));
+
+        let found: Vec<_> = tree.query_rect_sorted_y(20., 70., 10., 60.).collect();
+        assert_eq!(found.len(), expected.len());
+        assert!(found.windows(2).all(|pair| pair[0].min_y <= pair[1].min_y));
+        assert_eq!(tree.query_rect_sorted_x(500., 600., 0., 1.).count(), 0);
+    }
+
+    #[test]
+    fn ordered_queries() {
+        let values: Vec<_> = scattered(300, |i| ((i % 5) as f32, 2.))
+            .into_iter()
+            .zip(0..)
+            .map(|(v, i)| PayloadCell::new(Aabb::new(v.min_x, v.max_x, v.min_y, v.max_y), i % 3))
+            .collect();
+        let mut forward = KdTree::<_, 4>::default();
+        for value in &values {
+            forward.insert(value.clone());
+        }
+        //another history: reversed, with extra values removed afterwards
+        let mut backward = KdTree::<_, 8>::default();
+        for value in values.iter().rev() {
+            backward.insert(value.clone());
+            backward.insert(PayloadCell::new(Aabb::new(50., 51., 50., 51.), 7));
+        }
+        for _ in 0..300 {
+            backward.remove_one(PayloadCell::new(Aabb::new(50., 51., 50., 51.), 7));
+        }
+        let payload = |value: &PayloadCell<Aabb<f32>, i32>| *value.payload();
+        let a = forward.query_rect_ordered(20., 70., 10., 60., payload);
+        let b = backward.query_rect_ordered(20., 70., 10., 60., payload);
+        assert!(a.len() > 10);
+        assert_eq!(a, b);
+        assert_eq!(
+            forward.query_point_ordered(40., 40., payload),
+            backward.query_point_ordered(40., 40., payload)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use core::{fmt::Debug, ops::Deref};
+
+use crate::{Aabb, KdValue};
+
+/// Any value along with its bounds, to store foreign types without implementing
+/// `KdValue` for them.
+///
+/// The bounds are given by a function called once in `new`, and stored along with
+/// the value, which can't be changed afterwards:
+///
+/// ```
+/// use kdtree_collisions::{Aabb, KdTree, Spatial};
+///
+/// //a circle as (x, y, radius)
+/// fn bounds(circle: &(f32, f32, f32)) -> Aabb<f32> {
+///     let (x, y, radius) = *circle;
+///     Aabb::new(x - radius, x + radius, y - radius, y + radius)
+/// }
+///
+/// let mut tree = KdTree::<Spatial<(f32, f32, f32), f32>, 8>::default();
+/// tree.insert(Spatial::new((0., 0., 1.), bounds));
+/// tree.insert(Spatial::new((5., 0., 2.), bounds));
+/// assert_eq!(tree.query_point(3.5, 0.).next().unwrap().value().0, 5.);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Spatial<T, P> {
+    value: T,
+    bounds: Aabb<P>,
+}
+
+impl<T, P> Spatial<T, P> {
+    pub fn new(value: T, bounds: impl FnOnce(&T) -> Aabb<P>) -> Self {
+        let bounds = bounds(&value);
+        Self { value, bounds }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub fn bounds(&self) -> &Aabb<P> {
+        &self.bounds
+    }
+}
+
+/// Compares the values only.
+impl<T: PartialEq, P> PartialEq for Spatial<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T, P> Deref for Spatial<T, P> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Default + Clone + Debug + PartialEq, P: PartialOrd + Debug + Default + Clone> KdValue
+    for Spatial<T, P>
+{
+    type Position = P;
+
+    fn min_x(&self) -> P {
+        self.bounds.min_x.clone()
+    }
+
+    fn min_y(&self) -> P {
+        self.bounds.min_y.clone()
+    }
+
+    fn max_x(&self) -> P {
+        self.bounds.max_x.clone()
+    }
+
+    fn max_y(&self) -> P {
+        self.bounds.max_y.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Spatial;
+    use crate::{Aabb, KdTree};
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct Sprite {
+        x: i32,
+        y: i32,
+        size: i32,
+    }
+
+    fn sprite_bounds(sprite: &Sprite) -> Aabb<i32> {
+        Aabb::new(
+            sprite.x,
+            sprite.x + sprite.size,
+            sprite.y,
+            sprite.y + sprite.size,
+        )
+    }
+
+    #[test]
+    fn spatial() {
+        let mut tree = KdTree::<Spatial<Sprite, i32>, 4>::default();
+        for i in 0..50 {
+            let sprite = Sprite {
+                x: i * 37 % 101,
+                y: i % 7,
+                size: 2,
+            };
+            tree.insert(Spatial::new(sprite, sprite_bounds));
+        }
+        let found: Vec<_> = tree.query_rect(0, 10, 0, 10).map(|s| s.x).collect();
+        let expected = (0..50).map(|i| i * 37 % 101).filter(|x| *x <= 10).count();
+        assert_eq!(found.len(), expected);
+        assert_eq!(tree.query_point(38, 1).next().unwrap().bounds().min_x, 37);
+        let sprite = Sprite {
+            x: 37,
+            y: 1,
+            size: 2,
+        };
+        assert!(tree.remove_one(Spatial::new(sprite, sprite_bounds)));
+        assert_eq!(tree.len(), 49);
+    }
+}
@@ -0,0 +1,95 @@
+use alloc::sync::Arc;
+use core::{fmt::Debug, ops::Deref};
+
+use crate::KdValue;
+
+/// A value stored in its own allocation, whose address doesn't change when the
+/// leaves of the tree are reallocated by insertions and removals.
+///
+/// Cloning a `Stable` found by a query gives a handle to the same value, which can
+/// be kept across later mutations of the tree without borrowing it.
+#[derive(Debug, Default)]
+pub struct Stable<Value>(Arc<Value>);
+
+impl<Value> Stable<Value> {
+    pub fn new(value: Value) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// The address of the value, the same for all the handles to it.
+    pub fn as_ptr(&self) -> *const Value {
+        Arc::as_ptr(&self.0)
+    }
+
+    /// Whether both handles point to the same value, rather than equal ones.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Value> Clone for Stable<Value> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Value: PartialEq> PartialEq for Stable<Value> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Value> Deref for Stable<Value> {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl<Value: KdValue> KdValue for Stable<Value> {
+    type Position = Value::Position;
+
+    fn min_x(&self) -> Self::Position {
+        self.0.min_x()
+    }
+
+    fn min_y(&self) -> Self::Position {
+        self.0.min_y()
+    }
+
+    fn max_x(&self) -> Self::Position {
+        self.0.max_x()
+    }
+
+    fn max_y(&self) -> Self::Position {
+        self.0.max_y()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stable;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn stable_addresses() {
+        let mut tree = KdTree::<Stable<TestValue>, 4>::default();
+        tree.insert(Stable::new(TestValue::new(0., 1., 0., 1.)));
+        let handle = tree.query_point(0.5, 0.5).next().unwrap().clone();
+        let address = handle.as_ptr();
+        //splits and reallocates the leaves
+        for i in 1..101 {
+            let x = (i * 37 % 101) as f32;
+            tree.insert(Stable::new(TestValue::new(x, x + 1., 0., 1.)));
+        }
+        assert_eq!(handle.max_x, 1.);
+        let found = tree.query_point(0.5, 0.5).next().unwrap();
+        assert!(found.ptr_eq(&handle));
+        assert_eq!(found.as_ptr(), address);
+        //removal goes by equality
+        assert!(tree.remove_one(Stable::new(TestValue::new(0., 1., 0., 1.))));
+        assert_eq!(handle.min_x, 0.);
+        assert_eq!(tree.query_point(0.5, 0.5).count(), 0);
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(debug_assertions)]
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+use crate::KdValue;
+
+/// The bounds each value of a leaf had when it was stored, kept in debug builds
+/// to catch values whose bounds are changed while they are in the tree. Release
+/// builds keep nothing.
+///
+/// They are unknown for leaves which were changed without going through the
+/// tree, like deserialized ones, until the leaf is rebuilt.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct StoredBounds<P> {
+    #[cfg(debug_assertions)]
+    bounds: Option<Vec<[P; 4]>>,
+    position: PhantomData<P>,
+}
+
+impl<P> Default for StoredBounds<P> {
+    fn default() -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            bounds: None,
+            position: PhantomData,
+        }
+    }
+}
+
+impl<P> fmt::Debug for StoredBounds<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StoredBounds")
+    }
+}
+
+#[cfg(debug_assertions)]
+impl<P: PartialOrd> StoredBounds<P> {
+    pub(crate) fn of<Value: KdValue<Position = P>>(values: &[Value]) -> Self {
+        Self {
+            bounds: Some(values.iter().map(bounds).collect()),
+            position: PhantomData,
+        }
+    }
+
+    //the last value was just pushed
+    pub(crate) fn pushed<Value: KdValue<Position = P>>(&mut self, values: &[Value]) {
+        match &mut self.bounds {
+            Some(stored) if stored.len() + 1 == values.len() => {
+                stored.push(bounds(&values[values.len() - 1]))
+            }
+            _ => self.bounds = None,
+        }
+    }
+
+    //the value at this index was just swap removed, leaving `len` values
+    pub(crate) fn swap_removed(&mut self, index: usize, len: usize) {
+        match &mut self.bounds {
+            Some(stored) if stored.len() == len + 1 => {
+                stored.swap_remove(index);
+            }
+            _ => self.bounds = None,
+        }
+    }
+
+    //panics naming the first value whose bounds changed since it was stored
+    pub(crate) fn check<Value: KdValue<Position = P>>(&self, values: &[Value]) {
+        let stored = match &self.bounds {
+            Some(stored) if stored.len() == values.len() => stored,
+            _ => return,
+        };
+        for (value, stored) in values.iter().zip(stored) {
+            //NaN bounds stay the same when they are still NaN
+            let unchanged = bounds(value)
+                .iter()
+                .zip(stored)
+                .all(|(a, b)| a == b || (a.partial_cmp(a).is_none() && b.partial_cmp(b).is_none()));
+            assert!(
+                unchanged,
+                "the bounds of {:?} were changed while it was in the tree",
+                value
+            );
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+fn bounds<Value: KdValue>(value: &Value) -> [Value::Position; 4] {
+    [value.min_x(), value.max_x(), value.min_y(), value.max_y()]
+}
+
+#[cfg(not(debug_assertions))]
+impl<P: PartialOrd> StoredBounds<P> {
+    #[inline(always)]
+    pub(crate) fn of<Value: KdValue<Position = P>>(_values: &[Value]) -> Self {
+        Self {
+            position: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn pushed<Value: KdValue<Position = P>>(&mut self, _values: &[Value]) {}
+
+    #[inline(always)]
+    pub(crate) fn swap_removed(&mut self, _index: usize, _len: usize) {}
+
+    #[inline(always)]
+    pub(crate) fn check<Value: KdValue<Position = P>>(&self, _values: &[Value]) {}
+}
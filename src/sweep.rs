@@ -0,0 +1,280 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::{Aabb, KdValue, ProxyId};
+
+/// A sweep-and-prune index: the bounds of every value are kept sorted per axis.
+///
+/// When values only move a little between frames, re-sorting the endpoints with an
+/// insertion sort is close to linear, which makes finding all the overlapping
+/// pairs cheaper than traversing a tree.
+#[derive(Debug)]
+pub struct SweepAndPrune<Value: KdValue> {
+    values: Vec<Option<Value>>,
+    //index of the [min x, max x, min y, max y] endpoints of each value
+    slots: Vec<[usize; 4]>,
+    free: Vec<usize>,
+    axes: [Vec<Endpoint<Value::Position>>; 2],
+}
+
+#[derive(Debug)]
+struct Endpoint<P> {
+    position: P,
+    proxy: usize,
+    is_max: bool,
+}
+
+impl<P: PartialOrd> Endpoint<P> {
+    //minimums go first on ties, so that touching boxes overlap
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.position
+            .partial_cmp(&other.position)
+            .unwrap_or(Ordering::Equal)
+            .then(self.is_max.cmp(&other.is_max))
+    }
+}
+
+impl<Value: KdValue> Default for SweepAndPrune<Value> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            axes: [Vec::new(), Vec::new()],
+        }
+    }
+}
+
+impl<Value: KdValue> SweepAndPrune<Value> {
+    pub fn len(&self) -> usize {
+        self.values.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, id: ProxyId) -> Option<&Value> {
+        self.values.get(id.0).and_then(Option::as_ref)
+    }
+
+    pub fn insert(&mut self, value: Value) -> ProxyId {
+        let proxy = match self.free.pop() {
+            Some(proxy) => proxy,
+            None => {
+                self.values.push(None);
+                self.slots.push([0; 4]);
+                self.values.len() - 1
+            }
+        };
+        let positions = [value.min_x(), value.max_x(), value.min_y(), value.max_y()];
+        for (endpoint, position) in IntoIterator::into_iter(positions).enumerate() {
+            let axis = endpoint / 2;
+            self.axes[axis].push(Endpoint {
+                position,
+                proxy,
+                is_max: endpoint % 2 == 1,
+            });
+            self.slots[proxy][endpoint] = self.axes[axis].len() - 1;
+            self.settle(axis, self.axes[axis].len() - 1);
+        }
+        self.values[proxy] = Some(value);
+        ProxyId(proxy)
+    }
+
+    pub fn remove(&mut self, id: ProxyId) -> Option<Value> {
+        let value = self.values.get_mut(id.0)?.take()?;
+        for axis in 0..2 {
+            //the higher endpoint first, so the lower one keeps its index
+            for endpoint in [axis * 2 + 1, axis * 2] {
+                let index = self.slots[id.0][endpoint];
+                self.axes[axis].remove(index);
+                for shifted in &self.axes[axis][index..] {
+                    let slot = axis * 2 + shifted.is_max as usize;
+                    self.slots[shifted.proxy][slot] -= 1;
+                }
+            }
+        }
+        self.free.push(id.0);
+        Some(value)
+    }
+
+    /// Replaces a value, moving its endpoints to their new place in the sorted
+    /// lists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was removed.
+    pub fn update(&mut self, id: ProxyId, value: Value) {
+        assert!(self.get(id).is_some(), "the proxy was removed");
+        let positions = [value.min_x(), value.max_x(), value.min_y(), value.max_y()];
+        for (endpoint, position) in IntoIterator::into_iter(positions).enumerate() {
+            let axis = endpoint / 2;
+            let index = self.slots[id.0][endpoint];
+            self.axes[axis][index].position = position;
+            self.settle(axis, index);
+        }
+        self.values[id.0] = Some(value);
+    }
+
+    /// Calls `f` once for every pair of values overlapping each other.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&Value, &Value)) {
+        let mut active: Vec<usize> = Vec::new();
+        for endpoint in &self.axes[0] {
+            if endpoint.is_max {
+                let index = active.iter().position(|&a| a == endpoint.proxy);
+                if let Some(index) = index {
+                    active.swap_remove(index);
+                }
+                continue;
+            }
+            let value = self.values[endpoint.proxy].as_ref().expect("live proxy");
+            for &other in &active {
+                let other = self.values[other].as_ref().expect("live proxy");
+                if value.min_y() <= other.max_y() && other.min_y() <= value.max_y() {
+                    f(other, value)
+                }
+            }
+            active.push(endpoint.proxy);
+        }
+    }
+
+    /// Finds the proxy of a value, scanning every value.
+    pub fn find(&self, value: &Value) -> Option<ProxyId> {
+        self.values
+            .iter()
+            .position(|v| v.as_ref() == Some(value))
+            .map(ProxyId)
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> SweepRectQuery<'_, Value> {
+        SweepRectQuery {
+            index: self,
+            endpoints: self.axes[0].iter(),
+            rect: Aabb {
+                min_x,
+                max_x,
+                min_y,
+                max_y,
+            },
+        }
+    }
+
+    pub fn query_point(&self, x: Value::Position, y: Value::Position) -> SweepRectQuery<'_, Value>
+    where
+        Value::Position: Clone,
+    {
+        self.query_rect(x.clone(), x, y.clone(), y)
+    }
+
+    //moves an endpoint whose position changed back to its sorted place
+    fn settle(&mut self, axis: usize, from: usize) {
+        let endpoints = &mut self.axes[axis];
+        let mut index = from;
+        while index > 0 && endpoints[index].cmp(&endpoints[index - 1]) == Ordering::Less {
+            endpoints.swap(index, index - 1);
+            index -= 1;
+        }
+        while index + 1 < endpoints.len()
+            && endpoints[index].cmp(&endpoints[index + 1]) == Ordering::Greater
+        {
+            endpoints.swap(index, index + 1);
+            index += 1;
+        }
+        //every endpoint between the old and the new index moved by one
+        for (i, endpoint) in endpoints
+            .iter()
+            .enumerate()
+            .take(from.max(index) + 1)
+            .skip(from.min(index))
+        {
+            self.slots[endpoint.proxy][axis * 2 + endpoint.is_max as usize] = i;
+        }
+    }
+}
+
+/// The values of a `SweepAndPrune` overlapping a rectangle.
+#[derive(Debug)]
+pub struct SweepRectQuery<'a, Value: KdValue> {
+    index: &'a SweepAndPrune<Value>,
+    endpoints: core::slice::Iter<'a, Endpoint<Value::Position>>,
+    rect: Aabb<Value::Position>,
+}
+
+impl<'a, Value: KdValue> Iterator for SweepRectQuery<'a, Value> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for endpoint in self.endpoints.by_ref() {
+            if endpoint.position > self.rect.max_x {
+                //every value left starts after the rectangle
+                self.endpoints = [].iter();
+                return None;
+            }
+            if endpoint.is_max {
+                continue;
+            }
+            let value = self.index.values[endpoint.proxy]
+                .as_ref()
+                .expect("live proxy");
+            if value.max_x() >= self.rect.min_x
+                && value.min_y() <= self.rect.max_y
+                && value.max_y() >= self.rect.min_y
+            {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SweepAndPrune;
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn sweep_and_prune() {
+        let mut sap = SweepAndPrune::<TestValue>::default();
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let mut values: Vec<_> = (0..60)
+            .map(|i| {
+                let (x, y) = ((i * 7 % 23) as f32, (i * 11 % 17) as f32);
+                TestValue::new(x, x + 2., y, y + 2.)
+            })
+            .collect();
+        let ids: Vec<_> = values.iter().map(|v| sap.insert(v.clone())).collect();
+        for (i, (id, value)) in ids.iter().zip(&mut values).enumerate() {
+            let dx = (i % 5) as f32 - 2.;
+            *value = TestValue::new(value.min_x + dx, value.max_x + dx, value.min_y, value.max_y);
+            sap.update(*id, value.clone());
+        }
+        for id in ids.iter().step_by(3) {
+            sap.remove(*id);
+        }
+        for (i, value) in values.iter().enumerate() {
+            if i % 3 != 0 {
+                tree.insert(value.clone());
+            }
+        }
+        let (mut sap_pairs, mut tree_pairs) = (0, 0);
+        sap.for_each_pair(|_, _| sap_pairs += 1);
+        tree.for_each_pair(|_, _| tree_pairs += 1);
+        assert!(sap_pairs > 0);
+        assert_eq!(sap_pairs, tree_pairs);
+        assert_eq!(
+            sap.query_rect(5., 10., 3., 8.).count(),
+            tree.query_rect(5., 10., 3., 8.).count()
+        );
+        assert_eq!(
+            sap.query_point(7., 7.).count(),
+            tree.query_point(7., 7.).count()
+        );
+    }
+}
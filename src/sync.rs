@@ -0,0 +1,118 @@
+use alloc::collections::BTreeMap;
+
+use crate::{KdValue, SpatialIndex2D};
+
+/// Reconciles an index with the state of a world given as a whole each tick, such
+/// as the entities of an ECS.
+///
+/// Every tick, `set` is called for each live id, then `apply` compares them with
+/// the previous tick and only inserts, removes or moves what changed. Ids not set
+/// during a tick are removed.
+#[derive(Debug, Clone)]
+pub struct SpatialSync<Id, Value> {
+    synced: BTreeMap<Id, Value>,
+    pending: BTreeMap<Id, Value>,
+}
+
+/// What `SpatialSync::apply` changed in the index.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    pub inserted: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl<Id: Ord, Value: KdValue> Default for SpatialSync<Id, Value> {
+    fn default() -> Self {
+        Self {
+            synced: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Id: Ord, Value: KdValue> SpatialSync<Id, Value> {
+    /// Records the value of `id` for the current tick.
+    pub fn set(&mut self, id: Id, value: Value) {
+        self.pending.insert(id, value);
+    }
+
+    /// The value of `id` as of the last `apply`.
+    pub fn get(&self, id: &Id) -> Option<&Value> {
+        self.synced.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.synced.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.synced.is_empty()
+    }
+
+    /// Applies the differences between this tick and the previous one to `index`,
+    /// which must only be modified through this `SpatialSync`.
+    pub fn apply<Index: SpatialIndex2D<Value = Value>>(&mut self, index: &mut Index) -> SyncStats {
+        let mut stats = SyncStats::default();
+        let pending = core::mem::take(&mut self.pending);
+        let previous = core::mem::take(&mut self.synced);
+        let mut previous = previous.into_iter().peekable();
+        //both maps are sorted by id, so walk them side by side
+        for (id, value) in pending {
+            while let Some((_, old)) = previous.next_if(|(old_id, _)| *old_id < id) {
+                index.remove_one(&old);
+                stats.removed += 1;
+            }
+            match previous.next_if(|(old_id, _)| *old_id == id) {
+                Some((_, old)) if old == value => {}
+                Some((_, old)) => {
+                    index.remove_one(&old);
+                    index.insert(value.clone());
+                    stats.updated += 1;
+                }
+                None => {
+                    index.insert(value.clone());
+                    stats.inserted += 1;
+                }
+            }
+            self.synced.insert(id, value);
+        }
+        for (_, old) in previous {
+            index.remove_one(&old);
+            stats.removed += 1;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpatialSync, SyncStats};
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn spatial_sync() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let mut sync = SpatialSync::default();
+        for id in 0..10 {
+            sync.set(id, TestValue::new(id as f32, id as f32 + 1., 0., 1.));
+        }
+        sync.apply(&mut tree);
+        for id in 2..12 {
+            let x = if id == 5 { 50. } else { id as f32 };
+            sync.set(id, TestValue::new(x, x + 1., 0., 1.));
+        }
+        let stats = sync.apply(&mut tree);
+        assert_eq!(
+            stats,
+            SyncStats {
+                inserted: 2,
+                updated: 1,
+                removed: 2,
+            }
+        );
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.query_point(50.5, 0.5).count(), 1);
+        assert_eq!(tree.query_point(0.5, 0.5).count(), 0);
+    }
+}
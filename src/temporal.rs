@@ -0,0 +1,121 @@
+use core::fmt::Debug;
+
+use crate::{KdTree, KdValue};
+
+/// A value only present during a closed time interval, unbounded on the sides
+/// which are `None`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timed<Value, T> {
+    pub value: Value,
+    pub start: Option<T>,
+    pub end: Option<T>,
+}
+
+impl<Value, T: PartialOrd> Timed<Value, T> {
+    pub fn new(value: Value, start: T, end: T) -> Self {
+        Self {
+            value,
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+
+    /// A value present at any time.
+    pub fn always(value: Value) -> Self {
+        Self {
+            value,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Whether the value is present at some time between `t0` and `t1`, included.
+    pub fn is_during(&self, t0: &T, t1: &T) -> bool {
+        self.start.as_ref().is_none_or(|start| start <= t1)
+            && self.end.as_ref().is_none_or(|end| t0 <= end)
+    }
+}
+
+impl<Value: KdValue, T: Default + Clone + Debug + PartialEq> KdValue for Timed<Value, T> {
+    type Position = Value::Position;
+
+    fn min_x(&self) -> Self::Position {
+        self.value.min_x()
+    }
+
+    fn min_y(&self) -> Self::Position {
+        self.value.min_y()
+    }
+
+    fn max_x(&self) -> Self::Position {
+        self.value.max_x()
+    }
+
+    fn max_y(&self) -> Self::Position {
+        self.value.max_y()
+    }
+}
+
+impl<Value: KdValue, T: Default + Clone + Debug + PartialOrd, const ISLAND_SIZE: usize>
+    KdTree<Timed<Value, T>, ISLAND_SIZE>
+{
+    /// The values overlapping the rectangle which are present at time `t`.
+    pub fn query_rect_at(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        t: T,
+    ) -> impl Iterator<Item = &Timed<Value, T>> + '_ {
+        self.query_rect_during(min_x, max_x, min_y, max_y, t.clone(), t)
+    }
+
+    /// The values overlapping the rectangle which are present at some time
+    /// between `t0` and `t1`.
+    ///
+    /// Times are not indexed: the values overlapping the rectangle are filtered
+    /// by their interval.
+    pub fn query_rect_during(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        t0: T,
+        t1: T,
+    ) -> impl Iterator<Item = &Timed<Value, T>> + '_ {
+        self.query_rect(min_x, max_x, min_y, max_y)
+            .filter(move |value| value.is_during(&t0, &t1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timed;
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn time_bounded_queries() {
+        let mut tree = KdTree::<Timed<Aabb<f32>, u32>, 4>::default();
+        //a trail moving along x, one step per tick
+        for t in 0..100 {
+            let x = t as f32;
+            tree.insert(Timed::new(Aabb::new(x, x + 1., 0., 1.), t, t));
+        }
+        tree.insert(Timed::always(Aabb::new(50., 51., 0., 1.)));
+        assert_eq!(tree.query_rect_at(0., 200., 0., 1., 30).count(), 2);
+        assert_eq!(tree.query_rect_at(0., 10., 0., 1., 30).count(), 0);
+        let found: Vec<_> = tree
+            .query_rect_during(40., 60., 0., 1., 45, 55)
+            .map(|value| value.start)
+            .collect();
+        //the 11 steps and the value present at any time
+        assert_eq!(found.len(), 12);
+        assert!(found.contains(&None));
+        assert_eq!(
+            tree.query_rect_during(40., 60., 0., 1., 200, 300).count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{KdTree, KdValue, RectQuery, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Like `query_rect`, also matching the values at most `tolerance` away from
+    /// the rectangle along both axes, to absorb float drift.
+    pub fn query_rect_within(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        tolerance: Value::Position,
+    ) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect(
+            min_x - tolerance,
+            max_x + tolerance,
+            min_y - tolerance,
+            max_y + tolerance,
+        )
+    }
+
+    /// Like `query_point`, also matching the values at most `tolerance` away from
+    /// the point along both axes.
+    pub fn query_point_within(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+        tolerance: Value::Position,
+    ) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        self.query_rect_within(x, x, y, y, tolerance)
+    }
+
+    /// Like `for_each_pair`, also pairing the values at most `tolerance` apart.
+    pub fn for_each_pair_within(
+        &self,
+        tolerance: Value::Position,
+        mut f: impl FnMut(&Value, &Value),
+    ) {
+        for value in self.iter() {
+            let query = self.query_rect_within(
+                value.min_x(),
+                value.max_x(),
+                value.min_y(),
+                value.max_y(),
+                tolerance,
+            );
+            for other in query {
+                //each pair is found from both sides, only keep one of them
+                if (value as *const Value) < (other as *const Value) {
+                    f(value, other)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Aabb, KdTree};
+
+    #[test]
+    fn tolerance() {
+        let mut tree = KdTree::<Aabb<f32>, 4>::default();
+        //boxes drifting slightly apart
+        for i in 0..10 {
+            let x = i as f32 * 1.0001;
+            tree.insert(Aabb::new(x, x + 1., 0., 1.));
+        }
+        let mut pairs = 0;
+        tree.for_each_pair(|_, _| pairs += 1);
+        assert_eq!(pairs, 0);
+        tree.for_each_pair_within(0.001, |_, _| pairs += 1);
+        assert_eq!(pairs, 9);
+
+        assert_eq!(tree.query_rect(2.5, 3.0, 1.0005, 2.).count(), 0);
+        assert_eq!(
+            tree.query_rect_within(2.5, 3.0, 1.0005, 2., 0.001).count(),
+            2
+        );
+        assert_eq!(tree.query_point_within(1.00005, 0.5, 0.001).count(), 2);
+        assert_eq!(tree.query_point_within(1.00005, 0.5, 0.).count(), 0);
+
+        let a = Aabb::new(0., 1., 0., 1.);
+        assert!(!a.overlaps(&Aabb::new(1.01, 2., 0., 1.)));
+        assert!(a.overlaps_within(&Aabb::new(1.01, 2., 0., 1.), 0.02));
+        assert!(!a.overlaps_within(&Aabb::new(1.01, 2., 1.05, 2.), 0.02));
+    }
+}
@@ -0,0 +1,108 @@
+use alloc::{collections::BinaryHeap, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{KdTree, KdValue};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// The `k` values overlapping the rectangle with the highest `score`, along
+    /// with it, from the highest. Ties keep the values found first.
+    ///
+    /// Only the best `k` values found so far are kept while querying, in a heap
+    /// popping the lowest of them.
+    pub fn top_k_in_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+        k: usize,
+        score: impl Fn(&Value) -> f64,
+    ) -> Vec<(f64, &Value)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        //k may be far above the number of matches, so the heap only grows with them
+        let mut heap = BinaryHeap::new();
+        for value in self.query_rect(min_x, max_x, min_y, max_y) {
+            let scored = Scored {
+                score: score(value),
+                value,
+            };
+            if heap.len() < k {
+                heap.push(scored);
+            } else if heap
+                .peek()
+                .is_some_and(|lowest| scored.score > lowest.score)
+            {
+                heap.pop();
+                heap.push(scored);
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Scored { score, value }| (score, value))
+            .collect()
+    }
+}
+
+//a value kept in top_k_in_rect, the lowest score first
+struct Scored<'a, Value> {
+    score: f64,
+    value: &'a Value,
+}
+
+impl<Value> PartialEq for Scored<'_, Value> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Value> Eq for Scored<'_, Value> {}
+
+impl<Value> PartialOrd for Scored<'_, Value> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Value> Ord for Scored<'_, Value> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed, as the heap pops the greatest
+        other.score.total_cmp(&self.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, KdTree};
+
+    #[test]
+    fn top_k_in_rect() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        for i in 0..200 {
+            let (x, y) = ((i * 37 % 101) as f32, (i * 11 % 97) as f32);
+            tree.insert(TestValue::new(x, x + 1., y, y + 1.));
+        }
+        //the highest value in the region scores best
+        let score = |value: &TestValue| value.min_y as f64;
+        let mut expected: Vec<_> = tree.query_rect(10., 60., 20., 70.).map(score).collect();
+        expected.sort_by(|a, b| b.total_cmp(a));
+        let top = tree.top_k_in_rect(10., 60., 20., 70., 5, score);
+        let scores: Vec<_> = top.iter().map(|(score, _)| *score).collect();
+        assert_eq!(scores, expected[..5]);
+        assert!(top
+            .iter()
+            .all(|(score, value)| value.min_y as f64 == *score));
+        assert_eq!(
+            tree.top_k_in_rect(10., 60., 20., 70., 1000, score).len(),
+            expected.len()
+        );
+        assert!(tree.top_k_in_rect(10., 60., 20., 70., 0, score).is_empty());
+        //all of them
+        assert_eq!(
+            tree.top_k_in_rect(10., 60., 20., 70., usize::MAX, score)
+                .len(),
+            expected.len()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+
+use crate::{Aabb, KdTree, KdValue, RectQuery, Scalar};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    /// Queries a world wrapping around at the edges of `world`, like a torus: the
+    /// parts of the rectangle past an edge are also looked for at the opposite
+    /// edge. Values are only reported once even when found across several seams.
+    ///
+    /// Values are expected to lie inside the world: a value crossing a seam should
+    /// be inserted as one part on each side.
+    pub fn query_rect_wrapping(
+        &self,
+        world: &Aabb<Value::Position>,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> WrappingRectQuery<'_, Value, ISLAND_SIZE> {
+        let width = world.max_x - world.min_x;
+        let height = world.max_y - world.min_y;
+        let zero = Value::Position::default();
+        //the offsets moving the parts of the rectangle past an edge back inside
+        let offsets = |min, max, low, high, size| {
+            let mut offsets = vec![zero];
+            if min < low {
+                offsets.push(size);
+            }
+            if max > high {
+                offsets.push(zero - size);
+            }
+            offsets
+        };
+        let x_offsets = offsets(min_x, max_x, world.min_x, world.max_x, width);
+        let y_offsets = offsets(min_y, max_y, world.min_y, world.max_y, height);
+        let rects = x_offsets
+            .iter()
+            .flat_map(|&dx| {
+                y_offsets
+                    .iter()
+                    .map(move |&dy| Aabb::new(min_x + dx, max_x + dx, min_y + dy, max_y + dy))
+            })
+            .collect();
+        WrappingRectQuery {
+            tree: self,
+            rects,
+            query: None,
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// The values containing the point, wrapped back inside of `world` first.
+    pub fn query_point_wrapping(
+        &self,
+        world: &Aabb<Value::Position>,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> WrappingRectQuery<'_, Value, ISLAND_SIZE> {
+        let wrap = |position: Value::Position, low: Value::Position, high: Value::Position| {
+            let size = (high - low).to_f64();
+            let offset = (position - low).to_f64() % size;
+            let offset = if offset < 0. { offset + size } else { offset };
+            low + Value::Position::from_f64(offset)
+        };
+        let (x, y) = (
+            wrap(x, world.min_x, world.max_x),
+            wrap(y, world.min_y, world.max_y),
+        );
+        self.query_rect_wrapping(world, x, x, y, y)
+    }
+}
+
+/// The values of a wrapping world overlapping a rectangle.
+pub struct WrappingRectQuery<'a, Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: &'a KdTree<Value, ISLAND_SIZE>,
+    //the shifted rectangles left to query
+    rects: Vec<Aabb<Value::Position>>,
+    query: Option<RectQuery<'a, Value, ISLAND_SIZE>>,
+    seen: BTreeSet<*const Value>,
+}
+
+impl<'a, Value: KdValue, const ISLAND_SIZE: usize> Iterator
+    for WrappingRectQuery<'a, Value, ISLAND_SIZE>
+where
+    Value::Position: Scalar,
+{
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(query) = &mut self.query {
+                for value in query.by_ref() {
+                    if self.seen.insert(value as *const Value) {
+                        return Some(value);
+                    }
+                }
+            }
+            let rect = self.rects.pop()?;
+            self.query = Some(
+                self.tree
+                    .query_rect(rect.min_x, rect.max_x, rect.min_y, rect.max_y),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::TestValue, Aabb, KdTree};
+
+    #[test]
+    fn wrapping_queries() {
+        let world = Aabb::new(0., 100., 0., 50.);
+        let mut tree = KdTree::<TestValue, 4>::default();
+        tree.insert(TestValue::new(1., 3., 1., 3.));
+        tree.insert(TestValue::new(97., 99., 47., 49.));
+        tree.insert(TestValue::new(50., 52., 25., 27.));
+        tree.insert(TestValue::new(0., 100., 0., 50.));
+        assert_eq!(
+            tree.query_rect_wrapping(&world, 95., 105., 45., 55.)
+                .count(),
+            3
+        );
+        assert_eq!(
+            tree.query_rect_wrapping(&world, -4., 2., -1., 2.).count(),
+            3
+        );
+        assert_eq!(tree.query_point_wrapping(&world, 102., 52.).count(), 2);
+        assert_eq!(tree.query_point_wrapping(&world, -2., -2.).count(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+use core::cell::Cell;
+
+use crate::{KdTree, KdValue, PointQuery, RectQuery};
+
+/// A `KdTree` that records what it is used for, to help choosing `ISLAND_SIZE`.
+#[derive(Debug)]
+pub struct InstrumentedKdTree<Value: KdValue, const ISLAND_SIZE: usize> {
+    tree: KdTree<Value, ISLAND_SIZE>,
+    inserts: u64,
+    removals: u64,
+    queries: Cell<u64>,
+    results: Cell<u64>,
+}
+
+/// What was observed on an `InstrumentedKdTree`, and what island size it calls for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningReport {
+    pub inserts: u64,
+    pub removals: u64,
+    pub queries: u64,
+    pub results: u64,
+    pub len: usize,
+    pub depth: usize,
+    pub leaves: usize,
+    pub island_size: usize,
+    pub recommended_island_size: usize,
+    /// Number of inserts after which calling `KdTree::rebuild` is worth it, if the
+    /// tree was found to be unbalanced.
+    pub rebuild_every: Option<u64>,
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> Default for InstrumentedKdTree<Value, ISLAND_SIZE> {
+    fn default() -> Self {
+        Self::new(KdTree::default())
+    }
+}
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> InstrumentedKdTree<Value, ISLAND_SIZE> {
+    pub fn new(tree: KdTree<Value, ISLAND_SIZE>) -> Self {
+        Self {
+            tree,
+            inserts: 0,
+            removals: 0,
+            queries: Cell::new(0),
+            results: Cell::new(0),
+        }
+    }
+
+    pub fn tree(&self) -> &KdTree<Value, ISLAND_SIZE> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> KdTree<Value, ISLAND_SIZE> {
+        self.tree
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.inserts = 0;
+        self.removals = 0;
+        self.queries.set(0);
+        self.results.set(0);
+    }
+
+    pub fn insert(&mut self, value: Value) {
+        self.inserts += 1;
+        self.tree.insert(value)
+    }
+
+    pub fn remove_one(&mut self, value: Value) -> bool {
+        self.removals += 1;
+        self.tree.remove_one(value)
+    }
+
+    pub fn remove_all(&mut self, value: Value) {
+        self.removals += 1;
+        self.tree.remove_all(value)
+    }
+
+    pub fn query_point(
+        &self,
+        x: Value::Position,
+        y: Value::Position,
+    ) -> Counted<'_, PointQuery<'_, Value, ISLAND_SIZE>> {
+        self.queries.set(self.queries.get() + 1);
+        Counted {
+            inner: self.tree.query_point(x, y),
+            results: &self.results,
+        }
+    }
+
+    pub fn query_rect(
+        &self,
+        min_x: Value::Position,
+        max_x: Value::Position,
+        min_y: Value::Position,
+        max_y: Value::Position,
+    ) -> Counted<'_, RectQuery<'_, Value, ISLAND_SIZE>> {
+        self.queries.set(self.queries.get() + 1);
+        Counted {
+            inner: self.tree.query_rect(min_x, max_x, min_y, max_y),
+            results: &self.results,
+        }
+    }
+
+    pub fn tuning_report(&self) -> TuningReport {
+        let (depth, leaves) = shape(&self.tree);
+        let len = self.tree.len();
+        let queries = self.queries.get();
+        let results = self.results.get();
+        let writes = self.inserts + self.removals;
+
+        //a query scans whole leaves, so leaves about the size of a typical result
+        //waste little time. Writes only touch one leaf but pay for the splits,
+        //which favors bigger leaves.
+        let per_query = results.checked_div(queries).unwrap_or(0) as usize;
+        let mut recommended = per_query.max(8).next_power_of_two();
+        if writes > queries {
+            recommended *= 2;
+        }
+        let recommended_island_size = recommended.min(256);
+
+        //the split only halves full leaves, so a balanced tree would be about
+        //log2(len / (ISLAND_SIZE / 2)) deep
+        let half_island = (ISLAND_SIZE / 2).max(1);
+        let balanced_depth =
+            (usize::BITS - ((len / half_island).max(1) - 1).leading_zeros()) as usize;
+        let rebuild_every = if depth > 2 * balanced_depth + 2 {
+            Some((len as u64).max(1024))
+        } else {
+            None
+        };
+
+        TuningReport {
+            inserts: self.inserts,
+            removals: self.removals,
+            queries,
+            results,
+            len,
+            depth,
+            leaves,
+            island_size: ISLAND_SIZE,
+            recommended_island_size,
+            rebuild_every,
+        }
+    }
+}
+
+//returns the depth and the number of leaves of the tree
+fn shape<Value: KdValue, const ISLAND_SIZE: usize>(
+    tree: &KdTree<Value, ISLAND_SIZE>,
+) -> (usize, usize) {
+    match tree {
+        KdTree::Leaf(..) => (0, 1),
+        KdTree::Node(node) => {
+            let (left_depth, left_leaves) = shape(&node.left);
+            let (right_depth, right_leaves) = shape(&node.right);
+            (left_depth.max(right_depth) + 1, left_leaves + right_leaves)
+        }
+    }
+}
+
+/// A query on an `InstrumentedKdTree`, counting the values it yields.
+pub struct Counted<'a, Query> {
+    inner: Query,
+    results: &'a Cell<u64>,
+}
+
+impl<'a, Query: Iterator> Iterator for Counted<'a, Query> {
+    type Item = Query::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.results.set(self.results.get() + 1);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstrumentedKdTree;
+    use crate::tests::TestValue;
+
+    #[test]
+    fn report() {
+        let mut tree = InstrumentedKdTree::<TestValue, 4>::default();
+        for i in 0..100 {
+            let x = i as f32;
+            tree.insert(TestValue::new(x, x + 1., 0., 1.));
+        }
+        assert_eq!(tree.query_rect(10., 20., 0., 1.).count(), 12);
+        assert_eq!(tree.query_point(50.5, 0.5).count(), 1);
+        let report = tree.tuning_report();
+        assert_eq!(report.inserts, 100);
+        assert_eq!(report.queries, 2);
+        assert_eq!(report.results, 13);
+        assert_eq!(report.len, 100);
+        assert!(report.leaves > 1);
+        assert!(report.recommended_island_size >= 8);
+    }
+}
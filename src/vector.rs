@@ -0,0 +1,24 @@
+use crate::{KdTree, KdValue, PointQuery, RectQuery};
+
+impl<Value: KdValue, const ISLAND_SIZE: usize> KdTree<Value, ISLAND_SIZE> {
+    /// Like `query_point`, with the point given by any vector type convertible to
+    /// `[x, y]`, like the glam ones.
+    pub fn query_point_vec(
+        &self,
+        point: impl Into<[Value::Position; 2]>,
+    ) -> PointQuery<'_, Value, ISLAND_SIZE> {
+        let [x, y] = point.into();
+        self.query_point(x, y)
+    }
+
+    /// Like `query_rect`, with the corners given by any vector type convertible to
+    /// `[x, y]`.
+    pub fn query_rect_vec(
+        &self,
+        min: impl Into<[Value::Position; 2]>,
+        max: impl Into<[Value::Position; 2]>,
+    ) -> RectQuery<'_, Value, ISLAND_SIZE> {
+        let ([min_x, min_y], [max_x, max_y]) = (min.into(), max.into());
+        self.query_rect(min_x, max_x, min_y, max_y)
+    }
+}
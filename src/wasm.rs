@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Aabb, KdTree, PayloadCell};
+
+type IdBox = PayloadCell<Aabb<f64>, u32>;
+
+/// A tree of boxes identified by `u32` ids, exported to JavaScript as `KdTree`.
+///
+/// Query results are returned as `Uint32Array`s of ids.
+#[wasm_bindgen(js_name = KdTree)]
+#[derive(Debug, Default)]
+pub struct WasmKdTree {
+    tree: KdTree<IdBox, 16>,
+    //the bounds of every id, to remove by id
+    bounds: BTreeMap<u32, Aabb<f64>>,
+}
+
+#[wasm_bindgen(js_class = KdTree)]
+impl WasmKdTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Inserts a box, replacing the previous one with the same id.
+    pub fn insert(&mut self, id: u32, min_x: f64, min_y: f64, max_x: f64, max_y: f64) {
+        self.remove(id);
+        let bounds = Aabb::new(min_x, max_x, min_y, max_y);
+        self.bounds.insert(id, bounds);
+        self.tree.insert(PayloadCell::new(bounds, id));
+    }
+
+    /// Removes the box with this id, returning whether there was one.
+    pub fn remove(&mut self, id: u32) -> bool {
+        match self.bounds.remove(&id) {
+            Some(bounds) => self.tree.remove_one(PayloadCell::new(bounds, id)),
+            None => false,
+        }
+    }
+
+    #[wasm_bindgen(js_name = queryRect)]
+    pub fn query_rect(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<u32> {
+        self.tree
+            .query_rect(min_x, max_x, min_y, max_y)
+            .map(|value| *value.payload())
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = queryPoint)]
+    pub fn query_point(&self, x: f64, y: f64) -> Vec<u32> {
+        self.tree
+            .query_point(x, y)
+            .map(|value| *value.payload())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WasmKdTree;
+
+    #[test]
+    fn wasm_wrapper() {
+        let mut tree = WasmKdTree::new();
+        for id in 0..30 {
+            let x = (id * 11 % 31) as f64;
+            tree.insert(id, x, id as f64, x + 1., id as f64 + 1.);
+        }
+        tree.insert(3, 100., 100., 101., 101.);
+        assert_eq!(tree.size(), 30);
+        assert_eq!(tree.query_point(100.5, 100.5), vec![3]);
+        let mut hits = tree.query_rect(0., 0., 31., 4.5);
+        hits.sort_unstable();
+        assert_eq!(hits, vec![0, 1, 2, 4]);
+        assert!(tree.remove(3));
+        assert!(!tree.remove(3));
+        assert!(tree.query_point(100.5, 100.5).is_empty());
+    }
+}
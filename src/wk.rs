@@ -0,0 +1,323 @@
+use alloc::{string::String, vec::Vec};
+use core::{convert::TryInto, fmt};
+
+use crate::{Aabb, KdTree, PayloadCell};
+
+/// A geometry that could not be read, with its index in the imported sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeometryError {
+    pub index: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid geometry at index {}: {}",
+            self.index, self.message
+        )
+    }
+}
+
+impl core::error::Error for GeometryError {}
+
+/// The bounding box of a WKT (or EWKT) geometry, or `None` if it is empty.
+///
+/// Only the coordinates are read, the geometry is not validated further. Z and M
+/// values are ignored.
+pub fn wkt_bounds(wkt: &str) -> Result<Option<Aabb<f64>>, &'static str> {
+    //skips the SRID of EWKT
+    let wkt = wkt.split_once(';').map_or(wkt, |(_, wkt)| wkt);
+    let mut bounds = Bounds::default();
+    let mut depth = 0usize;
+    //the numbers of the coordinate being read
+    let mut coordinate = Vec::new();
+    let end_coordinate = |coordinate: &mut Vec<f64>, bounds: &mut Bounds| {
+        match coordinate[..] {
+            [] => {}
+            [x, y, ..] => bounds.add(x, y),
+            _ => return Err("coordinate with a single number"),
+        }
+        coordinate.clear();
+        Ok(())
+    };
+    let mut rest = wkt.trim_start();
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '(' | ')' | ',' => {
+                end_coordinate(&mut coordinate, &mut bounds)?;
+                if c == '(' {
+                    depth += 1;
+                } else if c == ')' {
+                    depth = depth.checked_sub(1).ok_or("unbalanced parentheses")?;
+                }
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ',')
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                //keywords, like the geometry types, Z, M or EMPTY
+                if word.chars().all(|c| c.is_ascii_alphabetic()) {
+                    if !coordinate.is_empty() {
+                        return Err("unexpected keyword");
+                    }
+                } else if depth > 0 {
+                    coordinate.push(word.parse().map_err(|_| "invalid number")?);
+                } else {
+                    return Err("coordinates outside of parentheses");
+                }
+            }
+        }
+        rest = rest.trim_start();
+    }
+    if depth != 0 {
+        return Err("unbalanced parentheses");
+    }
+    Ok(bounds.into_aabb())
+}
+
+/// The bounding box of a WKB (or EWKB) geometry, or `None` if it is empty.
+///
+/// Z and M values are ignored.
+pub fn wkb_bounds(wkb: &[u8]) -> Result<Option<Aabb<f64>>, &'static str> {
+    let mut reader = WkbReader { bytes: wkb };
+    let mut bounds = Bounds::default();
+    reader.geometry(&mut bounds, 0)?;
+    if !reader.bytes.is_empty() {
+        return Err("trailing bytes");
+    }
+    Ok(bounds.into_aabb())
+}
+
+impl<const ISLAND_SIZE: usize> KdTree<PayloadCell<Aabb<f64>, String>, ISLAND_SIZE> {
+    /// Bulk-builds a tree of WKT geometries, keeping each one as the payload of its
+    /// bounding box. Empty geometries are skipped.
+    pub fn from_wkt<Geometry: Into<String>>(
+        geometries: impl IntoIterator<Item = Geometry>,
+    ) -> Result<Self, GeometryError> {
+        import(geometries, |wkt: &String| wkt_bounds(wkt))
+    }
+}
+
+impl<const ISLAND_SIZE: usize> KdTree<PayloadCell<Aabb<f64>, Vec<u8>>, ISLAND_SIZE> {
+    /// Bulk-builds a tree of WKB geometries, keeping each one as the payload of its
+    /// bounding box. Empty geometries are skipped.
+    pub fn from_wkb<Geometry: Into<Vec<u8>>>(
+        geometries: impl IntoIterator<Item = Geometry>,
+    ) -> Result<Self, GeometryError> {
+        import(geometries, |wkb: &Vec<u8>| wkb_bounds(wkb))
+    }
+}
+
+fn import<
+    Geometry: Into<T>,
+    T: Default + Clone + fmt::Debug + PartialEq,
+    const ISLAND_SIZE: usize,
+>(
+    geometries: impl IntoIterator<Item = Geometry>,
+    bounds: impl Fn(&T) -> Result<Option<Aabb<f64>>, &'static str>,
+) -> Result<KdTree<PayloadCell<Aabb<f64>, T>, ISLAND_SIZE>, GeometryError> {
+    let mut values = Vec::new();
+    for (index, geometry) in geometries.into_iter().enumerate() {
+        let geometry = geometry.into();
+        let geometry_bounds =
+            bounds(&geometry).map_err(|message| GeometryError { index, message })?;
+        if let Some(geometry_bounds) = geometry_bounds {
+            values.push(PayloadCell::new(geometry_bounds, geometry));
+        }
+    }
+    Ok(KdTree::build(values))
+}
+
+#[derive(Default)]
+struct Bounds(Option<Aabb<f64>>);
+
+impl Bounds {
+    fn add(&mut self, x: f64, y: f64) {
+        //empty points are written with NaN coordinates in WKB
+        if x.is_nan() || y.is_nan() {
+            return;
+        }
+        let point = Aabb::new(x, x, y, y);
+        self.0 = Some(match &self.0 {
+            Some(bounds) => bounds.union(&point),
+            None => point,
+        });
+    }
+
+    fn into_aabb(self) -> Option<Aabb<f64>> {
+        self.0
+    }
+}
+
+//collections nested deeper are rejected instead of overflowing the stack
+const MAX_NESTING: usize = 64;
+
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl WkbReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        if self.bytes.len() < N {
+            return Err("truncated geometry");
+        }
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(taken.try_into().unwrap())
+    }
+
+    fn u32(&mut self, little_endian: bool) -> Result<u32, &'static str> {
+        let bytes = self.take::<4>()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn f64(&mut self, little_endian: bool) -> Result<f64, &'static str> {
+        let bytes = self.take::<8>()?;
+        Ok(if little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    fn points(
+        &mut self,
+        little_endian: bool,
+        dimensions: usize,
+        bounds: &mut Bounds,
+    ) -> Result<(), &'static str> {
+        let count = self.u32(little_endian)? as usize;
+        if count.saturating_mul(dimensions * 8) > self.bytes.len() {
+            return Err("truncated geometry");
+        }
+        for _ in 0..count {
+            self.point(little_endian, dimensions, bounds)?;
+        }
+        Ok(())
+    }
+
+    fn point(
+        &mut self,
+        little_endian: bool,
+        dimensions: usize,
+        bounds: &mut Bounds,
+    ) -> Result<(), &'static str> {
+        let x = self.f64(little_endian)?;
+        let y = self.f64(little_endian)?;
+        for _ in 2..dimensions {
+            self.f64(little_endian)?;
+        }
+        bounds.add(x, y);
+        Ok(())
+    }
+
+    fn geometry(&mut self, bounds: &mut Bounds, depth: usize) -> Result<(), &'static str> {
+        if depth > MAX_NESTING {
+            return Err("geometry collections nested too deep");
+        }
+        let little_endian = match self.take::<1>()? {
+            [0] => false,
+            [1] => true,
+            _ => return Err("invalid byte order"),
+        };
+        let kind = self.u32(little_endian)?;
+        //EWKB flags, then ISO dimensions
+        let (z, m) = (kind & 0x8000_0000 != 0, kind & 0x4000_0000 != 0);
+        if kind & 0x2000_0000 != 0 {
+            self.u32(little_endian)?;
+        }
+        let kind = kind & 0x0fff_ffff;
+        let dimensions = match kind / 1000 {
+            0 => 2 + z as usize + m as usize,
+            1 | 2 => 3,
+            3 => 4,
+            _ => return Err("unknown geometry type"),
+        };
+        match kind % 1000 {
+            1 => self.point(little_endian, dimensions, bounds),
+            2 => self.points(little_endian, dimensions, bounds),
+            3 => {
+                for _ in 0..self.u32(little_endian)? {
+                    self.points(little_endian, dimensions, bounds)?;
+                }
+                Ok(())
+            }
+            4..=7 => {
+                for _ in 0..self.u32(little_endian)? {
+                    self.geometry(bounds, depth + 1)?;
+                }
+                Ok(())
+            }
+            _ => Err("unknown geometry type"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wkb_bounds, wkt_bounds};
+    use crate::{Aabb, KdTree, PayloadCell};
+
+    #[test]
+    fn wkt_and_wkb_import() {
+        assert_eq!(
+            wkt_bounds("POLYGON ((30 10, 40 40, 20 40, 10 20, 30 10))"),
+            Ok(Some(Aabb::new(10., 40., 10., 40.)))
+        );
+        assert_eq!(
+            wkt_bounds("SRID=4326;GEOMETRYCOLLECTION (POINT Z (4 6 1), LINESTRING EMPTY, LINESTRING (-1.5 2, 3 -7e1))"),
+            Ok(Some(Aabb::new(-1.5, 4., -70., 6.)))
+        );
+        assert_eq!(wkt_bounds("POINT EMPTY"), Ok(None));
+        assert!(wkt_bounds("POINT (1 2").is_err());
+        assert!(wkt_bounds("POINT (1, 2)").is_err());
+        //a little endian linestring, then a big endian multipoint with Z
+        let mut line = vec![1, 2, 0, 0, 0, 2, 0, 0, 0];
+        for coordinate in &[1f64, 2., -3., 4.] {
+            line.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        assert_eq!(wkb_bounds(&line), Ok(Some(Aabb::new(-3., 1., 2., 4.))));
+        let mut multi = vec![0, 0, 0, 0x03, 0xec, 0, 0, 0, 1, 0, 0, 0, 0x03, 0xe9];
+        for coordinate in &[5f64, 6., 7.] {
+            multi.extend_from_slice(&coordinate.to_be_bytes());
+        }
+        assert_eq!(wkb_bounds(&multi), Ok(Some(Aabb::new(5., 5., 6., 6.))));
+        assert!(wkb_bounds(&line[..20]).is_err());
+        //collections of a single collection, nested too deep
+        let nested: Vec<u8> = [1, 7, 0, 0, 0, 1, 0, 0, 0].repeat(100_000);
+        assert_eq!(
+            wkb_bounds(&nested),
+            Err("geometry collections nested too deep")
+        );
+
+        let tree = KdTree::<PayloadCell<Aabb<f64>, String>, 4>::from_wkt(vec![
+            "POINT (1 1)",
+            "LINESTRING (0 0, 10 10)",
+            "POINT EMPTY",
+            "POLYGON ((20 20, 30 20, 30 30, 20 20))",
+        ])
+        .unwrap();
+        assert_eq!(tree.len(), 3);
+        let hits: Vec<_> = tree
+            .query_point(5., 5.)
+            .map(|value| value.payload().clone())
+            .collect();
+        assert_eq!(hits, vec!["LINESTRING (0 0, 10 10)".to_string()]);
+        let error =
+            KdTree::<PayloadCell<Aabb<f64>, String>, 4>::from_wkt(vec!["POINT (1 1)", "POINT (1"])
+                .unwrap_err();
+        assert_eq!(error.index, 1);
+        let tree =
+            KdTree::<PayloadCell<Aabb<f64>, Vec<u8>>, 4>::from_wkb(vec![line, multi]).unwrap();
+        assert_eq!(tree.query_rect(0., 6., 3., 7.).count(), 2);
+    }
+}
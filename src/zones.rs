@@ -0,0 +1,145 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use crate::{Aabb, KdValue, SpatialIndex2D};
+
+/// Identifies a zone registered in `TriggerZones`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ZoneId(usize);
+
+/// A value entering or leaving a zone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ZoneEvent<Id> {
+    Entered(ZoneId, Id),
+    Left(ZoneId, Id),
+}
+
+/// Persistent rectangles reporting the values entering and leaving them at each
+/// tick, like trigger volumes.
+///
+/// Values are identified by the `id` closure, and are inside a zone as long as
+/// they overlap it.
+#[derive(Debug, Clone)]
+pub struct TriggerZones<P, Id, F> {
+    id: F,
+    next_zone: usize,
+    zones: BTreeMap<ZoneId, Zone<P, Id>>,
+}
+
+#[derive(Debug, Clone)]
+struct Zone<P, Id> {
+    bounds: Aabb<P>,
+    inside: BTreeSet<Id>,
+}
+
+impl<P: PartialOrd + Clone, Id: Ord + Clone, F> TriggerZones<P, Id, F> {
+    pub fn new<Value>(id: F) -> Self
+    where
+        F: Fn(&Value) -> Id,
+    {
+        Self {
+            id,
+            next_zone: 0,
+            zones: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a zone, empty until the next tick.
+    pub fn add_zone(&mut self, bounds: Aabb<P>) -> ZoneId {
+        let zone = ZoneId(self.next_zone);
+        self.next_zone += 1;
+        self.zones.insert(
+            zone,
+            Zone {
+                bounds,
+                inside: BTreeSet::new(),
+            },
+        );
+        zone
+    }
+
+    /// Unregisters a zone, without reporting the values inside as leaving.
+    pub fn remove_zone(&mut self, zone: ZoneId) -> Option<Aabb<P>> {
+        self.zones.remove(&zone).map(|zone| zone.bounds)
+    }
+
+    /// Moves a zone; the values entering and leaving it are reported at the next
+    /// tick.
+    pub fn move_zone(&mut self, zone: ZoneId, bounds: Aabb<P>) -> bool {
+        match self.zones.get_mut(&zone) {
+            Some(zone) => {
+                zone.bounds = bounds;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn zone_bounds(&self, zone: ZoneId) -> Option<&Aabb<P>> {
+        self.zones.get(&zone).map(|zone| &zone.bounds)
+    }
+
+    /// The ids of the values inside the zone as of the last tick.
+    pub fn inside(&self, zone: ZoneId) -> impl Iterator<Item = &Id> {
+        self.zones
+            .get(&zone)
+            .into_iter()
+            .flat_map(|zone| zone.inside.iter())
+    }
+
+    /// Queries every zone in `index`, and returns the changes since the previous
+    /// tick, zone by zone: values that left first, then values that entered.
+    pub fn tick<Index>(&mut self, index: &Index) -> Vec<ZoneEvent<Id>>
+    where
+        Index: SpatialIndex2D,
+        Index::Value: KdValue<Position = P>,
+        F: Fn(&Index::Value) -> Id,
+    {
+        let mut events = Vec::new();
+        for (&zone_id, zone) in &mut self.zones {
+            let bounds = zone.bounds.clone();
+            let inside: BTreeSet<Id> = index
+                .query_rect(bounds.min_x, bounds.max_x, bounds.min_y, bounds.max_y)
+                .map(&self.id)
+                .collect();
+            events.extend(
+                zone.inside
+                    .difference(&inside)
+                    .map(|id| ZoneEvent::Left(zone_id, id.clone())),
+            );
+            events.extend(
+                inside
+                    .difference(&zone.inside)
+                    .map(|id| ZoneEvent::Entered(zone_id, id.clone())),
+            );
+            zone.inside = inside;
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TriggerZones, ZoneEvent};
+    use crate::{tests::TestValue, Aabb, KdTree};
+
+    #[test]
+    fn trigger_zones() {
+        let mut tree = KdTree::<TestValue, 4>::default();
+        let mut zones = TriggerZones::new(|value: &TestValue| value.min_y as i32);
+        let door = zones.add_zone(Aabb::new(10., 12., 0., 100.));
+        let player = TestValue::new(0., 1., 7., 8.);
+        tree.insert(player.clone());
+        assert!(zones.tick(&tree).is_empty());
+        tree.remove_one(player);
+        let player = TestValue::new(9.5, 10.5, 7., 8.);
+        tree.insert(player.clone());
+        assert_eq!(zones.tick(&tree), vec![ZoneEvent::Entered(door, 7)]);
+        assert_eq!(zones.inside(door).collect::<Vec<_>>(), vec![&7]);
+        tree.remove_one(player);
+        tree.insert(TestValue::new(12.5, 13.5, 7., 8.));
+        assert_eq!(zones.tick(&tree), vec![ZoneEvent::Left(door, 7)]);
+    }
+}